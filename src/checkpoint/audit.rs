@@ -0,0 +1,174 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Audit of the bottom-up checkpoints committed by a subnet.
+//!
+//! The committed checkpoints form a chain where each checkpoint points to the previous one through
+//! `prev_check`. The audit walks that chain and reports the gaps and breaks in it.
+
+use crate::checkpoint::proof::verify_proof;
+use crate::lotus::LotusClient;
+use fvm_shared::clock::ChainEpoch;
+use ipc_gateway::BottomUpCheckpoint;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An inconsistency found in the chain of committed checkpoints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditIssue {
+    /// The epoch of the checkpoint is not a multiple of the checkpoint period.
+    MisalignedEpoch { epoch: ChainEpoch },
+    /// More than one checkpoint was committed in the epoch.
+    DuplicateEpoch { epoch: ChainEpoch },
+    /// No checkpoint was committed for the periods between the two epochs.
+    Gap {
+        after_epoch: ChainEpoch,
+        before_epoch: ChainEpoch,
+    },
+    /// The `prev_check` of the checkpoint does not match the hash of the previous checkpoint.
+    BrokenLink {
+        epoch: ChainEpoch,
+        expected: String,
+        actual: String,
+    },
+    /// The proof of the checkpoint cannot be decoded or does not match the child chain.
+    InvalidProof { epoch: ChainEpoch, reason: String },
+}
+
+/// The result of auditing the checkpoints committed by a subnet in a range of epochs.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditReport {
+    pub subnet_id: String,
+    pub from_epoch: ChainEpoch,
+    pub to_epoch: ChainEpoch,
+    pub period: ChainEpoch,
+    /// The number of committed checkpoints in the range.
+    pub checkpoints: usize,
+    /// Whether the proofs were checked against the child chain. They are not if the child subnet
+    /// is not in the config of the agent.
+    pub proofs_verified: bool,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+    /// Returns true if no issues were found.
+    pub fn is_intact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Checks the epochs and the `prev_check` links of the committed checkpoints. The checkpoints do
+/// not need to be sorted.
+pub fn audit_chain(checkpoints: &[BottomUpCheckpoint], period: ChainEpoch) -> Vec<AuditIssue> {
+    let mut sorted = checkpoints.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|c| c.data.epoch);
+
+    let mut issues = vec![];
+    for c in sorted.iter() {
+        if period > 0 && c.data.epoch % period != 0 {
+            issues.push(AuditIssue::MisalignedEpoch {
+                epoch: c.data.epoch,
+            });
+        }
+    }
+
+    for pair in sorted.windows(2) {
+        let (prev, cur) = (pair[0], pair[1]);
+
+        if prev.data.epoch == cur.data.epoch {
+            issues.push(AuditIssue::DuplicateEpoch {
+                epoch: cur.data.epoch,
+            });
+            continue;
+        }
+        if cur.data.epoch - prev.data.epoch > period {
+            issues.push(AuditIssue::Gap {
+                after_epoch: prev.data.epoch,
+                before_epoch: cur.data.epoch,
+            });
+        }
+
+        let expected = prev.cid();
+        let actual = cur.data.prev_check.cid();
+        if expected != actual {
+            issues.push(AuditIssue::BrokenLink {
+                epoch: cur.data.epoch,
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Verifies the proofs of the checkpoints against the child chain. `client` must point to the child
+/// subnet.
+pub async fn audit_proofs<L: LotusClient>(
+    client: &L,
+    checkpoints: &[BottomUpCheckpoint],
+) -> Vec<AuditIssue> {
+    let mut issues = vec![];
+    for c in checkpoints {
+        if let Err(e) = verify_proof(client, c).await {
+            issues.push(AuditIssue::InvalidProof {
+                epoch: c.data.epoch,
+                reason: e.to_string(),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::audit::{audit_chain, AuditIssue};
+    use fvm_shared::clock::ChainEpoch;
+    use ipc_gateway::{BottomUpCheckpoint, SubnetID};
+    use primitives::TCid;
+
+    fn checkpoint(epoch: ChainEpoch, prev: Option<&BottomUpCheckpoint>) -> BottomUpCheckpoint {
+        let mut c = BottomUpCheckpoint::new(SubnetID::new_root(123), epoch);
+        if let Some(prev) = prev {
+            c.data.prev_check = TCid::from(prev.cid());
+        }
+        c
+    }
+
+    #[test]
+    fn test_intact_chain() {
+        let c10 = checkpoint(10, None);
+        let c20 = checkpoint(20, Some(&c10));
+        let c30 = checkpoint(30, Some(&c20));
+
+        assert!(audit_chain(&[c30, c10, c20], 10).is_empty());
+    }
+
+    #[test]
+    fn test_chain_issues() {
+        let c10 = checkpoint(10, None);
+        let c20 = checkpoint(20, Some(&c10));
+        // the checkpoint at 30 is missing and 40 points to 10 instead of 20
+        let c40 = checkpoint(40, Some(&c10));
+        let c45 = checkpoint(45, Some(&c40));
+
+        let (c10_cid, c20_cid) = (c10.cid(), c20.cid());
+
+        let issues = audit_chain(&[c10, c20, c40, c45], 10);
+        assert_eq!(
+            issues,
+            vec![
+                AuditIssue::MisalignedEpoch { epoch: 45 },
+                AuditIssue::Gap {
+                    after_epoch: 20,
+                    before_epoch: 40
+                },
+                AuditIssue::BrokenLink {
+                    epoch: 40,
+                    expected: c20_cid.to_string(),
+                    actual: c10_cid.to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Caps the top-down messages included in a single checkpoint.
+//!
+//! After a burst of cross-net messages, a checkpoint carrying all of them could exceed the message
+//! or gas limits of the child and never be executed. The messages are instead batched in nonce
+//! order, each checkpoint carrying the next batch after the one of the previous checkpoint, or from
+//! the nonce applied by the child if the previous checkpoint is already executed. The validators
+//! have to vote the same checkpoints, so the batches only depend on the chain state and on caps
+//! that are the same for every validator.
+
+use anyhow::Result;
+use fil_actors_runtime::cbor;
+use fvm_shared::clock::ChainEpoch;
+use ipc_gateway::CrossMsg;
+
+/// The maximum number of top-down messages in a single checkpoint.
+pub const MAX_TOP_DOWN_MSGS: usize = 100;
+/// The maximum encoded size of the top-down messages in a single checkpoint, well below the 64KiB
+/// limit of an fvm message.
+pub const MAX_TOP_DOWN_MSGS_BYTES: usize = 32 * 1024;
+
+/// A batch of top-down messages and the number of messages left for the following checkpoints.
+pub(crate) struct TopDownBatch {
+    pub(crate) msgs: Vec<CrossMsg>,
+    pub(crate) backlog: usize,
+}
+
+/// Selects the messages of the next checkpoint, i.e. consecutive nonces starting at `nonce`, up to
+/// `max_msgs` messages and `max_bytes` encoded bytes. The first message is always included, even if
+/// it is bigger than `max_bytes`, so that the batches keep on moving.
+pub(crate) fn batch_top_down_msgs(
+    mut msgs: Vec<CrossMsg>,
+    nonce: u64,
+    max_msgs: usize,
+    max_bytes: usize,
+) -> Result<TopDownBatch> {
+    msgs.retain(|m| m.msg.nonce >= nonce);
+    msgs.sort_by_key(|m| m.msg.nonce);
+    msgs.dedup_by_key(|m| m.msg.nonce);
+
+    // messages after a gap in the nonces cannot be applied yet.
+    let pending = msgs
+        .iter()
+        .zip(nonce..)
+        .take_while(|(m, expected)| m.msg.nonce == *expected)
+        .count();
+    if pending < msgs.len() {
+        log::warn!(
+            "top down messages have a gap after nonce {:}, leaving {:} messages out",
+            nonce + pending as u64,
+            msgs.len() - pending
+        );
+    }
+    msgs.truncate(pending);
+
+    let mut size = 0;
+    let mut count = 0;
+    for m in msgs.iter().take(max_msgs) {
+        let msg_size = cbor::serialize(m, "top down cross msg")?.len();
+        if count > 0 && size + msg_size > max_bytes {
+            break;
+        }
+        if msg_size > max_bytes {
+            log::warn!(
+                "top down message with nonce {:} is {msg_size:} bytes, above the limit of {max_bytes:}",
+                m.msg.nonce
+            );
+        }
+        size += msg_size;
+        count += 1;
+    }
+
+    let backlog = msgs.len() - count;
+    msgs.truncate(count);
+    Ok(TopDownBatch { msgs, backlog })
+}
+
+/// Tracks the backlog of top-down messages left after each checkpoint, to report when it is not
+/// shrinking.
+#[derive(Default)]
+pub(crate) struct BacklogTracker {
+    /// The epoch of the last checkpoint and its backlog.
+    last: Option<(ChainEpoch, usize)>,
+    /// The number of consecutive checkpoints the backlog did not shrink in.
+    stalled: u32,
+}
+
+impl BacklogTracker {
+    /// Records the backlog left by the checkpoint at `epoch`. Returns the number of consecutive
+    /// checkpoints the backlog did not shrink in. The validators voting the same checkpoint share
+    /// the same record.
+    pub(crate) fn record(&mut self, epoch: ChainEpoch, backlog: usize) -> u32 {
+        match self.last {
+            Some((last_epoch, _)) if epoch <= last_epoch => return self.stalled,
+            Some((_, last_backlog)) if backlog > 0 && backlog >= last_backlog => self.stalled += 1,
+            _ => self.stalled = 0,
+        }
+        self.last = Some((epoch, backlog));
+        self.stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::batch::{batch_top_down_msgs, BacklogTracker};
+    use fil_actors_runtime::cbor;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use ipc_gateway::{CrossMsg, StorableMsg};
+    use ipc_sdk::address::IPCAddress;
+    use ipc_sdk::subnet_id::SubnetID;
+
+    fn cross_msg(nonce: u64) -> CrossMsg {
+        let subnet = SubnetID::new_root(123);
+        CrossMsg {
+            msg: StorableMsg {
+                from: IPCAddress::new(&subnet, &Address::new_id(1000)).unwrap(),
+                to: IPCAddress::new(&subnet, &Address::new_id(1001)).unwrap(),
+                method: 0,
+                params: RawBytes::default(),
+                value: TokenAmount::from_atto(1),
+                nonce,
+            },
+            wrapped: false,
+        }
+    }
+
+    fn nonces(msgs: &[CrossMsg]) -> Vec<u64> {
+        msgs.iter().map(|m| m.msg.nonce).collect()
+    }
+
+    #[test]
+    fn test_batch_by_count() {
+        let msgs = (0..5).rev().map(cross_msg).collect();
+        let batch = batch_top_down_msgs(msgs, 1, 2, usize::MAX).unwrap();
+        assert_eq!(nonces(&batch.msgs), vec![1, 2]);
+        assert_eq!(batch.backlog, 2);
+    }
+
+    #[test]
+    fn test_batch_by_size() {
+        let size = cbor::serialize(&cross_msg(0), "").unwrap().len();
+        let msgs = (0..5).map(cross_msg).collect::<Vec<_>>();
+
+        let batch = batch_top_down_msgs(msgs.clone(), 0, 10, 3 * size).unwrap();
+        assert_eq!(nonces(&batch.msgs), vec![0, 1, 2]);
+        assert_eq!(batch.backlog, 2);
+
+        // an oversized message is still submitted on its own
+        let batch = batch_top_down_msgs(msgs, 0, 10, size - 1).unwrap();
+        assert_eq!(nonces(&batch.msgs), vec![0]);
+        assert_eq!(batch.backlog, 4);
+    }
+
+    #[test]
+    fn test_batch_stops_at_nonce_gap() {
+        let msgs = [0, 1, 3, 4].into_iter().map(cross_msg).collect();
+        let batch = batch_top_down_msgs(msgs, 0, 10, usize::MAX).unwrap();
+        assert_eq!(nonces(&batch.msgs), vec![0, 1]);
+        assert_eq!(batch.backlog, 0);
+    }
+
+    #[test]
+    fn test_backlog_tracker() {
+        let mut tracker = BacklogTracker::default();
+        assert_eq!(tracker.record(10, 5), 0);
+        // other validators voting the same checkpoint
+        assert_eq!(tracker.record(10, 5), 0);
+        assert_eq!(tracker.record(20, 5), 1);
+        assert_eq!(tracker.record(30, 6), 2);
+        assert_eq!(tracker.record(40, 3), 0);
+        assert_eq!(tracker.record(50, 0), 0);
+        assert_eq!(tracker.record(60, 0), 0);
+    }
+}
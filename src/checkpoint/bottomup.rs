@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: MIT
 //! Bottom up checkpoint manager
 
-use crate::checkpoint::{CheckpointManager, CheckpointMetadata, CheckpointQuery};
+use crate::checkpoint::{
+    CheckpointDirection, CheckpointManager, CheckpointMetadata, CheckpointQuery, SubmissionQuery,
+};
 use crate::config::Subnet;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -32,12 +34,16 @@ pub struct NativeBottomUpCheckpoint {
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct NativeChildCheck {
     pub source: SubnetID,
+    /// The bytes of the cids of the child checkpoints. The hashes kept in the evm contracts are
+    /// converted into keccak-256 cids.
     pub checks: Vec<Vec<u8>>,
 }
 
 /// The trait that handles the bottom up checkpoint submission data preparation and actual submission.
 #[async_trait]
-pub trait BottomUpHandler: Send + Sync + CheckpointQuery<NativeBottomUpCheckpoint> {
+pub trait BottomUpHandler:
+    Send + Sync + CheckpointQuery<NativeBottomUpCheckpoint> + SubmissionQuery
+{
     /// Fetch the checkpoint template at the specified epoch
     async fn checkpoint_template(&self, epoch: ChainEpoch) -> Result<NativeBottomUpCheckpoint>;
     /// Populate previous checkpoint hash for the checkpoint
@@ -49,12 +55,20 @@ pub trait BottomUpHandler: Send + Sync + CheckpointQuery<NativeBottomUpCheckpoin
     ) -> Result<()>;
     /// Populate the proof for the checkpoint
     async fn populate_proof(&self, template: &mut NativeBottomUpCheckpoint) -> Result<()>;
-    /// Submit the checkpoint for validator
+    /// Submit the checkpoint for validator, returns the message cid or transaction hash
     async fn submit(
         &self,
         validator: &Address,
         checkpoint: NativeBottomUpCheckpoint,
-    ) -> Result<ChainEpoch>;
+    ) -> Result<String>;
+}
+
+/// Builds the bottom-up checkpoints of a subnet without submitting them.
+#[async_trait]
+pub trait BottomUpCheckpointBuilder: Display + Send + Sync {
+    /// Builds the checkpoint of the epoch with the same pipeline used before submitting it, i.e.
+    /// the template with its proof and the hash of the previous checkpoint.
+    async fn build_checkpoint(&self, epoch: ChainEpoch) -> Result<NativeBottomUpCheckpoint>;
 }
 
 pub struct BottomUpManager<P, C> {
@@ -93,6 +107,25 @@ impl<P: BottomUpHandler, C: BottomUpHandler> Display for BottomUpManager<P, C> {
     }
 }
 
+#[async_trait]
+impl<P: BottomUpHandler, C: BottomUpHandler> BottomUpCheckpointBuilder for BottomUpManager<P, C> {
+    async fn build_checkpoint(&self, epoch: ChainEpoch) -> Result<NativeBottomUpCheckpoint> {
+        let mut template = self.child_handler.checkpoint_template(epoch).await?;
+        log::debug!("bottom up template: {template:?}");
+
+        self.child_handler.populate_proof(&mut template).await?;
+        log::debug!("bottom up checkpoint proof: {:?}", template.proof);
+
+        let prev_epoch = epoch - self.metadata.period;
+        self.parent_handler
+            .populate_prev_hash(&mut template, &self.metadata.child.id, prev_epoch)
+            .await?;
+        log::debug!("bottom up checkpoint prev check: {:?}", template.prev_check);
+
+        Ok(template)
+    }
+}
+
 #[async_trait]
 impl<P: BottomUpHandler, C: BottomUpHandler> CheckpointManager for BottomUpManager<P, C> {
     /// Get the subnet config that this manager is submitting checkpoints to. For example, if it is
@@ -112,6 +145,16 @@ impl<P: BottomUpHandler, C: BottomUpHandler> CheckpointManager for BottomUpManag
         &self.metadata.child
     }
 
+    /// Get the subnet config whose chain drives the submissions, i.e. the subnet `current_epoch`
+    /// is read from.
+    fn source_subnet(&self) -> &Subnet {
+        &self.metadata.child
+    }
+
+    fn direction(&self) -> CheckpointDirection {
+        CheckpointDirection::BottomUp
+    }
+
     /// The checkpoint period that the current manager is submitting upon
     fn checkpoint_period(&self) -> ChainEpoch {
         self.metadata.period
@@ -124,6 +167,12 @@ impl<P: BottomUpHandler, C: BottomUpHandler> CheckpointManager for BottomUpManag
             .await
     }
 
+    async fn validator_set_version(&self) -> Result<u64> {
+        self.parent_handler
+            .validator_set_version(&self.metadata.child.id)
+            .await
+    }
+
     /// Obtain the last executed epoch of the checkpoint submission
     async fn last_executed_epoch(&self) -> Result<ChainEpoch> {
         self.parent_handler
@@ -138,27 +187,19 @@ impl<P: BottomUpHandler, C: BottomUpHandler> CheckpointManager for BottomUpManag
 
     /// Submit the checkpoint based on the current epoch to submit and the previous epoch that was
     /// already submitted.
-    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<()> {
-        let mut template = self.child_handler.checkpoint_template(epoch).await?;
-        log::debug!("bottom up template: {template:?}");
-
-        self.child_handler.populate_proof(&mut template).await?;
-        log::debug!("bottom up checkpoint proof: {:?}", template.proof);
-
-        let prev_epoch = epoch - self.metadata.period;
-        self.parent_handler
-            .populate_prev_hash(&mut template, &self.metadata.child.id, prev_epoch)
-            .await?;
-        log::debug!("bottom up checkpoint prev check: {:?}", template.prev_check);
-
+    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<String> {
+        let template = self.build_checkpoint(epoch).await?;
         log::info!("bottom up checkpoint to submit: {template:?}");
 
         self.parent_handler
             .submit(validator, template)
             .await
-            .map_err(|e| anyhow!("cannot submit bottom up checkpoint due to: {e:}"))?;
+            .map_err(|e| anyhow!("cannot submit bottom up checkpoint due to: {e:}"))
+    }
 
-        Ok(())
+    /// Waits for a submission returned by `submit_checkpoint` to be executed.
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch> {
+        self.parent_handler.wait_submission(submission).await
     }
 
     /// Checks if the validator has already submitted in the epoch
@@ -0,0 +1,196 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Watches the chain heads of the subnets to wake up the checkpoint managers on new blocks.
+//!
+//! New heads are received through `Filecoin.ChainNotify` in fvm subnets and
+//! `eth_subscribe("newHeads")` in fevm subnets. If the node does not support websocket
+//! subscriptions, the watcher falls back to polling the chain head.
+
+use crate::config::subnet::NetworkType;
+use crate::config::Subnet;
+use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl, NO_PARAMS};
+use crate::metrics;
+use anyhow::{anyhow, Result};
+use fvm_shared::clock::ChainEpoch;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::time::sleep;
+use url::Url;
+
+/// The interval to poll the chain head when subscriptions are not available.
+const HEAD_POLLING_INTERVAL: Duration = Duration::from_secs(1);
+/// The time to keep polling before attempting to subscribe to new heads again.
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(60);
+
+const LOTUS_CHAIN_NOTIFY: &str = "Filecoin.ChainNotify";
+const LOTUS_CHAIN_HEAD: &str = "Filecoin.ChainHead";
+const ETH_SUBSCRIBE: &str = "eth_subscribe";
+const ETH_BLOCK_NUMBER: &str = "eth_blockNumber";
+
+/// Spawns a task that tracks the latest epoch of the subnet. The task stops once all the
+/// receivers of the returned channel are dropped.
+pub(crate) fn watch_chain_head(subnet: &Subnet) -> watch::Receiver<ChainEpoch> {
+    let (tx, rx) = watch::channel(0);
+    tokio::spawn(run_head_watcher(subnet.clone(), tx));
+    rx
+}
+
+async fn run_head_watcher(subnet: Subnet, tx: watch::Sender<ChainEpoch>) {
+    let backend = match subnet.network_type() {
+        NetworkType::Fvm => metrics::LOTUS_BACKEND,
+        NetworkType::Fevm => metrics::EVM_BACKEND,
+    };
+    let client = JsonRpcClientImpl::new(subnet.rpc_http().clone(), subnet.auth_token().as_deref())
+        .with_metrics_backend(backend);
+
+    loop {
+        match subscribe_heads(&subnet).await {
+            Ok(chan) => {
+                log::info!("subscribed to new heads of subnet: {:}", subnet.id);
+                loop {
+                    tokio::select! {
+                        _ = tx.closed() => return,
+                        r = chan.recv() => match r {
+                            Ok(v) => {
+                                if let Some(epoch) = parse_head_epoch(subnet.network_type(), &v) {
+                                    update_epoch(&tx, epoch);
+                                }
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                }
+                log::warn!("new heads subscription closed for subnet: {:}", subnet.id);
+            }
+            Err(e) => {
+                log::warn!(
+                    "cannot subscribe to new heads of subnet: {:} due to {e:}, polling instead",
+                    subnet.id
+                );
+            }
+        }
+
+        let start = Instant::now();
+        while start.elapsed() < RESUBSCRIBE_INTERVAL {
+            if tx.is_closed() {
+                return;
+            }
+            match poll_head(&client, subnet.network_type()).await {
+                Ok(epoch) => update_epoch(&tx, epoch),
+                Err(e) => log::warn!(
+                    "cannot poll chain head of subnet: {:} due to {e:}",
+                    subnet.id
+                ),
+            }
+            sleep(HEAD_POLLING_INTERVAL).await;
+        }
+    }
+}
+
+fn update_epoch(tx: &watch::Sender<ChainEpoch>, epoch: ChainEpoch) {
+    tx.send_if_modified(|current| {
+        if epoch > *current {
+            *current = epoch;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+async fn subscribe_heads(subnet: &Subnet) -> Result<async_channel::Receiver<Value>> {
+    let client = JsonRpcClientImpl::new(ws_url(subnet.rpc_http())?, subnet.auth_token().as_deref());
+    match subnet.network_type() {
+        NetworkType::Fvm => client.subscribe(LOTUS_CHAIN_NOTIFY, NO_PARAMS).await,
+        NetworkType::Fevm => client.subscribe(ETH_SUBSCRIBE, json!(["newHeads"])).await,
+    }
+}
+
+async fn poll_head(client: &JsonRpcClientImpl, network_type: NetworkType) -> Result<ChainEpoch> {
+    match network_type {
+        NetworkType::Fvm => {
+            let head = client.request::<Value>(LOTUS_CHAIN_HEAD, NO_PARAMS).await?;
+            head["Height"]
+                .as_i64()
+                .ok_or_else(|| anyhow!("chain head has no height"))
+        }
+        NetworkType::Fevm => {
+            let number = client
+                .request::<String>(ETH_BLOCK_NUMBER, NO_PARAMS)
+                .await?;
+            parse_hex_epoch(&number).ok_or_else(|| anyhow!("invalid block number: {number:}"))
+        }
+    }
+}
+
+/// Builds the websocket url of the node from its http endpoint.
+fn ws_url(http_url: &Url) -> Result<Url> {
+    let mut url = http_url.clone();
+    let scheme = match url.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        s => s,
+    }
+    .to_string();
+    url.set_scheme(&scheme)
+        .map_err(|_| anyhow!("cannot convert {http_url:} to a websocket url"))?;
+    Ok(url)
+}
+
+/// Extracts the epoch of the new head from a subscription notification. Returns `None` for the
+/// messages that are not new heads, such as the response to the subscription request.
+fn parse_head_epoch(network_type: NetworkType, notification: &Value) -> Option<ChainEpoch> {
+    match network_type {
+        // {"method":"xrpc.ch.val","params":[<chan id>,[{"Type":"apply","Val":{"Height":10,..}}]]}
+        NetworkType::Fvm => notification["params"][1]
+            .as_array()?
+            .iter()
+            .filter(|change| change["Type"] != "revert")
+            .filter_map(|change| change["Val"]["Height"].as_i64())
+            .max(),
+        // {"method":"eth_subscription","params":{"subscription":"0x..","result":{"number":"0x1b4",..}}}
+        NetworkType::Fevm => parse_hex_epoch(notification["params"]["result"]["number"].as_str()?),
+    }
+}
+
+fn parse_hex_epoch(number: &str) -> Option<ChainEpoch> {
+    ChainEpoch::from_str_radix(number.trim_start_matches("0x"), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::head::{parse_head_epoch, ws_url};
+    use crate::config::subnet::NetworkType;
+    use serde_json::json;
+    use url::Url;
+
+    #[test]
+    fn test_parse_head_epoch() {
+        let lotus = json!({
+            "jsonrpc": "2.0",
+            "method": "xrpc.ch.val",
+            "params": [1, [{"Type": "apply", "Val": {"Cids": [], "Blocks": [], "Height": 10}}]]
+        });
+        assert_eq!(parse_head_epoch(NetworkType::Fvm, &lotus), Some(10));
+
+        let subscribed = json!({"jsonrpc": "2.0", "id": 1, "result": 1});
+        assert_eq!(parse_head_epoch(NetworkType::Fvm, &subscribed), None);
+
+        let eth = json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": {"subscription": "0x9ce59a13", "result": {"number": "0x1b4"}}
+        });
+        assert_eq!(parse_head_epoch(NetworkType::Fevm, &eth), Some(436));
+    }
+
+    #[test]
+    fn test_ws_url() {
+        let url = Url::parse("https://api.node.glif.io/rpc/v1").unwrap();
+        assert_eq!(
+            ws_url(&url).unwrap().as_str(),
+            "wss://api.node.glif.io/rpc/v1"
+        );
+    }
+}
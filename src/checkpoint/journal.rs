@@ -0,0 +1,298 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Persistent journal of the checkpoint submissions.
+//!
+//! Every submission sent by a checkpoint manager is recorded with its message cid or transaction
+//! hash before waiting for its execution. After a restart, the journal lets the agent wait for the
+//! submissions that were already in flight instead of sending them again.
+
+use crate::checkpoint::{CheckpointDirection, CheckpointManager};
+use crate::config::ReloadableConfig;
+use anyhow::{anyhow, Result};
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// The name of the journal file in the agent repo directory.
+pub const JOURNAL_FILE_NAME: &str = "checkpoint_journal.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionStatus {
+    /// The submission was sent, but its execution has not been confirmed yet.
+    Pending,
+    /// The submission was executed or the validator's vote is already on chain.
+    Confirmed,
+    /// The submission could not be confirmed and should be sent again.
+    Failed,
+}
+
+/// Identifies the checkpoint manager of the submissions in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ManagerKey {
+    pub direction: CheckpointDirection,
+    pub parent: String,
+    pub child: String,
+}
+
+impl ManagerKey {
+    pub fn new(manager: &dyn CheckpointManager) -> Self {
+        Self {
+            direction: manager.direction(),
+            parent: manager.parent_subnet().id.to_string(),
+            child: manager.child_subnet().id.to_string(),
+        }
+    }
+}
+
+/// A single checkpoint submission of a validator in an epoch for a manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+    #[serde(flatten)]
+    pub manager: ManagerKey,
+    pub epoch: ChainEpoch,
+    pub validator: String,
+    /// The message cid or transaction hash of the submission.
+    pub submission: String,
+    pub status: SubmissionStatus,
+    /// Unix timestamp in seconds of the latest status change.
+    pub updated_at: u64,
+}
+
+type JournalKey = (ManagerKey, ChainEpoch, String);
+
+/// The on-disk journal of the checkpoint submissions. Every update is persisted by writing the
+/// whole journal to a temporary file that replaces the previous one, so a crash never leaves a
+/// partially written journal behind. The file is written off the async runtime, while holding the
+/// lock of the records so that the updates reach the disk in order.
+pub struct CheckpointJournal {
+    path: PathBuf,
+    records: Mutex<HashMap<JournalKey, SubmissionRecord>>,
+}
+
+impl CheckpointJournal {
+    /// Loads the journal stored in `path`, or creates an empty one if the file does not exist.
+    /// Records that cannot be parsed, e.g. written by an older version of the agent, are dropped.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let records = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let records: Vec<Value> = serde_json::from_str(&content)
+                .map_err(|e| anyhow!("cannot parse checkpoint journal {path:?}: {e:}"))?;
+            records
+                .into_iter()
+                .filter_map(|r| match serde_json::from_value::<SubmissionRecord>(r) {
+                    Ok(r) => Some(r),
+                    Err(e) => {
+                        log::warn!("dropping checkpoint journal record in {path:?}: {e:}");
+                        None
+                    }
+                })
+                .map(|r| ((r.manager.clone(), r.epoch, r.validator.clone()), r))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            path,
+            records: Mutex::new(records),
+        })
+    }
+
+    /// Loads the journal from the repo directory of the agent config.
+    pub fn from_config(config: &ReloadableConfig) -> Result<Self> {
+        let repo = config
+            .get_config_repo()
+            .ok_or_else(|| anyhow!("No repo found in config to store the checkpoint journal"))?;
+        Self::load(Path::new(&repo).join(JOURNAL_FILE_NAME))
+    }
+
+    /// Returns the submission of the validator in the epoch for the manager, if any.
+    pub async fn get(
+        &self,
+        manager: &ManagerKey,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Option<SubmissionRecord> {
+        let records = self.records.lock().await;
+        records.get(&key(manager, epoch, validator)).cloned()
+    }
+
+    /// Records a new submission of the validator in the epoch as pending.
+    pub async fn record_pending(
+        &self,
+        manager: &ManagerKey,
+        epoch: ChainEpoch,
+        validator: &Address,
+        submission: String,
+    ) -> Result<()> {
+        let mut records = self.records.lock().await;
+        records.insert(
+            key(manager, epoch, validator),
+            SubmissionRecord {
+                manager: manager.clone(),
+                epoch,
+                validator: validator.to_string(),
+                submission,
+                status: SubmissionStatus::Pending,
+                updated_at: now(),
+            },
+        );
+        self.persist(&records).await
+    }
+
+    /// Updates the status of an existing submission. It is a noop if there is no submission
+    /// recorded for the validator in the epoch.
+    pub async fn update_status(
+        &self,
+        manager: &ManagerKey,
+        epoch: ChainEpoch,
+        validator: &Address,
+        status: SubmissionStatus,
+    ) -> Result<()> {
+        let mut records = self.records.lock().await;
+        match records.get_mut(&key(manager, epoch, validator)) {
+            Some(r) if r.status != status => {
+                r.status = status;
+                r.updated_at = now();
+            }
+            _ => return Ok(()),
+        }
+        self.persist(&records).await
+    }
+
+    /// Removes the submissions of the manager up to `last_executed_epoch`, they are no longer
+    /// needed as the checkpoints in those epochs have already been executed.
+    pub async fn prune(&self, manager: &ManagerKey, last_executed_epoch: ChainEpoch) -> Result<()> {
+        let mut records = self.records.lock().await;
+        let before = records.len();
+        records.retain(|(m, epoch, _), _| m != manager || *epoch > last_executed_epoch);
+        if records.len() == before {
+            return Ok(());
+        }
+        self.persist(&records).await
+    }
+
+    async fn persist(&self, records: &HashMap<JournalKey, SubmissionRecord>) -> Result<()> {
+        let mut records = records.values().collect::<Vec<_>>();
+        records.sort_by(|a, b| (&a.manager, a.epoch).cmp(&(&b.manager, b.epoch)));
+        let content = serde_json::to_string_pretty(&records)?;
+
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || write_atomically(&path, content.as_bytes())).await?
+    }
+}
+
+/// Writes the content to a temporary file that then replaces the file in `path`.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn key(manager: &ManagerKey, epoch: ChainEpoch, validator: &Address) -> JournalKey {
+    (manager.clone(), epoch, validator.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::journal::{
+        CheckpointJournal, ManagerKey, SubmissionStatus, JOURNAL_FILE_NAME,
+    };
+    use crate::checkpoint::CheckpointDirection;
+    use fvm_shared::address::Address;
+
+    #[tokio::test]
+    async fn test_journal_persists_submissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE_NAME);
+        let validator = Address::new_id(100);
+        let manager = ManagerKey {
+            direction: CheckpointDirection::BottomUp,
+            parent: String::from("/root"),
+            child: String::from("/root/t01"),
+        };
+        // the top-down manager of the same subnets is journaled separately
+        let top_down = ManagerKey {
+            direction: CheckpointDirection::TopDown,
+            ..manager.clone()
+        };
+
+        let journal = CheckpointJournal::load(&path).unwrap();
+        assert!(journal.get(&manager, 10, &validator).await.is_none());
+
+        journal
+            .record_pending(&manager, 10, &validator, String::from("cid"))
+            .await
+            .unwrap();
+        journal
+            .record_pending(&manager, 20, &validator, String::from("cid2"))
+            .await
+            .unwrap();
+        journal
+            .record_pending(&top_down, 10, &validator, String::from("cid3"))
+            .await
+            .unwrap();
+        journal
+            .update_status(&manager, 10, &validator, SubmissionStatus::Confirmed)
+            .await
+            .unwrap();
+
+        let journal = CheckpointJournal::load(&path).unwrap();
+        let record = journal.get(&manager, 10, &validator).await.unwrap();
+        assert_eq!(record.submission, "cid");
+        assert_eq!(record.status, SubmissionStatus::Confirmed);
+        assert_eq!(
+            journal.get(&manager, 20, &validator).await.unwrap().status,
+            SubmissionStatus::Pending
+        );
+        assert_eq!(
+            journal
+                .get(&top_down, 10, &validator)
+                .await
+                .unwrap()
+                .submission,
+            "cid3"
+        );
+
+        journal.prune(&manager, 10).await.unwrap();
+
+        let journal = CheckpointJournal::load(&path).unwrap();
+        assert!(journal.get(&manager, 10, &validator).await.is_none());
+        assert!(journal.get(&manager, 20, &validator).await.is_some());
+        assert!(journal.get(&top_down, 10, &validator).await.is_some());
+    }
+
+    #[test]
+    fn test_journal_drops_unknown_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE_NAME);
+        std::fs::write(
+            &path,
+            r#"[{"manager": "bottom-up, parent: /root, child: /root/t01"}]"#,
+        )
+        .unwrap();
+
+        let journal = CheckpointJournal::load(&path).unwrap();
+        assert!(journal.records.try_lock().unwrap().is_empty());
+    }
+}
@@ -2,34 +2,53 @@
 // SPDX-License-Identifier: MIT
 
 use crate::config::{ReloadableConfig, Subnet};
+use crate::events::{EventBus, EventTopic};
+use crate::manager::NonceManager;
+use crate::metrics;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures_util::future::join_all;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_identity::Wallet;
-use std::borrow::Borrow;
 use std::collections::HashSet;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::select;
-use tokio::time::sleep;
+use tokio::time::timeout;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 
+pub use audit::{audit_chain, audit_proofs, AuditIssue, AuditReport};
+pub use batch::{MAX_TOP_DOWN_MSGS, MAX_TOP_DOWN_MSGS_BYTES};
 pub use bottomup::*;
 use ipc_identity::PersistentKeyStore;
 use ipc_sdk::subnet_id::SubnetID;
-pub use proof::create_proof;
+pub use journal::{CheckpointJournal, ManagerKey, SubmissionRecord, SubmissionStatus};
+pub use proof::{create_proof, verify_proof, CheckpointProof};
+pub use setup::setup_bottom_up_builder;
+pub use status::{CheckpointDirection, CheckpointError, CheckpointStatusRegistry, ManagerStatus};
 use std::fmt::Display;
 pub use topdown::*;
+pub(crate) use validators::ValidatorCache;
+pub use validators::DEFAULT_VALIDATOR_CACHE_TTL;
 
+mod audit;
+mod batch;
 mod bottomup;
+mod head;
+mod journal;
 mod proof;
 mod setup;
+mod status;
 mod topdown;
+mod validators;
+mod worker;
 
-const TASKS_PROCESS_THRESHOLD_SEC: u64 = 15;
-const SUBMISSION_LOOK_AHEAD_EPOCH: ChainEpoch = 50;
+/// The timeout of a single call to a checkpoint manager, so that a hanging endpoint does not block
+/// its worker forever.
+const MANAGER_CALL_TIMEOUT: Duration = Duration::from_secs(60);
+/// The timeout to wait for a submission to be executed.
+const SUBMISSION_WAIT_TIMEOUT: Duration = Duration::from_secs(300);
 
 /// Checkpoint manager that handles a specific parent - child - checkpoint type tuple.
 /// For example, we might have `/r123` subnet and `/r123/t01` as child, one implementation of manager
@@ -53,6 +72,17 @@ pub trait CheckpointQuery<T>: VoteQuery<T> {
     async fn checkpoint_period(&self, subnet_id: &SubnetID) -> Result<ChainEpoch>;
     /// Get the list of validators in the subnet id
     async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>>;
+    /// Get the version of the validator set in the subnet id, i.e. its configuration number. It
+    /// changes every time a validator joins or leaves the subnet.
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64>;
+}
+
+/// Tracks the execution of the checkpoints submitted to a subnet
+#[async_trait]
+pub trait SubmissionQuery {
+    /// Waits for the submission, i.e. the message cid in fvm or the transaction hash in evm, to be
+    /// executed and returns the epoch it was executed in.
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch>;
 }
 
 #[async_trait]
@@ -68,12 +98,24 @@ pub trait CheckpointManager: Display + Send + Sync {
     /// Getter for the target subnet this checkpoint manager is handling
     fn child_subnet(&self) -> &Subnet;
 
+    /// Get the subnet config whose chain drives the submissions, i.e. the subnet `current_epoch`
+    /// is read from. If it is top down checkpoints, it returns the parent subnet. If it is bottom
+    /// up, it returns the child subnet.
+    fn source_subnet(&self) -> &Subnet;
+
+    /// The direction of the checkpoints, i.e. top-down or bottom-up.
+    fn direction(&self) -> CheckpointDirection;
+
     /// The checkpoint period that the current manager is submitting upon
     fn checkpoint_period(&self) -> ChainEpoch;
 
     /// Get the list of validators that should submit checkpoints
     async fn validators(&self) -> Result<Vec<Address>>;
 
+    /// Get the version of the validator set returned by `validators`. The validators only need to
+    /// be fetched again when it changes.
+    async fn validator_set_version(&self) -> Result<u64>;
+
     /// Obtain the last executed epoch of the checkpoint submission
     async fn last_executed_epoch(&self) -> Result<ChainEpoch>;
 
@@ -81,8 +123,12 @@ pub trait CheckpointManager: Display + Send + Sync {
     async fn current_epoch(&self) -> Result<ChainEpoch>;
 
     /// Submit the checkpoint based on the current epoch to submit and the previous epoch that was
-    /// already submitted.
-    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<()>;
+    /// already submitted. Returns the identifier of the submission, i.e. the message cid or the
+    /// transaction hash, without waiting for it to be executed.
+    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<String>;
+
+    /// Waits for a submission returned by `submit_checkpoint` to be executed.
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch>;
 
     /// Checks if the validator has already submitted in the epoch
     async fn should_submit_in_epoch(&self, validator: &Address, epoch: ChainEpoch) -> Result<bool>;
@@ -98,6 +144,17 @@ pub struct CheckpointSubsystem {
     config: Arc<ReloadableConfig>,
     fvm_wallet: Arc<RwLock<Wallet>>,
     evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    /// Keeps track of the submissions across restarts to avoid sending duplicates.
+    journal: Arc<CheckpointJournal>,
+    /// The status of the managers, exposed through the json rpc server.
+    status: Arc<CheckpointStatusRegistry>,
+    /// The maximum time the validator set of a manager is cached for.
+    validator_cache_ttl: Duration,
+    /// Assigns the nonces of the submissions, shared with the json rpc server.
+    nonces: Arc<NonceManager>,
+    /// Receives the checkpoints submitted and executed and the validator set changes, forwarded by
+    /// the json rpc server.
+    events: Arc<EventBus>,
 }
 
 impl CheckpointSubsystem {
@@ -106,11 +163,21 @@ impl CheckpointSubsystem {
         config: Arc<ReloadableConfig>,
         fvm_wallet: Arc<RwLock<Wallet>>,
         evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        journal: Arc<CheckpointJournal>,
+        status: Arc<CheckpointStatusRegistry>,
+        validator_cache_ttl: Duration,
+        nonces: Arc<NonceManager>,
+        events: Arc<EventBus>,
     ) -> Self {
         Self {
             config,
             fvm_wallet,
             evm_keystore,
+            journal,
+            status,
+            validator_cache_ttl,
+            nonces,
+            events,
         }
     }
 }
@@ -124,14 +191,22 @@ impl IntoSubsystem<anyhow::Error> for CheckpointSubsystem {
         loop {
             // Load the latest config.
             let config = self.config.get_config();
-            let managers = match setup::setup_managers_from_config(
+            // The workers are aborted once dropped, i.e. when the config is reloaded.
+            let _workers = match setup::setup_managers_from_config(
                 &config.subnets,
                 self.fvm_wallet.clone(),
                 self.evm_keystore.clone(),
+                &self.nonces,
             )
             .await
             {
-                Ok(r) => r,
+                Ok(r) => worker::spawn_workers(
+                    r,
+                    self.journal.clone(),
+                    self.status.clone(),
+                    self.events.clone(),
+                    self.validator_cache_ttl,
+                ),
                 Err(e) => {
                     log::error!("Please check configuration! Cannot start the checkpoint subsystem due to config error: {e:}. Update and reload config.");
                     match config_chan.recv().await {
@@ -146,95 +221,72 @@ impl IntoSubsystem<anyhow::Error> for CheckpointSubsystem {
                 }
             };
 
-            loop {
-                select! {
-                    _ = process_managers(managers.as_slice()) => {},
-                    r = config_chan.recv() => {
-                        log::info!("Config changed, reloading checkpointing subsystem");
-                        match r {
-                            // Config updated, return to caller
-                            Ok(_) => { break; },
-                            Err(_) => {
-                                return Err(anyhow!("Config channel unexpectedly closed, shutting down checkpointing subsystem"))
-                            },
-                        }
-                    }
-                    _ = subsys.on_shutdown_requested() => {
-                        log::info!("Shutting down checkpointing subsystem");
-                        return Ok(());
+            select! {
+                r = config_chan.recv() => {
+                    log::info!("Config changed, reloading checkpointing subsystem");
+                    if r.is_err() {
+                        return Err(anyhow!("Config channel unexpectedly closed, shutting down checkpointing subsystem"));
                     }
                 }
+                _ = subsys.on_shutdown_requested() => {
+                    log::info!("Shutting down checkpointing subsystem");
+                    return Ok(());
+                }
             }
         }
     }
 }
 
-fn handle_err_response(manager: &dyn CheckpointManager, response: anyhow::Result<()>) {
-    if response.is_err() {
-        log::warn!("manager {manager:} had error: {:}", response.unwrap_err());
-    }
-}
-
-async fn process_managers(managers: &[Box<dyn CheckpointManager>]) -> anyhow::Result<()> {
-    // Tracks the start time of the processing, will use this to determine should sleep
-    let start_time = Instant::now();
-
-    let futures = managers
-        .iter()
-        .map(|manager| async {
-            let response = submit_till_current_epoch(manager.borrow()).await;
-            handle_err_response(manager.borrow(), response);
-        })
-        .collect::<Vec<_>>();
-
-    join_all(futures).await;
-
-    sleep_or_continue(start_time).await;
-
-    Ok(())
-}
-
-async fn sleep_or_continue(start_time: Instant) {
-    let elapsed = start_time.elapsed().as_secs();
-    if elapsed < TASKS_PROCESS_THRESHOLD_SEC {
-        sleep(Duration::from_secs(TASKS_PROCESS_THRESHOLD_SEC - elapsed)).await
-    }
-}
-
 /// Attempts to submit checkpoints from the last executed epoch all the way to the current epoch for
-/// all the validators in the provided manager.
-async fn submit_till_current_epoch(manager: &dyn CheckpointManager) -> Result<()> {
-    if !manager.presubmission_check().await? {
+/// all the validators in the provided manager. Submissions already recorded in the journal are
+/// resumed instead of being sent again. The checkpoints submitted and executed are published to
+/// `events`. Returns the next epoch to vote on, if any.
+pub(crate) async fn submit_till_current_epoch(
+    manager: &dyn CheckpointManager,
+    journal: &CheckpointJournal,
+    status: &CheckpointStatusRegistry,
+    events: &EventBus,
+    validator_cache: &mut ValidatorCache,
+) -> Result<Option<ChainEpoch>> {
+    let manager_id = manager.to_string();
+    let journal_key = ManagerKey::new(manager);
+    let policy = manager.child_subnet().checkpointing_policy();
+
+    if !with_timeout(MANAGER_CALL_TIMEOUT, manager.presubmission_check()).await? {
         log::info!("subnet in manager: {manager:} not ready to submit checkpoint");
-        return Ok(());
+        return Ok(None);
     }
 
-    // we might have to obtain the list of validators as some validators might leave the subnet
-    // we can improve the performance by caching if this slows down the process significantly.
-    let mut validators = manager
-        .validators()
-        .await
-        .map_err(|e| anyhow!("cannot get child validators for {manager:} due to {e:}"))?;
+    // the validators are cached until the validator set changes, as some validators might join or
+    // leave the subnet.
+    let mut validators = with_timeout(
+        MANAGER_CALL_TIMEOUT,
+        validator_cache.validators(manager, events),
+    )
+    .await
+    .map_err(|e| anyhow!("cannot get child validators for {manager:} due to {e:}"))?;
     log::debug!("list of validators from on chain: {validators:?} for manager: {manager:}");
-    remove_not_managed(&mut validators, &manager.target_subnet().accounts());
+    let managed_accounts = policy.voting_accounts(manager.target_subnet().accounts());
+    remove_not_managed(&mut validators, &managed_accounts);
     log::debug!("list of validators: {validators:?} for manager: {manager:}");
+    status.update(&manager_id, |s| {
+        s.validators = validators.iter().map(|v| v.to_string()).collect()
+    });
 
     if validators.is_empty() {
         log::info!(
             "no validators: {validators:?} for manager: {manager:}, do not submit checkpoints"
         );
-        return Ok(());
+        return Ok(None);
     }
 
     let period = manager.checkpoint_period();
 
-    let last_executed_epoch = manager
-        .last_executed_epoch()
+    let last_executed_epoch = with_timeout(MANAGER_CALL_TIMEOUT, manager.last_executed_epoch())
         .await
         .map_err(|e| anyhow!("cannot get last executed epoch for {manager:} due to {e:}"))?;
     log::debug!("obtained last executed epoch: {last_executed_epoch:} for manager: {manager:}");
-    let current_epoch = manager
-        .current_epoch()
+    let current_epoch = with_timeout(MANAGER_CALL_TIMEOUT, manager.current_epoch())
         .await
         .map_err(|e| anyhow!("cannot get the current eopch for {manager:} due to {e:}"))?;
     log::debug!("obtained current epoch: {last_executed_epoch:} for manager: {manager:}");
@@ -246,10 +298,36 @@ async fn submit_till_current_epoch(manager: &dyn CheckpointManager) -> Result<()
         manager,
     );
 
+    journal.prune(&journal_key, last_executed_epoch).await?;
+
+    metrics::observe_checkpoint_epochs(manager, last_executed_epoch, current_epoch);
+
     let mut next_epoch = last_executed_epoch + period;
+    let mut previous_executed_epoch = None;
+    status.update(&manager_id, |s| {
+        previous_executed_epoch = s.last_executed_epoch;
+        s.last_executed_epoch = Some(last_executed_epoch);
+        s.current_epoch = Some(current_epoch);
+        s.next_epoch = Some(next_epoch);
+    });
+    // the first run of the manager only reads the last executed epoch.
+    if let Some(previous) = previous_executed_epoch {
+        if last_executed_epoch > previous {
+            events.publish(
+                EventTopic::CheckpointExecuted,
+                Some(&manager.child_subnet().id),
+                serde_json::json!({
+                    "manager": manager_id,
+                    "direction": manager.direction(),
+                    "epoch": last_executed_epoch,
+                }),
+            );
+        }
+    }
+    // the checkpoints are only submitted once they are `submission_delay_epochs` old.
     let cut_off_epoch = std::cmp::min(
-        current_epoch,
-        SUBMISSION_LOOK_AHEAD_EPOCH + last_executed_epoch,
+        current_epoch - policy.submission_delay_epochs,
+        policy.look_ahead_epochs + last_executed_epoch,
     );
 
     // Instead of loop all the way to `current_epoch`, we loop till `cut_off_epoch`.
@@ -261,33 +339,137 @@ async fn submit_till_current_epoch(manager: &dyn CheckpointManager) -> Result<()
         for validator in &validators {
             log::debug!("submit checkpoint for validator: {validator:?} in manager: {manager:}");
 
-            if !manager
-                .should_submit_in_epoch(validator, next_epoch)
-                .await?
+            if !with_timeout(
+                MANAGER_CALL_TIMEOUT,
+                manager.should_submit_in_epoch(validator, next_epoch),
+            )
+            .await?
             {
                 log::debug!(
                     "next submission epoch {next_epoch:?} already voted for validator: {:?} in manager: {manager:}",
                     validator.to_string()
                 );
+                journal
+                    .update_status(
+                        &journal_key,
+                        next_epoch,
+                        validator,
+                        SubmissionStatus::Confirmed,
+                    )
+                    .await?;
                 continue;
             }
 
+            let pending = journal
+                .get(&journal_key, next_epoch, validator)
+                .await
+                .filter(|r| r.status == SubmissionStatus::Pending);
+            if let Some(record) = pending {
+                log::info!(
+                    "resuming pending submission {:} at epoch {next_epoch:} for validator {validator:} in manager: {manager:}",
+                    record.submission
+                );
+                match with_timeout(
+                    SUBMISSION_WAIT_TIMEOUT,
+                    manager.wait_submission(&record.submission),
+                )
+                .await
+                {
+                    Ok(_) => {
+                        journal
+                            .update_status(
+                                &journal_key,
+                                next_epoch,
+                                validator,
+                                SubmissionStatus::Confirmed,
+                            )
+                            .await?;
+                        continue;
+                    }
+                    Err(e) => {
+                        log::warn!("pending submission {:} in manager: {manager:} failed: {e:}, submitting again", record.submission);
+                        journal
+                            .update_status(
+                                &journal_key,
+                                next_epoch,
+                                validator,
+                                SubmissionStatus::Failed,
+                            )
+                            .await?;
+                    }
+                }
+            }
+
             log::debug!(
                 "next submission epoch {next_epoch:} not voted for validator: {validator:} in manager: {manager:}, should vote"
             );
 
-            manager.submit_checkpoint(next_epoch, validator).await?;
+            let submission = with_timeout(
+                MANAGER_CALL_TIMEOUT,
+                manager.submit_checkpoint(next_epoch, validator),
+            )
+            .await?;
+            journal
+                .record_pending(&journal_key, next_epoch, validator, submission.clone())
+                .await?;
+
+            if let Err(e) = with_timeout(
+                SUBMISSION_WAIT_TIMEOUT,
+                manager.wait_submission(&submission),
+            )
+            .await
+            {
+                journal
+                    .update_status(
+                        &journal_key,
+                        next_epoch,
+                        validator,
+                        SubmissionStatus::Failed,
+                    )
+                    .await?;
+                return Err(anyhow!(
+                    "submission {submission:} in manager: {manager:} failed: {e:}"
+                ));
+            }
+            journal
+                .update_status(
+                    &journal_key,
+                    next_epoch,
+                    validator,
+                    SubmissionStatus::Confirmed,
+                )
+                .await?;
+            metrics::inc_checkpoint_submissions(manager);
+            events.publish(
+                EventTopic::CheckpointSubmitted,
+                Some(&manager.child_subnet().id),
+                serde_json::json!({
+                    "manager": manager_id,
+                    "direction": manager.direction(),
+                    "epoch": next_epoch,
+                    "validator": validator.to_string(),
+                    "submission": submission,
+                }),
+            );
 
             log::info!("checkpoint at epoch {next_epoch:} submitted for validator {validator:} in manager: {manager:}");
         }
 
         // increment next epoch
         next_epoch += period;
+        status.update(&manager_id, |s| s.next_epoch = Some(next_epoch));
     }
 
     log::info!("process checkpoint from epoch: {last_executed_epoch:} to {current_epoch:} in manager: {manager:}");
 
-    Ok(())
+    Ok(Some(next_epoch))
+}
+
+/// Runs a call to a checkpoint manager, failing if it does not complete within `limit`.
+async fn with_timeout<T>(limit: Duration, call: impl Future<Output = Result<T>>) -> Result<T> {
+    timeout(limit, call)
+        .await
+        .map_err(|_| anyhow!("call timed out after {limit:?}"))?
 }
 
 /// Removes the not managed accounts from the list of validators
@@ -3,16 +3,34 @@
 //! The checkpoint proof structs
 
 mod v1;
+mod v2;
 
 pub use crate::checkpoint::proof::v1::V1Proof;
+pub use crate::checkpoint::proof::v2::V2Proof;
 use crate::lotus::LotusClient;
+use anyhow::anyhow;
+use cid::Cid;
+use fil_actors_runtime::cbor;
+use fvm_ipld_encoding::RawBytes;
 use fvm_shared::clock::ChainEpoch;
+use ipc_gateway::BottomUpCheckpoint;
 use serde::{Deserialize, Serialize};
 
 /// The different versions of checkpoint proofs supported
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum CheckpointProof {
     V1(V1Proof),
+    V2(V2Proof),
+}
+
+impl CheckpointProof {
+    /// Checks if the proof matches the tipset cids and the state roots of its blocks.
+    pub fn matches(&self, tip_set: &[Cid], states: &[Cid]) -> bool {
+        match self {
+            CheckpointProof::V1(p) => p.matches(tip_set, states),
+            CheckpointProof::V2(p) => p.matches(tip_set, states),
+        }
+    }
 }
 
 /// Create the checkpoint proof
@@ -20,6 +38,52 @@ pub async fn create_proof<L: LotusClient>(
     client: &L,
     height: ChainEpoch,
 ) -> anyhow::Result<CheckpointProof> {
-    let v1_proof = v1::create_proof(client, height).await?;
-    Ok(CheckpointProof::V1(v1_proof))
+    let v2_proof = v2::create_proof(client, height).await?;
+    Ok(CheckpointProof::V2(v2_proof))
+}
+
+/// Verifies that the proof embedded in a committed checkpoint matches the tipset of the child chain
+/// at the epoch of the checkpoint. The `client` must point to the child subnet.
+pub async fn verify_proof<L: LotusClient>(
+    client: &L,
+    checkpoint: &BottomUpCheckpoint,
+) -> anyhow::Result<()> {
+    let epoch = checkpoint.data.epoch;
+    if checkpoint.data.proof.is_empty() {
+        return Err(anyhow!("checkpoint at epoch {epoch:} has no proof"));
+    }
+
+    let proof: CheckpointProof = cbor::deserialize(
+        &RawBytes::new(checkpoint.data.proof.clone()),
+        "bottom up checkpoint proof",
+    )?;
+    let (tip_set, states) = tipset_at_height(client, epoch).await?;
+    if !proof.matches(&tip_set, &states) {
+        return Err(anyhow!(
+            "proof of checkpoint at epoch {epoch:} does not match the child chain: {proof:?}"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the cids of the blocks in the tipset at `height` along with their state roots.
+async fn tipset_at_height<L: LotusClient>(
+    client: &L,
+    height: ChainEpoch,
+) -> anyhow::Result<(Vec<Cid>, Vec<Cid>)> {
+    let tip_sets = client.chain_head().await?.cids;
+    if tip_sets.is_empty() {
+        return Err(anyhow!("chain head has empty cid"));
+    }
+
+    let response = client
+        .get_tipset_by_height(height, Cid::try_from(&tip_sets[0])?)
+        .await?;
+    let states = response.blocks_state_roots()?;
+    if states.is_empty() {
+        return Err(anyhow!("height {height:} has zero blocks"));
+    }
+
+    Ok((response.tip_set_cids()?, states))
 }
@@ -0,0 +1,64 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+use crate::lotus::LotusClient;
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use serde::{Deserialize, Serialize};
+
+/// The checkpoint proof covering every block in the tipset. `states` contains the `parent_state`
+/// of each block, in the same order as the blocks in `tip_set`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct V2Proof {
+    tip_set: Vec<Cid>,
+    states: Vec<Cid>,
+}
+
+impl V2Proof {
+    /// Checks if the proof matches the tipset cids and the state roots of its blocks.
+    pub fn matches(&self, tip_set: &[Cid], states: &[Cid]) -> bool {
+        self.tip_set == tip_set && self.states == states
+    }
+}
+
+pub async fn create_proof<L: LotusClient>(
+    client: &L,
+    height: ChainEpoch,
+) -> anyhow::Result<V2Proof> {
+    let (tip_set, states) = super::tipset_at_height(client, height).await?;
+    Ok(V2Proof { tip_set, states })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::proof::v2::V2Proof;
+    use crate::checkpoint::proof::CheckpointProof;
+    use cid::multihash::{Code, MultihashDigest};
+    use cid::Cid;
+    use fil_actors_runtime::cbor;
+    use fvm_ipld_encoding::{RawBytes, DAG_CBOR};
+
+    #[test]
+    fn test_v2_proof_matches() {
+        let cid = |i: u8| Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[i]));
+        let blocks = [cid(0), cid(1)];
+        let states = [cid(2), cid(3)];
+
+        let proof = CheckpointProof::V2(V2Proof {
+            tip_set: blocks.to_vec(),
+            states: states.to_vec(),
+        });
+        assert!(proof.matches(&blocks, &states));
+        // every block state root is covered, not only the first one
+        assert!(!proof.matches(&blocks, &[states[0], states[0]]));
+        assert!(!proof.matches(&blocks[..1], &states[..1]));
+
+        // the proof survives the round trip through the bytes stored in the checkpoint
+        let bytes = cbor::serialize(&proof, "checkpoint proof")
+            .unwrap()
+            .to_vec();
+        let decoded: CheckpointProof =
+            cbor::deserialize(&RawBytes::new(bytes), "checkpoint proof").unwrap();
+        assert!(decoded.matches(&blocks, &states));
+    }
+}
@@ -1,11 +1,13 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use crate::checkpoint::CheckpointManager;
+use crate::checkpoint::bottomup::BottomUpManager;
+use crate::checkpoint::{BottomUpCheckpointBuilder, CheckpointManager};
 use crate::config::subnet::NetworkType;
 use crate::config::Subnet;
+use crate::jsonrpc::JsonRpcClientImpl;
 use crate::lotus::client::LotusJsonRPCClient;
 use crate::manager::fevm::FevmSubnetManager;
-use crate::manager::{EthSubnetManager, LotusSubnetManager};
+use crate::manager::{EthSubnetManager, LotusSubnetManager, NonceManager};
 use anyhow::anyhow;
 use ipc_identity::PersistentKeyStore;
 use ipc_identity::Wallet;
@@ -18,6 +20,7 @@ async fn parent_fevm_child_fvm(
     child: &Subnet,
     fvm_wallet_store: Arc<RwLock<Wallet>>,
     evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
 ) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
     if parent.network_type() != NetworkType::Fevm || child.network_type() != NetworkType::Fvm {
         return Err(anyhow!("parent not fevm or child not fvm"));
@@ -25,35 +28,108 @@ async fn parent_fevm_child_fvm(
 
     let mut managers = vec![];
 
-    let fevm = FevmSubnetManager::new(
-        EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store.clone())?,
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone()),
-    );
-    let fvm = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone()),
-        child.gateway_addr(),
-    );
-    let m: Box<dyn CheckpointManager> = Box::new(
-        crate::checkpoint::bottomup::BottomUpManager::new(parent.clone(), child.clone(), fevm, fvm)
+    let policy = child.checkpointing_policy();
+
+    if policy.bottom_up {
+        let m: Box<dyn CheckpointManager> = Box::new(
+            bottom_up_parent_fevm_child_fvm(
+                parent,
+                child,
+                fvm_wallet_store.clone(),
+                evm_wallet_store.clone(),
+                nonces,
+            )
             .await?,
-    );
+        );
 
-    managers.push(m);
+        managers.push(m);
+    }
 
-    let fevm = FevmSubnetManager::new(
-        EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store.clone())?,
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone()),
-    );
-    let fvm = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone()),
-        child.gateway_addr(),
-    );
-    let m: Box<dyn CheckpointManager> = Box::new(
-        crate::checkpoint::topdown::TopDownManager::new(parent.clone(), child.clone(), fevm, fvm)
+    if policy.top_down {
+        let fevm = FevmSubnetManager::new(
+            EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store.clone())?
+                .with_nonce_manager(parent.id.clone(), nonces.clone()),
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone())
+                .with_nonce_manager(nonces.clone()),
+        );
+        let fvm = LotusSubnetManager::new(
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone())
+                .with_nonce_manager(nonces.clone()),
+            child.gateway_addr(),
+        );
+        let m: Box<dyn CheckpointManager> = Box::new(
+            crate::checkpoint::topdown::TopDownManager::new(
+                parent.clone(),
+                child.clone(),
+                fevm,
+                fvm,
+            )
             .await?,
-    );
+        );
+
+        managers.push(m);
+    }
+
+    Ok(managers)
+}
+
+async fn parent_fevm_child_fevm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
+    if parent.network_type() != NetworkType::Fevm || child.network_type() != NetworkType::Fevm {
+        return Err(anyhow!("parent not fevm or child not fevm"));
+    }
+
+    let mut managers = vec![];
+
+    let policy = child.checkpointing_policy();
+
+    if policy.bottom_up {
+        let m: Box<dyn CheckpointManager> = Box::new(
+            bottom_up_parent_fevm_child_fevm(
+                parent,
+                child,
+                fvm_wallet_store.clone(),
+                evm_wallet_store.clone(),
+                nonces,
+            )
+            .await?,
+        );
+
+        managers.push(m);
+    }
+
+    if policy.top_down {
+        // the lotus client points to the child subnet, it is only used to generate the
+        // checkpoint proofs from the child's chain.
+        let fevm_p = FevmSubnetManager::new(
+            EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store.clone())?
+                .with_nonce_manager(parent.id.clone(), nonces.clone()),
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone())
+                .with_nonce_manager(nonces.clone()),
+        );
+        let fevm_c = FevmSubnetManager::new(
+            EthSubnetManager::from_subnet_with_wallet_store(child, evm_wallet_store)?
+                .with_nonce_manager(child.id.clone(), nonces.clone()),
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+                .with_nonce_manager(nonces.clone()),
+        );
+        let m: Box<dyn CheckpointManager> = Box::new(
+            crate::checkpoint::topdown::TopDownManager::new(
+                parent.clone(),
+                child.clone(),
+                fevm_p,
+                fevm_c,
+            )
+            .await?,
+        );
 
-    managers.push(m);
+        managers.push(m);
+    }
 
     Ok(managers)
 }
@@ -62,6 +138,7 @@ async fn parent_fvm_child_fvm(
     parent: &Subnet,
     child: &Subnet,
     fvm_wallet_store: Arc<RwLock<Wallet>>,
+    nonces: &Arc<NonceManager>,
 ) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
     if parent.network_type() != NetworkType::Fevm || child.network_type() != NetworkType::Fvm {
         return Err(anyhow!("parent not fevm or child not fvm"));
@@ -69,47 +146,242 @@ async fn parent_fvm_child_fvm(
 
     let mut managers = vec![];
 
-    let fvm_p = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone()),
-        parent.gateway_addr(),
+    let policy = child.checkpointing_policy();
+
+    if policy.bottom_up {
+        let m: Box<dyn CheckpointManager> = Box::new(
+            bottom_up_parent_fvm_child_fvm(parent, child, fvm_wallet_store.clone(), nonces).await?,
+        );
+
+        managers.push(m);
+    }
+
+    if policy.top_down {
+        let fvm_p = LotusSubnetManager::new(
+            LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone())
+                .with_nonce_manager(nonces.clone()),
+            parent.gateway_addr(),
+        );
+        let fvm_c = LotusSubnetManager::new(
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+                .with_nonce_manager(nonces.clone()),
+            child.gateway_addr(),
+        );
+        let m: Box<dyn CheckpointManager> = Box::new(
+            crate::checkpoint::topdown::TopDownManager::new(
+                parent.clone(),
+                child.clone(),
+                fvm_p,
+                fvm_c,
+            )
+            .await?,
+        );
+
+        managers.push(m);
+    }
+
+    Ok(managers)
+}
+
+async fn parent_fvm_child_fevm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
+    if parent.network_type() != NetworkType::Fvm || child.network_type() != NetworkType::Fevm {
+        return Err(anyhow!("parent not fvm or child not fevm"));
+    }
+
+    let mut managers = vec![];
+
+    let policy = child.checkpointing_policy();
+
+    if policy.bottom_up {
+        let m: Box<dyn CheckpointManager> = Box::new(
+            bottom_up_parent_fvm_child_fevm(
+                parent,
+                child,
+                fvm_wallet_store.clone(),
+                evm_wallet_store.clone(),
+                nonces,
+            )
+            .await?,
+        );
+
+        managers.push(m);
+    }
+
+    if policy.top_down {
+        let fvm = LotusSubnetManager::new(
+            LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone())
+                .with_nonce_manager(nonces.clone()),
+            parent.gateway_addr(),
+        );
+        let fevm = FevmSubnetManager::new(
+            EthSubnetManager::from_subnet_with_wallet_store(child, evm_wallet_store)?
+                .with_nonce_manager(child.id.clone(), nonces.clone()),
+            LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+                .with_nonce_manager(nonces.clone()),
+        );
+        let m: Box<dyn CheckpointManager> = Box::new(
+            crate::checkpoint::topdown::TopDownManager::new(
+                parent.clone(),
+                child.clone(),
+                fvm,
+                fevm,
+            )
+            .await?,
+        );
+
+        managers.push(m);
+    }
+
+    Ok(managers)
+}
+
+async fn bottom_up_parent_fevm_child_fvm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<BottomUpManager<FevmSubnetManager, LotusSubnetManager<JsonRpcClientImpl>>> {
+    let fevm = FevmSubnetManager::new(
+        EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store)?
+            .with_nonce_manager(parent.id.clone(), nonces.clone()),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone())
+            .with_nonce_manager(nonces.clone()),
     );
-    let fvm_c = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone()),
+    let fvm = LotusSubnetManager::new(
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+            .with_nonce_manager(nonces.clone()),
         child.gateway_addr(),
     );
-    let m: Box<dyn CheckpointManager> = Box::new(
-        crate::checkpoint::bottomup::BottomUpManager::new(
-            parent.clone(),
-            child.clone(),
-            fvm_p,
-            fvm_c,
-        )
-        .await?,
-    );
+    BottomUpManager::new(parent.clone(), child.clone(), fevm, fvm).await
+}
 
-    managers.push(m);
+async fn bottom_up_parent_fevm_child_fevm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<BottomUpManager<FevmSubnetManager, FevmSubnetManager>> {
+    // the lotus client points to the child subnet, it is only used to generate the
+    // checkpoint proofs from the child's chain.
+    let fevm_p = FevmSubnetManager::new(
+        EthSubnetManager::from_subnet_with_wallet_store(parent, evm_wallet_store.clone())?
+            .with_nonce_manager(parent.id.clone(), nonces.clone()),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store.clone())
+            .with_nonce_manager(nonces.clone()),
+    );
+    let fevm_c = FevmSubnetManager::new(
+        EthSubnetManager::from_subnet_with_wallet_store(child, evm_wallet_store)?
+            .with_nonce_manager(child.id.clone(), nonces.clone()),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+            .with_nonce_manager(nonces.clone()),
+    );
+    BottomUpManager::new(parent.clone(), child.clone(), fevm_p, fevm_c).await
+}
 
+async fn bottom_up_parent_fvm_child_fvm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<
+    BottomUpManager<LotusSubnetManager<JsonRpcClientImpl>, LotusSubnetManager<JsonRpcClientImpl>>,
+> {
     let fvm_p = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone()),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone())
+            .with_nonce_manager(nonces.clone()),
         parent.gateway_addr(),
     );
     let fvm_c = LotusSubnetManager::new(
-        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+            .with_nonce_manager(nonces.clone()),
         child.gateway_addr(),
     );
-    let m: Box<dyn CheckpointManager> = Box::new(
-        crate::checkpoint::topdown::TopDownManager::new(
-            parent.clone(),
-            child.clone(),
-            fvm_p,
-            fvm_c,
-        )
-        .await?,
+    BottomUpManager::new(parent.clone(), child.clone(), fvm_p, fvm_c).await
+}
+
+async fn bottom_up_parent_fvm_child_fevm(
+    parent: &Subnet,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<BottomUpManager<LotusSubnetManager<JsonRpcClientImpl>, FevmSubnetManager>> {
+    let fvm = LotusSubnetManager::new(
+        LotusJsonRPCClient::from_subnet_with_wallet_store(parent, fvm_wallet_store.clone())
+            .with_nonce_manager(nonces.clone()),
+        parent.gateway_addr(),
     );
+    let fevm = FevmSubnetManager::new(
+        EthSubnetManager::from_subnet_with_wallet_store(child, evm_wallet_store)?
+            .with_nonce_manager(child.id.clone(), nonces.clone()),
+        LotusJsonRPCClient::from_subnet_with_wallet_store(child, fvm_wallet_store)
+            .with_nonce_manager(nonces.clone()),
+    );
+    BottomUpManager::new(parent.clone(), child.clone(), fvm, fevm).await
+}
 
-    managers.push(m);
+/// Creates the builder of the bottom-up checkpoints of the `child` subnet, which must have its
+/// parent in `subnets`. The builder uses the same handlers as the checkpoint managers, but it never
+/// submits anything.
+pub async fn setup_bottom_up_builder(
+    subnets: &HashMap<SubnetID, Subnet>,
+    child: &Subnet,
+    fvm_wallet_store: Arc<RwLock<Wallet>>,
+    evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
+) -> anyhow::Result<Box<dyn BottomUpCheckpointBuilder>> {
+    let parent = child
+        .id
+        .parent()
+        .and_then(|p| subnets.get(&p))
+        .ok_or_else(|| anyhow!("subnet has no parent configured: {:}", child.id))?;
 
-    Ok(managers)
+    let builder: Box<dyn BottomUpCheckpointBuilder> =
+        match (parent.network_type(), child.network_type()) {
+            (NetworkType::Fvm, NetworkType::Fvm) => Box::new(
+                bottom_up_parent_fvm_child_fvm(parent, child, fvm_wallet_store, nonces).await?,
+            ),
+            (NetworkType::Fvm, NetworkType::Fevm) => Box::new(
+                bottom_up_parent_fvm_child_fevm(
+                    parent,
+                    child,
+                    fvm_wallet_store,
+                    evm_wallet_store,
+                    nonces,
+                )
+                .await?,
+            ),
+            (NetworkType::Fevm, NetworkType::Fvm) => Box::new(
+                bottom_up_parent_fevm_child_fvm(
+                    parent,
+                    child,
+                    fvm_wallet_store,
+                    evm_wallet_store,
+                    nonces,
+                )
+                .await?,
+            ),
+            (NetworkType::Fevm, NetworkType::Fevm) => Box::new(
+                bottom_up_parent_fevm_child_fevm(
+                    parent,
+                    child,
+                    fvm_wallet_store,
+                    evm_wallet_store,
+                    nonces,
+                )
+                .await?,
+            ),
+        };
+
+    Ok(builder)
 }
 
 pub async fn setup_manager_from_subnet(
@@ -117,6 +389,7 @@ pub async fn setup_manager_from_subnet(
     s: &Subnet,
     fvm_wallet_store: Arc<RwLock<Wallet>>,
     evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
 ) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
     let parent = if let Some(p) = s.id.parent() && subnets.contains_key(&p) {
         subnets.get(&p).unwrap()
@@ -128,17 +401,19 @@ pub async fn setup_manager_from_subnet(
     match (parent.network_type(), s.network_type()) {
         (NetworkType::Fvm, NetworkType::Fvm) => {
             log::info!("setup parent: {:?} fvm, child: {:?} fvm", parent.id, s.id);
-            parent_fvm_child_fvm(parent, s, fvm_wallet_store).await
+            parent_fvm_child_fvm(parent, s, fvm_wallet_store, nonces).await
         }
         (NetworkType::Fvm, NetworkType::Fevm) => {
-            unimplemented!()
+            log::info!("setup parent: {:?} fvm, child: {:?} fevm", parent.id, s.id);
+            parent_fvm_child_fevm(parent, s, fvm_wallet_store, evm_wallet_store, nonces).await
         }
         (NetworkType::Fevm, NetworkType::Fvm) => {
             log::info!("setup parent: {:?} fevm, child: {:?} fvm", parent.id, s.id);
-            parent_fevm_child_fvm(parent, s, fvm_wallet_store, evm_wallet_store).await
+            parent_fevm_child_fvm(parent, s, fvm_wallet_store, evm_wallet_store, nonces).await
         }
         (NetworkType::Fevm, NetworkType::Fevm) => {
-            todo!()
+            log::info!("setup parent: {:?} fevm, child: {:?} fevm", parent.id, s.id);
+            parent_fevm_child_fevm(parent, s, fvm_wallet_store, evm_wallet_store, nonces).await
         }
     }
 }
@@ -147,6 +422,7 @@ pub async fn setup_managers_from_config(
     subnets: &HashMap<SubnetID, Subnet>,
     fvm_wallet_store: Arc<RwLock<Wallet>>,
     evm_wallet_store: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: &Arc<NonceManager>,
 ) -> anyhow::Result<Vec<Box<dyn CheckpointManager>>> {
     let mut managers = vec![];
 
@@ -158,6 +434,7 @@ pub async fn setup_managers_from_config(
             s,
             fvm_wallet_store.clone(),
             evm_wallet_store.clone(),
+            nonces,
         )
         .await?;
         managers.extend(subnet_managers);
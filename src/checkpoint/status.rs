@@ -0,0 +1,167 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Keeps track of what each checkpoint manager is doing, so that it can be queried through the
+//! json rpc server.
+
+use crate::checkpoint::CheckpointManager;
+use fvm_shared::clock::ChainEpoch;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckpointDirection {
+    TopDown,
+    BottomUp,
+}
+
+impl Display for CheckpointDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckpointDirection::TopDown => write!(f, "top-down"),
+            CheckpointDirection::BottomUp => write!(f, "bottom-up"),
+        }
+    }
+}
+
+/// The latest error of a checkpoint manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CheckpointError {
+    pub message: String,
+    /// Unix timestamp in seconds of when the error happened.
+    pub timestamp: u64,
+}
+
+/// The status of a checkpoint manager. The epochs are `None` until the manager has read them from
+/// the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ManagerStatus {
+    pub direction: CheckpointDirection,
+    pub parent: String,
+    pub child: String,
+    pub period: ChainEpoch,
+    pub last_executed_epoch: Option<ChainEpoch>,
+    pub current_epoch: Option<ChainEpoch>,
+    /// The next epoch the manager plans to vote on.
+    pub next_epoch: Option<ChainEpoch>,
+    /// The validators managed by the agent that submit checkpoints.
+    pub validators: Vec<String>,
+    pub last_error: Option<CheckpointError>,
+}
+
+impl ManagerStatus {
+    fn new(manager: &dyn CheckpointManager) -> Self {
+        Self {
+            direction: manager.direction(),
+            parent: manager.parent_subnet().id.to_string(),
+            child: manager.child_subnet().id.to_string(),
+            period: manager.checkpoint_period(),
+            last_executed_epoch: None,
+            current_epoch: None,
+            next_epoch: None,
+            validators: vec![],
+            last_error: None,
+        }
+    }
+}
+
+/// The status of all the active checkpoint managers, shared between the checkpoint subsystem and
+/// the json rpc server.
+#[derive(Default)]
+pub struct CheckpointStatusRegistry {
+    managers: RwLock<BTreeMap<String, ManagerStatus>>,
+}
+
+impl CheckpointStatusRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the status of all the active managers.
+    pub fn list(&self) -> Vec<ManagerStatus> {
+        let managers = self.managers.read().unwrap();
+        managers.values().cloned().collect()
+    }
+
+    /// Replaces the tracked managers with `managers`, i.e. after the config is reloaded.
+    pub(crate) fn reset(&self, managers: &[Box<dyn CheckpointManager>]) {
+        let mut tracked = self.managers.write().unwrap();
+        *tracked = managers
+            .iter()
+            .map(|m| (m.to_string(), ManagerStatus::new(m.as_ref())))
+            .collect();
+    }
+
+    /// Updates the status of the manager. It is a noop if the manager is not tracked.
+    pub(crate) fn update(&self, manager: &str, f: impl FnOnce(&mut ManagerStatus)) {
+        let mut managers = self.managers.write().unwrap();
+        if let Some(status) = managers.get_mut(manager) {
+            f(status);
+        }
+    }
+
+    /// Records the latest error of the manager.
+    pub(crate) fn set_error(&self, manager: &str, error: &anyhow::Error) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.update(manager, |s| {
+            s.last_error = Some(CheckpointError {
+                message: error.to_string(),
+                timestamp,
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::status::{CheckpointDirection, CheckpointStatusRegistry, ManagerStatus};
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_status_registry() {
+        let registry = CheckpointStatusRegistry::new();
+        let manager = "top-down, parent: /root, child: /root/t01";
+        registry.managers.write().unwrap().insert(
+            manager.to_string(),
+            ManagerStatus {
+                direction: CheckpointDirection::TopDown,
+                parent: String::from("/root"),
+                child: String::from("/root/t01"),
+                period: 10,
+                last_executed_epoch: None,
+                current_epoch: None,
+                next_epoch: None,
+                validators: vec![],
+                last_error: None,
+            },
+        );
+
+        registry.update(manager, |s| s.next_epoch = Some(20));
+        registry.set_error(manager, &anyhow!("endpoint unreachable"));
+        // untracked managers are ignored
+        registry.update("bottom-up, parent: /root, child: /root/t02", |s| {
+            s.next_epoch = Some(30)
+        });
+
+        let status = registry.list();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].next_epoch, Some(20));
+        assert_eq!(
+            status[0].last_error.as_ref().unwrap().message,
+            "endpoint unreachable"
+        );
+        assert_eq!(
+            serde_json::to_value(status[0].direction).unwrap(),
+            serde_json::json!("top-down")
+        );
+    }
+}
@@ -1,7 +1,13 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use crate::checkpoint::{CheckpointManager, CheckpointMetadata, CheckpointQuery};
+use crate::checkpoint::batch::{
+    batch_top_down_msgs, BacklogTracker, TopDownBatch, MAX_TOP_DOWN_MSGS, MAX_TOP_DOWN_MSGS_BYTES,
+};
+use crate::checkpoint::{
+    CheckpointDirection, CheckpointManager, CheckpointMetadata, CheckpointQuery, SubmissionQuery,
+};
 use crate::config::Subnet;
+use crate::metrics;
 use anyhow::Result;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
@@ -9,10 +15,17 @@ use fvm_shared::clock::ChainEpoch;
 use ipc_gateway::{CrossMsg, TopDownCheckpoint};
 use ipc_sdk::subnet_id::SubnetID;
 use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+/// The number of consecutive checkpoints the top-down backlog does not shrink in before it is
+/// reported.
+const BACKLOG_STALL_WARNING: u32 = 3;
 
 /// The trait that handles the bottom up checkpoint submission data preparation and actual submission.
 #[async_trait]
-pub trait TopDownHandler: Send + Sync + CheckpointQuery<TopDownCheckpoint> {
+pub trait TopDownHandler:
+    Send + Sync + CheckpointQuery<TopDownCheckpoint> + SubmissionQuery
+{
     /// Checks if the gateway is initialized
     async fn gateway_initialized(&self) -> Result<bool>;
     /// Get the latest applied top down nonce
@@ -24,18 +37,15 @@ pub trait TopDownHandler: Send + Sync + CheckpointQuery<TopDownCheckpoint> {
         nonce: u64,
         epoch: ChainEpoch,
     ) -> Result<Vec<CrossMsg>>;
-    /// Submit the checkpoint for validator
-    async fn submit(
-        &self,
-        validator: &Address,
-        checkpoint: TopDownCheckpoint,
-    ) -> Result<ChainEpoch>;
+    /// Submit the checkpoint for validator, returns the message cid or transaction hash
+    async fn submit(&self, validator: &Address, checkpoint: TopDownCheckpoint) -> Result<String>;
 }
 
 pub struct TopDownManager<P: TopDownHandler, C: TopDownHandler> {
     metadata: CheckpointMetadata,
     parent_handler: P,
     child_handler: C,
+    backlog: Mutex<BacklogTracker>,
 }
 
 impl<P: TopDownHandler, C: TopDownHandler> TopDownManager<P, C> {
@@ -54,10 +64,41 @@ impl<P: TopDownHandler, C: TopDownHandler> TopDownManager<P, C> {
             },
             parent_handler,
             child_handler,
+            backlog: Mutex::new(BacklogTracker::default()),
         })
     }
 }
 
+impl<P: TopDownHandler, C: TopDownHandler> TopDownManager<P, C> {
+    /// The batch of top-down messages of the checkpoint at `epoch`. The checkpoints voted ahead of
+    /// the executed one carry the messages after the batches of the checkpoints before them, so
+    /// those batches are replayed from the nonce applied by the child. Every validator derives the
+    /// same batches from the chain state and votes the same checkpoint.
+    async fn batch_at(&self, epoch: ChainEpoch) -> Result<TopDownBatch> {
+        let child = &self.metadata.child.id;
+        let last_executed = self.child_handler.last_executed_epoch(child).await?;
+        let mut nonce = self.child_handler.applied_topdown_nonce(child).await?;
+        log::info!("latest applied top down nonce for {self:}: {nonce}");
+
+        let mut batch_epoch = last_executed + self.metadata.period;
+        loop {
+            let msgs = self
+                .parent_handler
+                .top_down_msgs(child, nonce, batch_epoch)
+                .await?;
+            // the messages are capped so that the checkpoint can always be executed, the rest
+            // are carried by the following checkpoints.
+            let batch =
+                batch_top_down_msgs(msgs, nonce, MAX_TOP_DOWN_MSGS, MAX_TOP_DOWN_MSGS_BYTES)?;
+            if batch_epoch >= epoch {
+                return Ok(batch);
+            }
+            nonce += batch.msgs.len() as u64;
+            batch_epoch += self.metadata.period;
+        }
+    }
+}
+
 impl<P: TopDownHandler, C: TopDownHandler> Display for TopDownManager<P, C> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -84,6 +125,16 @@ impl<P: TopDownHandler, C: TopDownHandler> CheckpointManager for TopDownManager<
         &self.metadata.child
     }
 
+    /// Get the subnet config whose chain drives the submissions, i.e. the subnet `current_epoch`
+    /// is read from.
+    fn source_subnet(&self) -> &Subnet {
+        &self.metadata.parent
+    }
+
+    fn direction(&self) -> CheckpointDirection {
+        CheckpointDirection::TopDown
+    }
+
     fn checkpoint_period(&self) -> ChainEpoch {
         self.metadata.period
     }
@@ -94,6 +145,12 @@ impl<P: TopDownHandler, C: TopDownHandler> CheckpointManager for TopDownManager<
             .await
     }
 
+    async fn validator_set_version(&self) -> Result<u64> {
+        self.parent_handler
+            .validator_set_version(&self.metadata.child.id)
+            .await
+    }
+
     async fn last_executed_epoch(&self) -> Result<ChainEpoch> {
         self.child_handler
             .last_executed_epoch(&self.metadata.child.id)
@@ -104,22 +161,24 @@ impl<P: TopDownHandler, C: TopDownHandler> CheckpointManager for TopDownManager<
         self.parent_handler.current_epoch().await
     }
 
-    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<()> {
-        let nonce = self
-            .child_handler
-            .applied_topdown_nonce(&self.metadata.child.id)
-            .await?;
-        log::info!("latest applied top down nonce for {self:}: {nonce}");
-
-        let top_down_msgs = self
-            .parent_handler
-            .top_down_msgs(&self.metadata.child.id, nonce, epoch)
-            .await?;
+    async fn submit_checkpoint(&self, epoch: ChainEpoch, validator: &Address) -> Result<String> {
+        let batch = self.batch_at(epoch).await?;
+        let top_down_msgs = batch.msgs;
         log::info!(
-            "top down messages to execute for {self:}: {:}",
-            top_down_msgs.len()
+            "top down messages to execute for {self:}: {:}, left for later checkpoints: {:}",
+            top_down_msgs.len(),
+            batch.backlog
         );
 
+        metrics::observe_top_down_backlog(self, batch.backlog);
+        let stalled = self.backlog.lock().unwrap().record(epoch, batch.backlog);
+        if stalled >= BACKLOG_STALL_WARNING {
+            log::warn!(
+                "top down backlog of {self:} did not shrink in {stalled:} checkpoints, {:} messages left",
+                batch.backlog
+            );
+        }
+
         // we submit the topdown messages to the CHILD subnet.
         let topdown_checkpoint = TopDownCheckpoint {
             epoch,
@@ -128,19 +187,20 @@ impl<P: TopDownHandler, C: TopDownHandler> CheckpointManager for TopDownManager<
 
         log::info!("top down checkpoint to submit: {topdown_checkpoint:?}");
 
-        let submitted_epoch = self
+        let submission = self
             .child_handler
             .submit(validator, topdown_checkpoint)
             .await?;
 
         log::info!(
-            "checkpoint at epoch {:} for manager: {:} published with at epoch: {:?}, executed",
-            epoch,
-            self,
-            submitted_epoch,
+            "checkpoint at epoch {epoch:} for manager: {self:} published with: {submission:}"
         );
 
-        Ok(())
+        Ok(submission)
+    }
+
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch> {
+        self.child_handler.wait_submission(submission).await
     }
 
     async fn should_submit_in_epoch(&self, validator: &Address, epoch: ChainEpoch) -> Result<bool> {
@@ -0,0 +1,110 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Caches the validator set of a checkpoint manager.
+//!
+//! Fetching the validators on every run is expensive, so they are fetched again only when the
+//! version of the validator set in the subnet actor changes or the cached set is older than the
+//! configured TTL. Validators joining or leaving the subnet are logged and published as a
+//! `validator_set_changed` event.
+
+use crate::checkpoint::CheckpointManager;
+use crate::events::{EventBus, EventTopic};
+use anyhow::Result;
+use fvm_shared::address::Address;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// The default time a validator set is cached for, even if its version did not change.
+pub const DEFAULT_VALIDATOR_CACHE_TTL: Duration = Duration::from_secs(300);
+
+struct CachedValidators {
+    version: u64,
+    validators: Vec<Address>,
+    fetched_at: Instant,
+}
+
+/// The validator set of a single checkpoint manager.
+pub(crate) struct ValidatorCache {
+    ttl: Duration,
+    cached: Option<CachedValidators>,
+}
+
+impl ValidatorCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self { ttl, cached: None }
+    }
+
+    /// Returns the validators of the manager, fetching them only if the cached set is stale.
+    pub(crate) async fn validators(
+        &mut self,
+        manager: &dyn CheckpointManager,
+        events: &EventBus,
+    ) -> Result<Vec<Address>> {
+        let version = manager.validator_set_version().await?;
+        if let Some(cached) = &self.cached {
+            if cached.version == version && cached.fetched_at.elapsed() < self.ttl {
+                return Ok(cached.validators.clone());
+            }
+        }
+
+        let validators = manager.validators().await?;
+        if let Some(cached) = &self.cached {
+            let (joined, left) = diff_validators(&cached.validators, &validators);
+            for v in &joined {
+                log::info!("validator {v:} joined in manager: {manager:}, validator set version: {version:}");
+            }
+            for v in &left {
+                log::info!(
+                    "validator {v:} left in manager: {manager:}, validator set version: {version:}"
+                );
+            }
+            if !joined.is_empty() || !left.is_empty() {
+                events.publish(
+                    EventTopic::ValidatorSetChanged,
+                    Some(&manager.child_subnet().id),
+                    serde_json::json!({
+                        "version": version,
+                        "joined": joined.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                        "left": left.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                    }),
+                );
+            }
+        }
+
+        self.cached = Some(CachedValidators {
+            version,
+            validators: validators.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(validators)
+    }
+}
+
+/// Returns the validators that joined and left when the validator set changed from `old` to `new`.
+fn diff_validators(old: &[Address], new: &[Address]) -> (Vec<Address>, Vec<Address>) {
+    let old_set: HashSet<_> = old.iter().collect();
+    let new_set: HashSet<_> = new.iter().collect();
+
+    let joined = new.iter().filter(|v| !old_set.contains(v)).cloned();
+    let left = old.iter().filter(|v| !new_set.contains(v)).cloned();
+    (joined.collect(), left.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::validators::diff_validators;
+    use fvm_shared::address::Address;
+
+    #[test]
+    fn test_diff_validators() {
+        let (a, b, c) = (Address::new_id(1), Address::new_id(2), Address::new_id(3));
+
+        let (joined, left) = diff_validators(&[a, b], &[b, c]);
+        assert_eq!(joined, vec![c]);
+        assert_eq!(left, vec![a]);
+
+        let (joined, left) = diff_validators(&[a, b], &[b, a]);
+        assert!(joined.is_empty());
+        assert!(left.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Runs every checkpoint manager in its own supervised task.
+//!
+//! A worker waits for the head of the subnet its manager reads the current epoch from to cross the
+//! next epoch to vote on, and then submits the checkpoints. Errors are retried with an exponential
+//! backoff local to the worker, so a slow or unreachable endpoint only delays the managers using
+//! it. If a worker panics, its supervisor restarts it.
+
+use crate::checkpoint::{
+    head, submit_till_current_epoch, CheckpointJournal, CheckpointManager,
+    CheckpointStatusRegistry, ValidatorCache,
+};
+use crate::events::EventBus;
+use crate::metrics;
+use anyhow::anyhow;
+use futures_util::FutureExt;
+use fvm_shared::clock::ChainEpoch;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout};
+
+/// The delay before the first retry of a failed run.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// The maximum delay between retries of a failed run.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Spawns a supervised worker for each manager. Managers reading their current epoch from the same
+/// subnet share the same chain head watcher. All the workers are aborted when the returned set is
+/// dropped.
+pub(crate) fn spawn_workers(
+    managers: Vec<Box<dyn CheckpointManager>>,
+    journal: Arc<CheckpointJournal>,
+    status: Arc<CheckpointStatusRegistry>,
+    events: Arc<EventBus>,
+    validator_cache_ttl: Duration,
+) -> JoinSet<()> {
+    status.reset(&managers);
+    metrics::reset_checkpoint_epochs();
+
+    let mut watchers = HashMap::new();
+    let mut workers = JoinSet::new();
+
+    for manager in managers {
+        let source = manager.source_subnet();
+        let heads = watchers
+            .entry(source.id.clone())
+            .or_insert_with(|| head::watch_chain_head(source))
+            .clone();
+        workers.spawn(supervise(
+            Arc::from(manager),
+            heads,
+            journal.clone(),
+            status.clone(),
+            events.clone(),
+            validator_cache_ttl,
+        ));
+    }
+
+    workers
+}
+
+/// Runs the worker of the manager, restarting it with an exponential backoff whenever it panics.
+async fn supervise(
+    manager: Arc<dyn CheckpointManager>,
+    heads: watch::Receiver<ChainEpoch>,
+    journal: Arc<CheckpointJournal>,
+    status: Arc<CheckpointStatusRegistry>,
+    events: Arc<EventBus>,
+    validator_cache_ttl: Duration,
+) {
+    let mut backoff = Backoff::default();
+    loop {
+        let worker = Worker::new(
+            manager.clone(),
+            heads.clone(),
+            journal.clone(),
+            status.clone(),
+            events.clone(),
+            ValidatorCache::new(validator_cache_ttl),
+        );
+        if AssertUnwindSafe(worker.run()).catch_unwind().await.is_ok() {
+            return;
+        }
+
+        let delay = backoff.next_delay();
+        log::error!("checkpoint worker of manager: {manager:} panicked, restarting in {delay:?}");
+        status.set_error(&manager.to_string(), &anyhow!("checkpoint worker panicked"));
+        metrics::inc_checkpoint_failures(manager.as_ref());
+        sleep(delay).await;
+    }
+}
+
+/// Submits the checkpoints of a single manager as new heads arrive in its subnet.
+struct Worker {
+    manager: Arc<dyn CheckpointManager>,
+    /// The latest epoch of the subnet the manager reads its current epoch from.
+    heads: watch::Receiver<ChainEpoch>,
+    journal: Arc<CheckpointJournal>,
+    status: Arc<CheckpointStatusRegistry>,
+    events: Arc<EventBus>,
+    validators: ValidatorCache,
+    /// The next epoch the manager plans to vote on. It is `None` if the last run failed or the
+    /// subnet was not ready for submissions.
+    next_epoch: Option<ChainEpoch>,
+    backoff: Backoff,
+}
+
+impl Worker {
+    fn new(
+        manager: Arc<dyn CheckpointManager>,
+        heads: watch::Receiver<ChainEpoch>,
+        journal: Arc<CheckpointJournal>,
+        status: Arc<CheckpointStatusRegistry>,
+        events: Arc<EventBus>,
+        validators: ValidatorCache,
+    ) -> Self {
+        Self {
+            manager,
+            heads,
+            journal,
+            status,
+            events,
+            validators,
+            next_epoch: None,
+            backoff: Backoff::default(),
+        }
+    }
+
+    async fn run(mut self) {
+        let policy = self.manager.child_subnet().checkpointing_policy();
+        let threshold = Duration::from_secs(policy.polling_interval_secs);
+
+        loop {
+            self.wait_for_next_epoch(policy.submission_delay_epochs, threshold)
+                .await;
+
+            let response = submit_till_current_epoch(
+                self.manager.as_ref(),
+                &self.journal,
+                &self.status,
+                &self.events,
+                &mut self.validators,
+            )
+            .await;
+            match response {
+                Ok(next_epoch) => {
+                    self.backoff.reset();
+                    self.next_epoch = next_epoch;
+                    let head = *self.heads.borrow_and_update();
+                    match next_epoch {
+                        // the subnet is not ready yet, check again later.
+                        None => sleep(threshold).await,
+                        // the look-ahead stopped the run short of the head, nothing changes until
+                        // the pending checkpoints are executed.
+                        Some(epoch) if head > epoch + policy.submission_delay_epochs => {
+                            self.wait_for_new_head(threshold).await
+                        }
+                        Some(_) => {}
+                    }
+                }
+                Err(e) => {
+                    let delay = self.backoff.next_delay();
+                    log::warn!(
+                        "manager {:} had error: {e:}, retrying in {delay:?}",
+                        self.manager
+                    );
+                    self.status.set_error(&self.manager.to_string(), &e);
+                    metrics::inc_checkpoint_failures(self.manager.as_ref());
+                    self.next_epoch = None;
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Waits for a new head of the subnet, or at most `threshold`.
+    async fn wait_for_new_head(&mut self, threshold: Duration) {
+        if let Ok(Err(_)) = timeout(threshold, self.heads.changed()).await {
+            // the watcher of the subnet stopped, fall back to running periodically.
+            sleep(threshold).await;
+        }
+    }
+
+    /// Waits until the head of the subnet crosses the next epoch to vote on, plus the submission
+    /// delay. It returns right away if that epoch is not known.
+    async fn wait_for_next_epoch(&mut self, delay: ChainEpoch, threshold: Duration) {
+        let next_epoch = match self.next_epoch {
+            Some(epoch) => epoch + delay,
+            None => return,
+        };
+
+        loop {
+            let head = *self.heads.borrow();
+            if head > next_epoch {
+                return;
+            }
+            if self.heads.changed().await.is_err() {
+                // the watcher of the subnet stopped, fall back to running periodically.
+                sleep(threshold).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Exponential backoff between `MIN_RETRY_DELAY` and `MAX_RETRY_DELAY`.
+struct Backoff {
+    delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            delay: MIN_RETRY_DELAY,
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns the delay before the next retry and doubles the following one.
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = std::cmp::min(self.delay * 2, MAX_RETRY_DELAY);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.delay = MIN_RETRY_DELAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::worker::{Backoff, MAX_RETRY_DELAY, MIN_RETRY_DELAY};
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::default();
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY);
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY * 2);
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY * 4);
+
+        for _ in 0..20 {
+            backoff.next_delay();
+        }
+        assert_eq!(backoff.next_delay(), MAX_RETRY_DELAY);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY);
+    }
+}
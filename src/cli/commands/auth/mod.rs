@@ -0,0 +1,34 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Json rpc authentication cli commands
+
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use clap::{Args, Subcommand};
+
+use self::new_token::{NewToken, NewTokenArgs};
+
+mod new_token;
+
+#[derive(Debug, Args)]
+#[command(
+    name = "auth",
+    about = "commands to manage the tokens authenticating the json rpc requests"
+)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct AuthCommandsArgs {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+impl AuthCommandsArgs {
+    pub async fn handle(&self, global: &GlobalArguments) -> anyhow::Result<()> {
+        match &self.command {
+            Commands::NewToken(args) => NewToken::handle(global, args).await,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Commands {
+    NewToken(NewTokenArgs),
+}
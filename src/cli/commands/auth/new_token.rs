@@ -0,0 +1,53 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The command to mint a json rpc token
+
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::ReloadableConfig;
+use crate::server::auth::{JwtAuth, Permission};
+use async_trait::async_trait;
+use clap::Args;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Mints a token signed with the jwt secret of the agent. The command reads the secret from the
+/// agent repo directly, so it has to run on the host of the agent.
+pub(crate) struct NewToken;
+
+#[async_trait]
+impl CommandLineHandler for NewToken {
+    type Arguments = NewTokenArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("new token with args: {:?}", arguments);
+
+        let config = ReloadableConfig::new(global.config_path())?;
+        let auth = JwtAuth::from_config(&config)?;
+
+        let expires_at = match arguments.expires_in {
+            Some(secs) => Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + secs),
+            None => None,
+        };
+        let token = auth.create_token(arguments.permission, expires_at)?;
+
+        println!("{token:}");
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Mint a token to authenticate the json rpc requests")]
+pub(crate) struct NewTokenArgs {
+    #[arg(
+        long,
+        short,
+        default_value = "read",
+        help = "The permission granted by the token, along with the lower ones: read, write, sign or admin"
+    )]
+    pub permission: Permission,
+    #[arg(
+        long,
+        short,
+        help = "The number of seconds the token is valid, forever if not set"
+    )]
+    pub expires_in: Option<u64>,
+}
@@ -0,0 +1,68 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Audit bottom-up checkpoints cli command
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+
+/// The command to audit the chain of bottom-up checkpoints committed by a subnet.
+pub(crate) struct AuditBottomUpCheckpoints;
+
+#[async_trait]
+impl CommandLineHandler for AuditBottomUpCheckpoints {
+    type Arguments = AuditBottomUpCheckpointsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("audit bottom-up checkpoints with args: {:?}", arguments);
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let report = client
+            .audit_bottom_up_checkpoints(
+                &arguments.subnet,
+                arguments.from_epoch,
+                arguments.to_epoch,
+            )
+            .await?;
+
+        log::info!(
+            "audited {} checkpoints from epoch {} to {} with period {}",
+            report.checkpoints,
+            report.from_epoch,
+            report.to_epoch,
+            report.period
+        );
+        if !report.proofs_verified {
+            log::warn!("child subnet not in config, checkpoint proofs were not verified");
+        }
+
+        if report.is_intact() {
+            log::info!("checkpoint chain is intact");
+        } else {
+            for issue in report.issues.iter() {
+                log::warn!("{}", serde_json::to_string(issue)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Audit the chain of bottom-up checkpoints committed by a subnet")]
+pub(crate) struct AuditBottomUpCheckpointsArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "The subnet id of the checkpointing subnet")]
+    pub subnet: String,
+    #[arg(long, short, help = "Audit checkpoints from this epoch")]
+    pub from_epoch: ChainEpoch,
+    #[arg(long, short, help = "Audit checkpoints up to this epoch")]
+    pub to_epoch: ChainEpoch,
+}
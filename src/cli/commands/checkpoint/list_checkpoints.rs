@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use clap::Args;
 use fvm_shared::clock::ChainEpoch;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 
@@ -22,8 +22,7 @@ impl CommandLineHandler for ListBottomUpCheckpoints {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("list checkpoints with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
         let checkpoints = client
             .list_bottom_up_checkpoints(&arguments.subnet, arguments.from_epoch, arguments.to_epoch)
             .await?;
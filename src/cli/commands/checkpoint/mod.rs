@@ -6,9 +6,15 @@ use crate::cli::commands::checkpoint::list_checkpoints::{
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use clap::{Args, Subcommand};
 
+use self::audit_bottomup::{AuditBottomUpCheckpoints, AuditBottomUpCheckpointsArgs};
+use self::preview_bottomup::{PreviewBottomUpCheckpoint, PreviewBottomUpCheckpointArgs};
+use self::status::{CheckpointStatus, CheckpointStatusArgs};
 use self::topdown_executed::{LastTopDownExec, LastTopDownExecArgs};
 
+mod audit_bottomup;
 mod list_checkpoints;
+mod preview_bottomup;
+mod status;
 mod topdown_executed;
 
 #[derive(Debug, Args)]
@@ -24,6 +30,11 @@ impl CheckpointCommandsArgs {
         match &self.command {
             Commands::ListBottomup(args) => ListBottomUpCheckpoints::handle(global, args).await,
             Commands::LastTopdown(args) => LastTopDownExec::handle(global, args).await,
+            Commands::Status(args) => CheckpointStatus::handle(global, args).await,
+            Commands::PreviewBottomup(args) => {
+                PreviewBottomUpCheckpoint::handle(global, args).await
+            }
+            Commands::AuditBottomup(args) => AuditBottomUpCheckpoints::handle(global, args).await,
         }
     }
 }
@@ -32,4 +43,7 @@ impl CheckpointCommandsArgs {
 pub(crate) enum Commands {
     ListBottomup(ListBottomUpCheckpointsArgs),
     LastTopdown(LastTopDownExecArgs),
+    Status(CheckpointStatusArgs),
+    PreviewBottomup(PreviewBottomUpCheckpointArgs),
+    AuditBottomup(AuditBottomUpCheckpointsArgs),
 }
@@ -0,0 +1,49 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Preview bottom-up checkpoint cli command
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+
+/// The command to build the bottom-up checkpoint of a subnet in an epoch without submitting it.
+pub(crate) struct PreviewBottomUpCheckpoint;
+
+#[async_trait]
+impl CommandLineHandler for PreviewBottomUpCheckpoint {
+    type Arguments = PreviewBottomUpCheckpointArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("preview bottom-up checkpoint with args: {:?}", arguments);
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let checkpoint = client
+            .preview_bottom_up_checkpoint(&arguments.subnet, arguments.epoch)
+            .await?;
+
+        log::info!(
+            "bottom-up checkpoint at epoch {}:\n{}",
+            arguments.epoch,
+            serde_json::to_string_pretty(&checkpoint)?
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Build the bottom-up checkpoint of a subnet in an epoch without submitting it")]
+pub(crate) struct PreviewBottomUpCheckpointArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "The subnet id of the checkpointing subnet")]
+    pub subnet: String,
+    #[arg(long, short, help = "The epoch of the checkpoint")]
+    pub epoch: ChainEpoch,
+}
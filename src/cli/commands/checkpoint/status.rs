@@ -0,0 +1,72 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Checkpoint status cli command
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use clap::Args;
+use fvm_shared::clock::ChainEpoch;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+
+/// The command to show the status of the checkpoint managers run by the agent daemon.
+pub(crate) struct CheckpointStatus;
+
+#[async_trait]
+impl CommandLineHandler for CheckpointStatus {
+    type Arguments = CheckpointStatusArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("checkpoint status with args: {:?}", arguments);
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let managers = client.checkpoint_status(arguments.subnet.clone()).await?;
+
+        if managers.is_empty() {
+            log::info!("no active checkpoint managers");
+        }
+
+        for m in managers.iter() {
+            log::info!(
+                "{} - parent: {}, child: {}, period: {}, last executed epoch: {}, current epoch: {}, next epoch: {}, validators: {:?}",
+                m.direction,
+                m.parent,
+                m.child,
+                m.period,
+                display_epoch(m.last_executed_epoch),
+                display_epoch(m.current_epoch),
+                display_epoch(m.next_epoch),
+                m.validators
+            );
+            if let Some(e) = &m.last_error {
+                log::info!(
+                    "    last error at {} (unix time): {}",
+                    e.timestamp,
+                    e.message
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn display_epoch(epoch: Option<ChainEpoch>) -> String {
+    epoch.map_or_else(|| String::from("unknown"), |e| e.to_string())
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the status of the checkpoint managers in the agent daemon")]
+pub(crate) struct CheckpointStatusArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(
+        long,
+        short,
+        help = "Only show the managers with this subnet as parent or child"
+    )]
+    pub subnet: Option<String>,
+}
@@ -7,7 +7,7 @@ use std::fmt::Debug;
 use async_trait::async_trait;
 use clap::Args;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 
@@ -21,8 +21,7 @@ impl CommandLineHandler for LastTopDownExec {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("last topdown exec with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
         let epoch = client.last_top_down_executed(&arguments.subnet).await?;
 
         log::info!("Last top-down checkpoint executed in epoch: {epoch:}");
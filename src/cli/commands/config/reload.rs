@@ -1,6 +1,6 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 use async_trait::async_trait;
@@ -16,8 +16,7 @@ impl CommandLineHandler for ReloadConfig {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("reload config with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
 
         client.reload_config(arguments.path.clone()).await?;
 
@@ -6,7 +6,8 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::commands::transaction::log_transaction;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 
@@ -20,8 +21,20 @@ impl CommandLineHandler for Release {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("release operation with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        if arguments.asynchronous {
+            let transaction = client
+                .release_async(
+                    &arguments.subnet,
+                    arguments.from.clone(),
+                    arguments.to.clone(),
+                    arguments.amount,
+                )
+                .await?;
+            log_transaction(&transaction);
+            return Ok(());
+        }
+
         let epoch = client
             .release(
                 &arguments.subnet,
@@ -54,4 +67,9 @@ pub(crate) struct ReleaseArgs {
     pub subnet: String,
     #[arg(help = "The amount to release in FIL, in whole FIL")]
     pub amount: f64,
+    #[arg(
+        long = "async",
+        help = "Return as soon as the message is submitted, with the id to query its status"
+    )]
+    pub asynchronous: bool,
 }
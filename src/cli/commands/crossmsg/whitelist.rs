@@ -7,10 +7,10 @@ use cid::Cid;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::server::whitelist::WhitelistPropagatorParams;
 
 /// The command to whitelist a propagator for a message in a postbox
@@ -23,8 +23,7 @@ impl CommandLineHandler for WhitelistPropagator {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("whitelist operation with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(&arguments.ipc_agent_url, global)?;
 
         let params = WhitelistPropagatorParams {
             subnet: arguments.subnet.clone(),
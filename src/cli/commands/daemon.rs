@@ -11,11 +11,17 @@ use clap::Args;
 use ipc_identity::Wallet;
 use tokio_graceful_shutdown::{IntoSubsystem, Toplevel};
 
-use crate::checkpoint::CheckpointSubsystem;
+use crate::checkpoint::{
+    CheckpointJournal, CheckpointStatusRegistry, CheckpointSubsystem, DEFAULT_VALIDATOR_CACHE_TTL,
+};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::ReloadableConfig;
+use crate::events::EventBus;
+use crate::manager::NonceManager;
+use crate::relayer::{CrossMsgRelayerSubsystem, RelayerJournal};
 use crate::server::jsonrpc::JsonRPCServer;
 use crate::server::{new_evm_keystore_from_config, new_fvm_wallet_from_config};
+use crate::tracker::TransactionTracker;
 
 /// The number of seconds to wait for a subsystem to start before returning an error.
 const SUBSYSTEM_WAIT_TIME_SECS: Duration = Duration::from_secs(10);
@@ -34,7 +40,10 @@ impl CommandLineHandler for LaunchDaemon {
             global
         );
 
-        let reloadable_config = Arc::new(ReloadableConfig::new(global.config_path())?);
+        // the events of the subsystems, pushed to the websocket subscribers of the server.
+        let events = Arc::new(EventBus::new());
+        let reloadable_config =
+            Arc::new(ReloadableConfig::new(global.config_path())?.with_event_bus(events.clone()));
         let fvm_wallet = Arc::new(RwLock::new(Wallet::new(new_fvm_wallet_from_config(
             reloadable_config.clone(),
         )?)));
@@ -42,19 +51,42 @@ impl CommandLineHandler for LaunchDaemon {
             reloadable_config.clone(),
         )?));
 
+        let journal = Arc::new(CheckpointJournal::from_config(&reloadable_config)?);
+        let checkpoint_status = Arc::new(CheckpointStatusRegistry::new());
+        let relayer_journal = Arc::new(RelayerJournal::from_config(&reloadable_config)?);
+        // the subsystems send messages from the same accounts, their nonces are assigned together.
+        let nonces = Arc::new(NonceManager::new());
+        let tracker = Arc::new(TransactionTracker::from_config(&reloadable_config)?);
+
         // Start subsystems.
         let checkpointing = CheckpointSubsystem::new(
             reloadable_config.clone(),
             fvm_wallet.clone(),
             evm_keystore.clone(),
+            journal,
+            checkpoint_status.clone(),
+            Duration::from_secs(arguments.validator_cache_ttl),
+            nonces.clone(),
+            events.clone(),
+        );
+        let relayer = CrossMsgRelayerSubsystem::new(
+            reloadable_config.clone(),
+            fvm_wallet.clone(),
+            relayer_journal,
+            nonces.clone(),
         );
         let server = JsonRPCServer::new(
             reloadable_config.clone(),
             fvm_wallet.clone(),
             evm_keystore.clone(),
+            checkpoint_status,
+            nonces,
+            tracker,
+            events,
         );
         Toplevel::new()
             .start("Checkpoint subsystem", checkpointing.into_subsystem())
+            .start("Cross-message relayer subsystem", relayer.into_subsystem())
             .start("JSON-RPC server subsystem", server.into_subsystem())
             .catch_signals()
             .handle_shutdown_requests(SUBSYSTEM_WAIT_TIME_SECS)
@@ -66,4 +98,11 @@ impl CommandLineHandler for LaunchDaemon {
 
 #[derive(Debug, Args)]
 #[command(about = "Launch the ipc agent daemon process")]
-pub(crate) struct LaunchDaemonArgs {}
+pub(crate) struct LaunchDaemonArgs {
+    #[arg(
+        long,
+        default_value_t = DEFAULT_VALIDATOR_CACHE_TTL.as_secs(),
+        help = "The maximum number of seconds the validator set of a subnet is cached for"
+    )]
+    pub validator_cache_ttl: u64,
+}
@@ -2,24 +2,33 @@
 // SPDX-License-Identifier: MIT
 //! This mod contains the different command line implementations.
 
+mod auth;
 mod checkpoint;
 mod config;
 mod crossmsg;
 mod daemon;
 mod subnet;
+mod transaction;
 mod util;
 pub mod wallet;
 
+use crate::cli::commands::auth::AuthCommandsArgs;
 use crate::cli::commands::checkpoint::CheckpointCommandsArgs;
 use crate::cli::commands::crossmsg::CrossMsgsCommandsArgs;
 use crate::cli::commands::daemon::{LaunchDaemon, LaunchDaemonArgs};
+use crate::cli::commands::transaction::TransactionCommandsArgs;
 use crate::cli::commands::util::UtilCommandsArgs;
 use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::UNIX_SOCKET_SCHEME;
+use crate::jsonrpc::JsonRpcClientImpl;
+use crate::server::auth::{JwtAuth, Permission, JWT_SECRET_FILE_NAME};
 use crate::server::{new_evm_keystore_from_path, new_keystore_from_path};
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use ipc_identity::{KeyStore, PersistentKeyStore};
 use std::fmt::Debug;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use subnet::SubnetCommandsArgs;
 use url::Url;
 
@@ -30,6 +39,9 @@ pub use subnet::*;
 
 use super::default_repo_path;
 
+/// The validity of the tokens minted by the CLI from the jwt secret of the agent.
+const LOCAL_TOKEN_TTL_SECS: u64 = 300;
+
 /// The collection of all subcommands to be called, see clap's documentation for usage. Internal
 /// to the current mode. Register a new command accordingly.
 #[derive(Debug, Subcommand)]
@@ -45,6 +57,8 @@ enum Commands {
     Wallet(WalletCommandsArgs),
     CrossMsg(CrossMsgsCommandsArgs),
     Checkpoint(CheckpointCommandsArgs),
+    Transaction(TransactionCommandsArgs),
+    Auth(AuthCommandsArgs),
     Util(UtilCommandsArgs),
 }
 #[derive(Debug, Parser)]
@@ -101,6 +115,8 @@ pub async fn cli() -> anyhow::Result<()> {
         Commands::CrossMsg(args) => args.handle(global).await,
         Commands::Wallet(args) => args.handle(global).await,
         Commands::Checkpoint(args) => args.handle(global).await,
+        Commands::Transaction(args) => args.handle(global).await,
+        Commands::Auth(args) => args.handle(global).await,
         Commands::Util(args) => args.handle(global).await,
     };
 
@@ -115,16 +131,55 @@ pub(crate) fn get_ipc_agent_url(
         Some(url) => url.parse()?,
         None => {
             let config = global.config()?;
-            let addr = config.server.json_rpc_address.to_string();
-            // We are resolving back to our own ipc-agent node.
-            // Since it's our own node, we will use http since we
-            // should be in the same network.
-            format!("http://{addr:}/json_rpc").parse()?
+            // We are resolving back to our own ipc-agent node, through its unix socket if it
+            // listens on one, or using https if it serves the json rpc api over tls.
+            config.server.json_rpc_url()?
         }
     };
     Ok(url)
 }
 
+/// The json rpc client of the ipc agent, sending the token and using the tls settings of the
+/// global arguments. Without a token, the client mints one with the jwt secret of the agent repo
+/// if it can read it, i.e. if it runs on the host of the agent.
+pub(crate) fn get_ipc_agent_client(
+    ipc_agent_url: &Option<String>,
+    global: &GlobalArguments,
+) -> Result<JsonRpcClientImpl> {
+    let url = get_ipc_agent_url(ipc_agent_url, global)?;
+    let token = match global.token() {
+        Some(token) => Some(token.to_string()),
+        None => local_token(global),
+    };
+    let tls = global.client_tls();
+    if url.scheme() != UNIX_SOCKET_SCHEME && (url.scheme() == "https" || !tls.is_empty()) {
+        JsonRpcClientImpl::new_with_tls(url, token.as_deref(), &tls)
+    } else {
+        Ok(JsonRpcClientImpl::new(url, token.as_deref()))
+    }
+}
+
+/// Mints a short-lived admin token with the jwt secret in the repo of the config, `None` if the
+/// secret cannot be read.
+fn local_token(global: &GlobalArguments) -> Option<String> {
+    let config_path = global.config_path();
+    let path = Path::new(&config_path).parent()?.join(JWT_SECRET_FILE_NAME);
+    if !path.exists() {
+        return None;
+    }
+
+    let auth = match JwtAuth::load(&path) {
+        Ok(auth) => auth,
+        Err(e) => {
+            log::debug!("cannot load the jwt secret {path:?}: {e:}");
+            return None;
+        }
+    };
+    let expires_at =
+        SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() + LOCAL_TOKEN_TTL_SECS;
+    auth.create_token(Permission::Admin, Some(expires_at)).ok()
+}
+
 pub(crate) fn get_fvm_store(path: Option<String>) -> Result<KeyStore> {
     let path = match path {
         Some(p) => p,
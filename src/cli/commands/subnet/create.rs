@@ -7,10 +7,12 @@ use clap::Args;
 use fvm_shared::clock::ChainEpoch;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::commands::transaction::log_transaction;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 use crate::server::create::CreateSubnetParams;
+use crate::tracker::TrackedTransaction;
 
 /// The command to create a new subnet actor.
 pub struct CreateSubnet;
@@ -20,8 +22,21 @@ impl CreateSubnet {
         global: &GlobalArguments,
         arguments: &CreateSubnetArgs,
     ) -> anyhow::Result<String> {
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let params = CreateSubnetParams {
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        client.create_subnet(Self::params(arguments)).await
+    }
+
+    /// Submits the creation of the subnet without waiting for its execution.
+    pub async fn create_async(
+        global: &GlobalArguments,
+        arguments: &CreateSubnetArgs,
+    ) -> anyhow::Result<TrackedTransaction> {
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        client.create_subnet_async(Self::params(arguments)).await
+    }
+
+    fn params(arguments: &CreateSubnetArgs) -> CreateSubnetParams {
+        CreateSubnetParams {
             from: arguments.from.clone(),
             parent: arguments.parent.clone(),
             name: arguments.name.clone(),
@@ -29,10 +44,8 @@ impl CreateSubnet {
             min_validators: arguments.min_validators,
             bottomup_check_period: arguments.bottomup_check_period,
             topdown_check_period: arguments.topdown_check_period,
-        };
-
-        let client = IpcAgentClient::default_from_url(url);
-        client.create_subnet(params).await
+            asynchronous: arguments.asynchronous,
+        }
     }
 }
 
@@ -43,6 +56,12 @@ impl CommandLineHandler for CreateSubnet {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("create subnet with args: {:?}", arguments);
 
+        if arguments.asynchronous {
+            let transaction = CreateSubnet::create_async(global, arguments).await?;
+            log_transaction(&transaction);
+            return Ok(());
+        }
+
         let address = CreateSubnet::create(global, arguments).await?;
 
         log::info!(
@@ -74,4 +93,9 @@ pub struct CreateSubnetArgs {
     pub bottomup_check_period: ChainEpoch,
     #[arg(long, help = "The top down checkpoint period in number of blocks")]
     pub topdown_check_period: ChainEpoch,
+    #[arg(
+        long = "async",
+        help = "Return as soon as the message is submitted, with the id to query its status"
+    )]
+    pub asynchronous: bool,
 }
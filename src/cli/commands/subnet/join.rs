@@ -6,7 +6,8 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::commands::transaction::log_transaction;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 use crate::server::join::JoinSubnetParams;
@@ -21,8 +22,6 @@ impl CommandLineHandler for JoinSubnet {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("join subnet with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-
         // The json rpc server will handle directing the request to
         // the correct parent.
         let params = JoinSubnetParams {
@@ -31,9 +30,16 @@ impl CommandLineHandler for JoinSubnet {
             collateral: arguments.collateral,
             validator_net_addr: arguments.validator_net_addr.clone(),
             worker_addr: arguments.worker_addr.clone(),
+            asynchronous: arguments.asynchronous,
         };
 
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        if arguments.asynchronous {
+            let transaction = client.join_subnet_async(params).await?;
+            log_transaction(&transaction);
+            return Ok(());
+        }
+
         client.join_subnet(params).await?;
 
         log::info!("joined subnet: {:}", arguments.subnet);
@@ -65,4 +71,9 @@ pub struct JoinSubnetArgs {
         help = "The validator worker address. If not set will be the same as `from`"
     )]
     pub worker_addr: Option<String>,
+    #[arg(
+        long = "async",
+        help = "Return as soon as the message is submitted, with the id to query its status"
+    )]
+    pub asynchronous: bool,
 }
@@ -6,7 +6,7 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::IpcAgentClient;
 use crate::server::leave::LeaveSubnetParams;
@@ -26,9 +26,7 @@ impl CommandLineHandler for LeaveSubnet {
             from: arguments.from.clone(),
         };
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
         client.leave_subnet(params).await?;
 
         log::info!("left subnet: {:}", arguments.subnet);
@@ -10,10 +10,10 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::str::FromStr;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::server::list_subnets::ListSubnetsParams;
 use serde::Deserialize;
 
@@ -27,8 +27,7 @@ impl CommandLineHandler for ListSubnets {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("list subnets with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(&arguments.ipc_agent_url, global)?;
 
         let params = ListSubnetsParams {
             gateway_address: arguments.gateway_address.clone(),
@@ -2,10 +2,10 @@
 // SPDX-License-Identifier: MIT
 //! The command to set the validator net address
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::server::net_addr::SetValidatorNetAddrParams;
 use async_trait::async_trait;
 use clap::Args;
@@ -20,8 +20,7 @@ impl CommandLineHandler for SetValidatorNetAddr {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("set the validator net addr args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(&arguments.ipc_agent_url, global)?;
 
         let params = SetValidatorNetAddrParams {
             subnet: arguments.subnet.clone(),
@@ -8,10 +8,10 @@ use ipc_gateway::SubnetID;
 use std::fmt::Debug;
 use std::str::FromStr;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::server::rpc::RPCSubnetParams;
 
 /// The command to get the RPC endpoint for a subnet
@@ -24,8 +24,7 @@ impl CommandLineHandler for RPCSubnet {
     async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
         log::debug!("get rpc for subnet with args: {:?}", arguments);
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(&arguments.ipc_agent_url, global)?;
 
         let params = RPCSubnetParams {
             subnet: arguments.subnet.clone(),
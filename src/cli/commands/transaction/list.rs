@@ -0,0 +1,67 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! List transactions cli command
+
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::commands::transaction::log_transaction;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+use crate::tracker::TransactionStatus;
+
+/// The command to list the transactions submitted asynchronously.
+pub(crate) struct ListTransactions;
+
+#[async_trait]
+impl CommandLineHandler for ListTransactions {
+    type Arguments = ListTransactionsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("list transactions with args: {:?}", arguments);
+
+        let status = arguments
+            .status
+            .as_deref()
+            .map(TransactionStatus::from_str)
+            .transpose()?;
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let transactions = client
+            .list_transactions(arguments.subnet.clone(), status, arguments.limit)
+            .await?;
+
+        if transactions.is_empty() {
+            log::info!("no transactions found");
+        }
+        for t in transactions.iter() {
+            log_transaction(t);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "List the transactions submitted asynchronously, the most recent first")]
+pub(crate) struct ListTransactionsArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(
+        long,
+        short,
+        help = "Only list the transactions submitted to this subnet"
+    )]
+    pub subnet: Option<String>,
+    #[arg(
+        long,
+        help = "Only list the transactions with this status: pending, completed, failed or interrupted"
+    )]
+    pub status: Option<String>,
+    #[arg(long, short, help = "The maximum number of transactions to list")]
+    pub limit: Option<usize>,
+}
@@ -0,0 +1,58 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Tracked transactions cli commands
+
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::tracker::TrackedTransaction;
+use clap::{Args, Subcommand};
+
+use self::list::{ListTransactions, ListTransactionsArgs};
+use self::status::{GetTransactionStatus, TransactionStatusArgs};
+
+mod list;
+mod status;
+
+#[derive(Debug, Args)]
+#[command(
+    name = "transaction",
+    about = "commands to query the transactions submitted asynchronously"
+)]
+#[command(args_conflicts_with_subcommands = true)]
+pub(crate) struct TransactionCommandsArgs {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+impl TransactionCommandsArgs {
+    pub async fn handle(&self, global: &GlobalArguments) -> anyhow::Result<()> {
+        match &self.command {
+            Commands::Status(args) => GetTransactionStatus::handle(global, args).await,
+            Commands::List(args) => ListTransactions::handle(global, args).await,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Commands {
+    Status(TransactionStatusArgs),
+    List(ListTransactionsArgs),
+}
+
+/// Logs the record of a transaction submitted asynchronously.
+pub(crate) fn log_transaction(t: &TrackedTransaction) {
+    log::info!(
+        "transaction {} - method: {}, subnet: {}, from: {}, status: {}, submissions: {:?}",
+        t.id,
+        t.method,
+        t.subnet,
+        t.from,
+        t.status,
+        t.submissions
+    );
+    if let Some(result) = &t.result {
+        log::info!("    result: {result:}");
+    }
+    if let Some(error) = &t.error {
+        log::info!("    error: {error:}");
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Transaction status cli command
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::commands::transaction::log_transaction;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+
+/// The command to show the record of a transaction submitted asynchronously.
+pub(crate) struct GetTransactionStatus;
+
+#[async_trait]
+impl CommandLineHandler for GetTransactionStatus {
+    type Arguments = TransactionStatusArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("transaction status with args: {:?}", arguments);
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let transaction = client.get_transaction_status(arguments.id).await?;
+
+        log_transaction(&transaction);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the status of a transaction submitted asynchronously")]
+pub(crate) struct TransactionStatusArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(help = "The tracking id of the transaction")]
+    pub id: u64,
+}
@@ -5,8 +5,10 @@ use crate::cli::{CommandLineHandler, GlobalArguments};
 use clap::{Args, Subcommand};
 
 use self::f4::{EthToF4Addr, EthToF4AddrArgs};
+use self::openrpc::{OpenRpc, OpenRpcArgs};
 
 mod f4;
+mod openrpc;
 
 #[derive(Debug, Args)]
 #[command(name = "util", about = "util commands")]
@@ -20,6 +22,7 @@ impl UtilCommandsArgs {
     pub async fn handle(&self, global: &GlobalArguments) -> anyhow::Result<()> {
         match &self.command {
             Commands::EthToF4Addr(args) => EthToF4Addr::handle(global, args).await,
+            Commands::OpenRpc(args) => OpenRpc::handle(global, args).await,
         }
     }
 }
@@ -27,4 +30,5 @@ impl UtilCommandsArgs {
 #[derive(Debug, Subcommand)]
 pub(crate) enum Commands {
     EthToF4Addr(EthToF4AddrArgs),
+    OpenRpc(OpenRpcArgs),
 }
@@ -0,0 +1,45 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Dump the OpenRPC document of the agent
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::get_ipc_agent_client;
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::sdk::IpcAgentClient;
+
+/// The command to print the OpenRPC document served by the agent
+pub(crate) struct OpenRpc;
+
+#[async_trait]
+impl CommandLineHandler for OpenRpc {
+    type Arguments = OpenRpcArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("dump openrpc document with args: {:?}", arguments);
+
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
+        let document = serde_json::to_string_pretty(&client.discover().await?)?;
+
+        match &arguments.output {
+            Some(path) => {
+                std::fs::write(path, document)?;
+                log::info!("OpenRPC document written to {path:}");
+            }
+            None => println!("{document:}"),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Print the OpenRPC document of the IPC Agent JSON RPC methods")]
+pub(crate) struct OpenRpcArgs {
+    #[arg(short, long, help = "The file to write the document to, optional")]
+    pub output: Option<String>,
+    #[arg(short, long, help = "The JSON RPC server url for ipc agent, optional")]
+    pub ipc_agent_url: Option<String>,
+}
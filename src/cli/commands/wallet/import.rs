@@ -7,7 +7,7 @@ use clap::Args;
 use std::fmt::Debug;
 use std::str::FromStr;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::get_ipc_agent_client;
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::sdk::{IpcAgentClient, LotusJsonKeyType};
 use crate::server::wallet::WalletType;
@@ -23,8 +23,7 @@ impl CommandLineHandler for WalletImport {
 
         let wallet_type = WalletType::from_str(&arguments.wallet_type)?;
 
-        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let client = IpcAgentClient::default_from_url(url);
+        let client = IpcAgentClient::new(get_ipc_agent_client(&arguments.ipc_agent_url, global)?);
 
         let addr = if matches!(wallet_type, WalletType::Evm) && let Some(key) = &arguments.private_key {
             let p = if let Some(stripped) = key.strip_prefix("0x") { stripped } else { key };
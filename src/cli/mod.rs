@@ -7,6 +7,7 @@ use clap::Args;
 mod commands;
 
 use crate::config::Config;
+use crate::jsonrpc::ClientTls;
 pub use commands::*;
 
 const DEFAULT_REPO_PATH: &str = ".ipc-agent";
@@ -39,6 +40,24 @@ pub struct GlobalArguments {
         help = "The toml config file path for IPC Agent, default to ${HOME}/.ipc-agent/config.toml"
     )]
     config_path: Option<String>,
+    #[arg(
+        long,
+        env = "IPC_AGENT_TOKEN",
+        help = "The token authenticating the requests to the IPC Agent json rpc server"
+    )]
+    token: Option<String>,
+    #[arg(
+        long,
+        env = "IPC_AGENT_TLS_CA",
+        help = "The PEM file with the CA certificate of the IPC Agent json rpc server"
+    )]
+    tls_ca: Option<String>,
+    #[arg(
+        long,
+        env = "IPC_AGENT_TLS_IDENTITY",
+        help = "The PEM file with the client certificate and key, if the IPC Agent requires mutual tls"
+    )]
+    tls_identity: Option<String>,
 }
 
 impl GlobalArguments {
@@ -46,6 +65,17 @@ impl GlobalArguments {
         self.config_path.clone().unwrap_or_else(default_config_path)
     }
 
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub fn client_tls(&self) -> ClientTls {
+        ClientTls {
+            ca_cert_path: self.tls_ca.clone(),
+            identity_path: self.tls_identity.clone(),
+        }
+    }
+
     pub fn config(&self) -> Result<Config> {
         let config_path = self.config_path();
         Config::from_file(config_path)
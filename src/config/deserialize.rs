@@ -132,6 +132,39 @@ where
     addrs.map_err(D::Error::custom)
 }
 
+/// A serde deserialization method to deserialize an optional list of account strings, each of them
+/// either an fvm or an eth address, into a vector of [`Address`].
+pub(crate) fn deserialize_optional_accounts<'de, D>(
+    deserializer: D,
+) -> anyhow::Result<Option<Vec<Address>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let addrs: Result<Vec<Address>, _> = <Vec<String>>::deserialize(deserializer)?
+        .iter()
+        .map(|raw_addr| {
+            if raw_addr.starts_with("0x") {
+                eth_addr_str_to_address(raw_addr)
+            } else {
+                Ok(Address::from_str(raw_addr)?)
+            }
+        })
+        .collect();
+    addrs.map(Some).map_err(D::Error::custom)
+}
+
+/// A serde deserialization method to deserialize an interval in seconds, rejecting zero so that the
+/// loops polling at that interval never spin.
+pub(crate) fn deserialize_interval_secs<'de, D>(deserializer: D) -> anyhow::Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match u64::deserialize(deserializer)? {
+        0 => Err(D::Error::custom("the interval must be at least 1 second")),
+        secs => Ok(secs),
+    }
+}
+
 fn eth_addr_str_to_address(s: &str) -> anyhow::Result<Address> {
     let addr = EthAddress::from_str(s)?;
     Ok(Address::from(addr))
@@ -24,8 +24,8 @@ use ipc_sdk::subnet_id::SubnetID;
 pub use reload::ReloadableConfig;
 use serde::{Deserialize, Serialize};
 use serialize::serialize_subnets_to_str;
-pub use server::JSON_RPC_ENDPOINT;
-pub use server::{json_rpc_methods, Server};
+pub use server::{json_rpc_methods, Server, ServerTls, ServerUnixSocket};
+pub use server::{JSON_RPC_ENDPOINT, METRICS_ENDPOINT, UNIX_SOCKET_SCHEME, WS_ENDPOINT};
 pub use subnet::Subnet;
 
 pub const JSON_RPC_VERSION: &str = "2.0";
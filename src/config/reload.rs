@@ -3,6 +3,7 @@
 //! Reloadable config
 
 use crate::config::Config;
+use crate::events::{EventBus, EventTopic};
 use anyhow::Result;
 use std::ops::DerefMut;
 use std::path::Path;
@@ -20,6 +21,8 @@ pub struct ReloadableConfig {
     /// We keep at least one channel active, so that we dont encounter a `SendError`. We might need to use it later.
     #[allow(dead_code)]
     broadcast_rx: broadcast::Receiver<()>,
+    /// The bus the reloads are published to, if any.
+    events: Option<Arc<EventBus>>,
 }
 
 impl ReloadableConfig {
@@ -34,9 +37,16 @@ impl ReloadableConfig {
             config,
             broadcast_tx,
             broadcast_rx,
+            events: None,
         })
     }
 
+    /// Publishes a `config_reloaded` event to `events` on every reload.
+    pub fn with_event_bus(mut self, events: Arc<EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
     /// Read from the config file.
     pub fn get_config(&self) -> Arc<Config> {
         let config = self.config.read().unwrap();
@@ -62,11 +72,16 @@ impl ReloadableConfig {
         let new_config = Config::from_file_async(path).await?;
         log::info!("new config loaded: {new_config:?}");
 
-        let mut config = self.config.write().unwrap();
-        let r = config.deref_mut();
-        *r = Arc::new(new_config);
+        {
+            let mut config = self.config.write().unwrap();
+            let r = config.deref_mut();
+            *r = Arc::new(new_config);
+        }
 
         self.broadcast_tx.send(()).unwrap_or_default();
+        if let Some(events) = &self.events {
+            events.publish(EventTopic::ConfigReloaded, None, ());
+        }
 
         Ok(())
     }
@@ -5,6 +5,7 @@
 use crate::config::Subnet;
 use anyhow::anyhow;
 use fvm_shared::address::{Address, Payload};
+use fvm_shared::econ::TokenAmount;
 use ipc_sdk::subnet_id::SubnetID;
 use primitives::EthAddress;
 use serde::ser::{Error, SerializeSeq};
@@ -74,6 +75,23 @@ where
     seq.end()
 }
 
+pub fn serialize_optional_accounts<S>(addrs: &Option<Vec<Address>>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match addrs {
+        Some(addrs) => serialize_accounts(addrs, s),
+        None => s.serialize_none(),
+    }
+}
+
+pub fn serialize_token_amount_to_str<S>(amount: &TokenAmount, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_str(&amount.atto().to_string())
+}
+
 fn address_to_eth_address(addr: &Address) -> anyhow::Result<EthAddress> {
     match addr.payload() {
         Payload::Delegated(inner) => {
@@ -134,7 +152,10 @@ mod tests {
     fn test_serialization() {
         let mut config = Config {
             server: Server {
-                json_rpc_address: "127.0.0.1:3030".parse().unwrap(),
+                json_rpc_address: Some("127.0.0.1:3030".parse().unwrap()),
+                unix_socket: None,
+                require_auth: false,
+                tls: None,
             },
             subnets: Default::default(),
         };
@@ -151,6 +172,8 @@ mod tests {
                     Address::from_str("f01").unwrap(),
                 ],
             }),
+            checkpointing: None,
+            relayer: None,
         };
 
         let eth_addr1 = EthAddress::from_str("0x6BE1Ccf648c74800380d0520D797a170c808b624").unwrap();
@@ -163,7 +186,10 @@ mod tests {
                 auth_token: None,
                 accounts: vec![Address::from(eth_addr1), Address::from(eth_addr1)],
                 registry_addr: Address::from(eth_addr1),
+                fees: None,
             }),
+            checkpointing: None,
+            relayer: None,
         };
         config.add_subnet(subnet1);
         config.add_subnet(subnet2);
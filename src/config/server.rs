@@ -1,13 +1,95 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::Path;
+use url::Url;
 
 pub const JSON_RPC_ENDPOINT: &str = "json_rpc";
+pub const METRICS_ENDPOINT: &str = "metrics";
+/// The endpoint of the websocket json rpc api, which also serves the event subscriptions.
+pub const WS_ENDPOINT: &str = "ws";
+/// The scheme of the urls of the agents listening on a unix socket, e.g.
+/// `unix:///run/ipc-agent/agent.sock`.
+pub const UNIX_SOCKET_SCHEME: &str = "unix";
+/// Only the user running the agent can connect to its unix socket by default.
+pub const DEFAULT_UNIX_SOCKET_PERMISSIONS: u32 = 0o600;
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct Server {
-    pub json_rpc_address: SocketAddr,
+    /// The tcp address of the json rpc api, not served over tcp if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_rpc_address: Option<SocketAddr>,
+    /// Serve the json rpc api on a unix socket, on top of or instead of `json_rpc_address`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<ServerUnixSocket>,
+    /// Reject the json rpc requests without a token. Otherwise they are only granted the `read`
+    /// permission.
+    #[serde(default)]
+    pub require_auth: bool,
+    /// Serve the json rpc api over https.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ServerTls>,
+}
+
+impl Server {
+    /// The url of the json rpc endpoint. The unix socket is preferred if configured, as it is
+    /// only reachable from the host. Otherwise the tcp address is used, with https if tls is
+    /// configured.
+    pub fn json_rpc_url(&self) -> Result<Url> {
+        if let Some(unix_socket) = &self.unix_socket {
+            return unix_socket.url();
+        }
+
+        let address = self
+            .json_rpc_address
+            .ok_or_else(|| anyhow!("no json rpc address or unix socket in the server config"))?;
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let url = format!("{scheme:}://{address:}/{JSON_RPC_ENDPOINT:}");
+        Ok(url.parse()?)
+    }
+}
+
+/// The unix socket of the server, declared in the optional `[server.unix_socket]` section.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ServerUnixSocket {
+    /// The absolute path of the socket, replaced if it already exists when the server starts.
+    pub path: String,
+    /// The file permissions of the socket, e.g. `0o660` to let the group of the user running the
+    /// agent connect.
+    #[serde(default = "default_unix_socket_permissions")]
+    pub permissions: u32,
+}
+
+impl ServerUnixSocket {
+    /// The url the clients reach the socket with.
+    pub fn url(&self) -> Result<Url> {
+        if !Path::new(&self.path).is_absolute() {
+            return Err(anyhow!(
+                "the path of the unix socket must be absolute: {:}",
+                self.path
+            ));
+        }
+        Ok(format!("{UNIX_SOCKET_SCHEME:}://{:}", self.path).parse()?)
+    }
+}
+
+fn default_unix_socket_permissions() -> u32 {
+    DEFAULT_UNIX_SOCKET_PERMISSIONS
+}
+
+/// The tls settings of the server, declared in the optional `[server.tls]` section.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ServerTls {
+    /// The PEM file with the certificate chain of the server.
+    pub cert_path: String,
+    /// The PEM file with the private key of the server.
+    pub key_path: String,
+    /// The PEM file with the CA certificates the clients are authenticated with. Only the clients
+    /// presenting a certificate signed by one of them are accepted if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ca_path: Option<String>,
 }
 
 pub mod json_rpc_methods {
@@ -32,4 +114,16 @@ pub mod json_rpc_methods {
     pub const WALLET_BALANCES: &str = "ipc_walletBalances";
     pub const LIST_BOTTOMUP_CHECKPOINTS: &str = "ipc_listBottomUpCheckpoints";
     pub const LAST_TOPDOWN_EXECUTED: &str = "ipc_lastTopDownCheckpointExecuted";
+    pub const CHECKPOINT_STATUS: &str = "ipc_checkpointStatus";
+    pub const PREVIEW_BOTTOMUP_CHECKPOINT: &str = "ipc_previewBottomUpCheckpoint";
+    pub const AUDIT_BOTTOMUP_CHECKPOINTS: &str = "ipc_auditBottomUpCheckpoints";
+    pub const GET_TRANSACTION_STATUS: &str = "ipc_getTransactionStatus";
+    pub const LIST_TRANSACTIONS: &str = "ipc_listTransactions";
+    /// Returns the OpenRPC document of the methods above.
+    pub const RPC_DISCOVER: &str = "rpc.discover";
+    /// Only served over websocket.
+    pub const SUBSCRIBE: &str = "ipc_subscribe";
+    pub const UNSUBSCRIBE: &str = "ipc_unsubscribe";
+    /// The method of the notifications carrying the events to the subscribers.
+    pub const SUBSCRIPTION: &str = "ipc_subscription";
 }
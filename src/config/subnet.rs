@@ -1,18 +1,44 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
 use ipc_sdk::subnet_id::SubnetID;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::config::deserialize::{
     deserialize_accounts, deserialize_address_from_str, deserialize_eth_accounts,
-    deserialize_eth_address_from_str, deserialize_subnet_id,
+    deserialize_eth_address_from_str, deserialize_interval_secs, deserialize_optional_accounts,
+    deserialize_subnet_id,
 };
 use crate::config::serialize::{
     serialize_accounts, serialize_address_to_str, serialize_eth_accounts,
-    serialize_eth_address_to_str, serialize_subnet_id_to_str,
+    serialize_eth_address_to_str, serialize_optional_accounts, serialize_subnet_id_to_str,
+    serialize_token_amount_to_str,
 };
+use crate::lotus::message::deserialize::deserialize_token_amount_from_str;
+
+/// The default maximum number of epochs past the last executed checkpoint that are submitted in a
+/// single run.
+pub const DEFAULT_LOOK_AHEAD_EPOCHS: ChainEpoch = 50;
+/// The default interval in seconds to check again a subnet that is not ready for submissions.
+pub const DEFAULT_POLLING_INTERVAL_SECS: u64 = 15;
+/// The default interval in seconds between two scans of the postbox of a subnet by the relayer.
+pub const DEFAULT_RELAYER_POLLING_INTERVAL_SECS: u64 = 30;
+/// The default period in seconds the fee budget of the relayer applies to.
+pub const DEFAULT_FEE_BUDGET_PERIOD_SECS: u64 = 24 * 60 * 60;
+/// The default reward percentile of the recent blocks used as the priority fee of a transaction.
+pub const DEFAULT_FEE_PERCENTILE: u8 = 5;
+/// The default number of recent blocks the fees of a transaction are estimated from.
+pub const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 10;
+/// The default number of seconds a transaction can stay pending before it is replaced.
+pub const DEFAULT_REPLACEMENT_TIMEOUT_SECS: u64 = 120;
+/// The default percentage the fees of a replacement transaction are increased by. Most nodes
+/// require at least 10%.
+pub const DEFAULT_REPLACEMENT_FEE_BUMP_PERCENT: u64 = 20;
+/// The default maximum number of times a transaction is replaced.
+pub const DEFAULT_MAX_REPLACEMENTS: u32 = 5;
 
 /// Represents a subnet declaration in the config.
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
@@ -22,6 +48,12 @@ pub struct Subnet {
     pub id: SubnetID,
     pub network_name: String,
     pub config: SubnetConfig,
+    /// The policy of the checkpoints between the subnet and its parent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpointing: Option<CheckpointingConfig>,
+    /// The relayer of the cross-net messages in the postbox of the subnet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayer: Option<RelayerConfig>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
@@ -75,6 +107,109 @@ impl Subnet {
             SubnetConfig::Fevm(s) => s.accounts.clone(),
         }
     }
+
+    /// The checkpointing policy of the subnet, or the default policy if it is not configured.
+    pub fn checkpointing_policy(&self) -> CheckpointingConfig {
+        self.checkpointing.clone().unwrap_or_default()
+    }
+}
+
+/// The checkpointing policy of a subnet, declared in the optional `[subnets.checkpointing]`
+/// section. It applies to the checkpoints between the subnet and its parent, every missing field
+/// takes its default value.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct CheckpointingConfig {
+    /// Submits the top-down checkpoints from the parent to the subnet.
+    pub top_down: bool,
+    /// Submits the bottom-up checkpoints from the subnet to the parent.
+    pub bottom_up: bool,
+    /// The maximum number of epochs past the last executed checkpoint that are submitted in a
+    /// single run.
+    pub look_ahead_epochs: ChainEpoch,
+    /// The interval in seconds to check again a subnet that is not ready for submissions, at
+    /// least 1 second.
+    #[serde(deserialize_with = "deserialize_interval_secs")]
+    pub polling_interval_secs: u64,
+    /// The accounts voting the checkpoints, either fvm or eth addresses. Only the accounts that
+    /// are also configured in the subnet the checkpoints are submitted to vote. All of them vote
+    /// if not set.
+    #[serde(deserialize_with = "deserialize_optional_accounts")]
+    #[serde(serialize_with = "serialize_optional_accounts")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<Vec<Address>>,
+    /// The number of epochs to wait past the epoch of a checkpoint before submitting it.
+    pub submission_delay_epochs: ChainEpoch,
+}
+
+impl Default for CheckpointingConfig {
+    fn default() -> Self {
+        Self {
+            top_down: true,
+            bottom_up: true,
+            look_ahead_epochs: DEFAULT_LOOK_AHEAD_EPOCHS,
+            polling_interval_secs: DEFAULT_POLLING_INTERVAL_SECS,
+            accounts: None,
+            submission_delay_epochs: 0,
+        }
+    }
+}
+
+impl CheckpointingConfig {
+    /// Filters the managed accounts of a subnet down to the ones allowed to vote.
+    pub fn voting_accounts(&self, mut managed: Vec<Address>) -> Vec<Address> {
+        if let Some(accounts) = &self.accounts {
+            managed.retain(|a| accounts.contains(a));
+        }
+        managed
+    }
+}
+
+/// The relayer of a subnet, declared in the optional `[subnets.relayer]` section. The relayer
+/// propagates the cross-net messages sent by the managed accounts that are waiting in the postbox
+/// of the subnet.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RelayerConfig {
+    /// The accounts whose messages are propagated, either fvm or eth addresses. Only the accounts
+    /// that are also configured in the subnet are used. All of them are used if not set.
+    #[serde(default, deserialize_with = "deserialize_optional_accounts")]
+    #[serde(serialize_with = "serialize_optional_accounts")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<Vec<Address>>,
+    /// The maximum fees in attoFIL spent by the relayer in the subnet over a budget period.
+    #[serde(deserialize_with = "deserialize_token_amount_from_str")]
+    #[serde(serialize_with = "serialize_token_amount_to_str")]
+    pub fee_budget: TokenAmount,
+    /// The maximum fee in attoFIL paid for the propagation of a single message. It is charged in
+    /// full against the budget.
+    #[serde(deserialize_with = "deserialize_token_amount_from_str")]
+    #[serde(serialize_with = "serialize_token_amount_to_str")]
+    pub max_fee_per_msg: TokenAmount,
+    /// The period in seconds the fee budget applies to.
+    #[serde(default = "default_fee_budget_period_secs")]
+    pub budget_period_secs: u64,
+    /// The interval in seconds between two scans of the postbox, at least 1 second.
+    #[serde(default = "default_relayer_polling_interval_secs")]
+    #[serde(deserialize_with = "deserialize_interval_secs")]
+    pub polling_interval_secs: u64,
+}
+
+impl RelayerConfig {
+    /// Filters the managed accounts of a subnet down to the ones whose messages are relayed.
+    pub fn relaying_accounts(&self, mut managed: Vec<Address>) -> Vec<Address> {
+        if let Some(accounts) = &self.accounts {
+            managed.retain(|a| accounts.contains(a));
+        }
+        managed
+    }
+}
+
+fn default_fee_budget_period_secs() -> u64 {
+    DEFAULT_FEE_BUDGET_PERIOD_SECS
+}
+
+fn default_relayer_polling_interval_secs() -> u64 {
+    DEFAULT_RELAYER_POLLING_INTERVAL_SECS
 }
 
 /// The FVM subnet config parameters
@@ -104,4 +239,73 @@ pub struct EVMSubnet {
     #[serde(deserialize_with = "deserialize_eth_accounts", default)]
     #[serde(serialize_with = "serialize_eth_accounts")]
     pub accounts: Vec<Address>,
+    /// The fees of the transactions sent to the subnet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees: Option<EvmFeeConfig>,
+}
+
+impl EVMSubnet {
+    /// The fee config of the subnet. Without a `[subnets.config.fees]` section, the fees are the
+    /// ones estimated by the provider of the subnet.
+    pub fn fee_config(&self) -> EvmFeeConfig {
+        self.fees.clone().unwrap_or_else(|| EvmFeeConfig {
+            strategy: FeeStrategy::Provider,
+            ..Default::default()
+        })
+    }
+}
+
+/// The fees of the transactions sent to an evm subnet, declared in the optional
+/// `[subnets.config.fees]` section. Every missing field takes its default value.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct EvmFeeConfig {
+    /// How the fees of a new transaction are picked.
+    pub strategy: FeeStrategy,
+    /// The number of seconds a transaction can stay pending before it is replaced by one with the
+    /// same nonce and higher fees.
+    pub replacement_timeout_secs: u64,
+    /// The percentage the fees of a replacement transaction are increased by.
+    pub replacement_fee_bump_percent: u64,
+    /// The maximum number of times a transaction is replaced.
+    pub max_replacements: u32,
+}
+
+impl Default for EvmFeeConfig {
+    fn default() -> Self {
+        Self {
+            strategy: FeeStrategy::default(),
+            replacement_timeout_secs: DEFAULT_REPLACEMENT_TIMEOUT_SECS,
+            replacement_fee_bump_percent: DEFAULT_REPLACEMENT_FEE_BUMP_PERCENT,
+            max_replacements: DEFAULT_MAX_REPLACEMENTS,
+        }
+    }
+}
+
+/// The strategy picking the eip-1559 fees of a transaction. The fees are in wei per gas.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeStrategy {
+    /// Always the same fees.
+    Fixed {
+        max_fee_per_gas: u64,
+        max_priority_fee_per_gas: u64,
+    },
+    /// The priority fee is the `percentile` of the rewards in the last `blocks` blocks, and the
+    /// maximum fee allows the base fee to double.
+    OraclePercentile { percentile: u8, blocks: u64 },
+    /// The fees of the default oracle, capped at `max_fee_per_gas`. Transactions are not replaced
+    /// past the cap.
+    MaxFeeCap { max_fee_per_gas: u64 },
+    /// The fees estimated by the provider of the subnet.
+    Provider,
+}
+
+impl Default for FeeStrategy {
+    fn default() -> Self {
+        FeeStrategy::OraclePercentile {
+            percentile: DEFAULT_FEE_PERCENTILE,
+            blocks: DEFAULT_FEE_HISTORY_BLOCKS,
+        }
+    }
 }
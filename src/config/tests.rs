@@ -6,16 +6,26 @@ use std::str::FromStr;
 use std::sync::{Arc, Condvar, Mutex};
 
 use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
 use indoc::formatdoc;
 use ipc_sdk::subnet_id::SubnetID;
 use primitives::EthAddress;
 use tempfile::NamedTempFile;
 use url::Url;
 
+use crate::config::server::DEFAULT_UNIX_SOCKET_PERMISSIONS;
+use crate::config::subnet::{
+    CheckpointingConfig, EvmFeeConfig, FeeStrategy, RelayerConfig, SubnetConfig,
+    DEFAULT_FEE_BUDGET_PERIOD_SECS, DEFAULT_MAX_REPLACEMENTS, DEFAULT_POLLING_INTERVAL_SECS,
+    DEFAULT_REPLACEMENT_FEE_BUMP_PERCENT,
+};
 use crate::config::{Config, ReloadableConfig};
+use crate::events::{EventBus, EventTopic};
 
 // Arguments for the config's fields
 const SERVER_JSON_RPC_ADDR: &str = "127.0.0.1:3030";
+const TLS_CERT_PATH: &str = "/etc/ipc-agent/cert.pem";
+const TLS_KEY_PATH: &str = "/etc/ipc-agent/key.pem";
 const ROOT_ID: &str = "/r123";
 const CHILD_ID: &str = "/r123/f0100";
 const GATEWAY_ADDR: &str = "f064";
@@ -41,7 +51,13 @@ async fn reload_works() {
 
     file.write_all(config_str.as_bytes()).unwrap();
 
-    let h = Arc::new(ReloadableConfig::new(path.clone()).unwrap());
+    let events = Arc::new(EventBus::new());
+    let mut event_rx = events.subscribe();
+    let h = Arc::new(
+        ReloadableConfig::new(path.clone())
+            .unwrap()
+            .with_event_bus(events),
+    );
     let original_config = h.get_config();
 
     // A simple barrier implementation for testing.
@@ -77,12 +93,21 @@ async fn reload_works() {
         cvar.notify_one();
     }
     rx.recv().await.unwrap();
+    assert_eq!(
+        event_rx.recv().await.unwrap().topic,
+        EventTopic::ConfigReloaded
+    );
 
     let updated_config = h.get_config();
     assert_ne!(
         updated_config.server.json_rpc_address,
         original_config.server.json_rpc_address
     );
+
+    // the checkpointing section was removed from the child subnet
+    let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+    assert!(original_config.subnets[&child_id].checkpointing.is_some());
+    assert!(updated_config.subnets[&child_id].checkpointing.is_none());
 }
 
 #[test]
@@ -90,9 +115,54 @@ fn check_server_config() {
     let config = read_config().server;
     assert_eq!(
         config.json_rpc_address,
-        SocketAddr::from_str(SERVER_JSON_RPC_ADDR).unwrap(),
+        Some(SocketAddr::from_str(SERVER_JSON_RPC_ADDR).unwrap()),
         "invalid server rpc address"
     );
+    assert!(config.unix_socket.is_none());
+
+    let tls = config.tls.as_ref().unwrap();
+    assert_eq!(tls.cert_path, TLS_CERT_PATH);
+    assert_eq!(tls.key_path, TLS_KEY_PATH);
+    assert!(tls.client_ca_path.is_none());
+    assert_eq!(
+        config.json_rpc_url().unwrap().as_str(),
+        format!("https://{SERVER_JSON_RPC_ADDR}/json_rpc")
+    );
+}
+
+#[test]
+fn check_server_unix_socket_config() {
+    let config = Config::from_toml_str(
+        r#"
+        [server]
+        unix_socket = { path = "/run/ipc-agent/agent.sock" }
+        "#,
+    )
+    .unwrap()
+    .server;
+    assert!(config.json_rpc_address.is_none());
+    let unix_socket = config.unix_socket.as_ref().unwrap();
+    assert_eq!(unix_socket.permissions, DEFAULT_UNIX_SOCKET_PERMISSIONS);
+    assert_eq!(
+        config.json_rpc_url().unwrap().as_str(),
+        "unix:///run/ipc-agent/agent.sock"
+    );
+
+    // the unix socket is preferred over the tcp address
+    let config = Config::from_toml_str(&formatdoc!(
+        r#"
+        [server]
+        json_rpc_address = "{SERVER_JSON_RPC_ADDR}"
+
+        [server.unix_socket]
+        path = "agent.sock"
+        permissions = 0o660
+        "#
+    ))
+    .unwrap()
+    .server;
+    assert_eq!(config.unix_socket.as_ref().unwrap().permissions, 0o660);
+    assert!(config.json_rpc_url().is_err());
 }
 
 #[test]
@@ -129,12 +199,119 @@ fn check_subnets_config() {
     );
 }
 
+#[test]
+fn check_checkpointing_config() {
+    let config = read_config().subnets;
+
+    // the default policy applies when the section is missing
+    let root = &config[&SubnetID::from_str(ROOT_ID).unwrap()];
+    assert_eq!(root.checkpointing, None);
+    assert_eq!(root.checkpointing_policy(), CheckpointingConfig::default());
+
+    let child = &config[&SubnetID::from_str(CHILD_ID).unwrap()];
+    let eth_address = Address::from(EthAddress::from_str(ETH_ADDRESS).unwrap());
+    let account = Address::from_str(ACCOUNT_ADDRESS).unwrap();
+    assert_eq!(
+        child.checkpointing_policy(),
+        CheckpointingConfig {
+            top_down: false,
+            bottom_up: true,
+            look_ahead_epochs: 20,
+            polling_interval_secs: DEFAULT_POLLING_INTERVAL_SECS,
+            accounts: Some(vec![eth_address]),
+            submission_delay_epochs: 5,
+        }
+    );
+    assert_eq!(
+        child
+            .checkpointing_policy()
+            .voting_accounts(vec![account, eth_address]),
+        vec![eth_address]
+    );
+
+    // the section survives a round trip through the config file
+    let config = read_config();
+    let from_str = Config::from_toml_str(&toml::to_string(&config).unwrap()).unwrap();
+    assert_eq!(from_str, config);
+}
+
+#[test]
+fn check_zero_polling_interval_rejected() {
+    let checkpointing = config_str().replace(
+        "submission_delay_epochs = 5",
+        "submission_delay_epochs = 5\npolling_interval_secs = 0",
+    );
+    assert!(Config::from_toml_str(&checkpointing).is_err());
+
+    let relayer = config_str().replace("polling_interval_secs = 60", "polling_interval_secs = 0");
+    assert!(Config::from_toml_str(&relayer).is_err());
+}
+
+#[test]
+fn check_relayer_config() {
+    let config = read_config().subnets;
+
+    let child = &config[&SubnetID::from_str(CHILD_ID).unwrap()];
+    assert_eq!(child.relayer, None);
+
+    let root = &config[&SubnetID::from_str(ROOT_ID).unwrap()];
+    let account = Address::from_str(ACCOUNT_ADDRESS).unwrap();
+    assert_eq!(
+        root.relayer,
+        Some(RelayerConfig {
+            accounts: None,
+            fee_budget: TokenAmount::from_whole(1),
+            max_fee_per_msg: TokenAmount::from_nano(10_000_000),
+            budget_period_secs: DEFAULT_FEE_BUDGET_PERIOD_SECS,
+            polling_interval_secs: 60,
+        })
+    );
+    assert_eq!(
+        root.relayer
+            .as_ref()
+            .unwrap()
+            .relaying_accounts(root.accounts()),
+        vec![account]
+    );
+}
+
+#[test]
+fn check_fee_config() {
+    let config = read_config().subnets;
+
+    let child = &config[&SubnetID::from_str(CHILD_ID).unwrap()];
+    let mut evm_config = match &child.config {
+        SubnetConfig::Fevm(config) => config.clone(),
+        _ => panic!("child subnet should be fevm"),
+    };
+    assert_eq!(
+        evm_config.fees,
+        Some(EvmFeeConfig {
+            strategy: FeeStrategy::MaxFeeCap {
+                max_fee_per_gas: 1_000_000_000_000,
+            },
+            replacement_timeout_secs: 60,
+            replacement_fee_bump_percent: DEFAULT_REPLACEMENT_FEE_BUMP_PERCENT,
+            max_replacements: DEFAULT_MAX_REPLACEMENTS,
+        })
+    );
+    assert_eq!(Some(evm_config.fee_config()), evm_config.fees);
+
+    // without a fees section, the fees are estimated by the provider
+    evm_config.fees = None;
+    assert_eq!(evm_config.fee_config().strategy, FeeStrategy::Provider);
+}
+
 fn config_str() -> String {
     formatdoc!(
         r#"
         [server]
         json_rpc_address = "{SERVER_JSON_RPC_ADDR}"
 
+        [server.tls]
+        cert_path = "{TLS_CERT_PATH}"
+        key_path = "{TLS_KEY_PATH}"
+
         [[subnets]]
         id = "{ROOT_ID}"
         network_name = "root"
@@ -146,6 +323,11 @@ fn config_str() -> String {
         auth_token = "{ROOT_AUTH_TOKEN}"
         accounts = ["{ACCOUNT_ADDRESS}"]
 
+        [subnets.relayer]
+        fee_budget = "1000000000000000000"
+        max_fee_per_msg = "10000000000000000"
+        polling_interval_secs = 60
+
         [[subnets]]
         id = "{CHILD_ID}"
         network_name = "child"
@@ -157,6 +339,19 @@ fn config_str() -> String {
         registry_addr = "{ETH_ADDRESS}"
         gateway_addr = "{ETH_ADDRESS}"
         accounts = ["{ETH_ADDRESS}", "{ETH_ADDRESS}"]
+
+        [subnets.config.fees]
+        replacement_timeout_secs = 60
+
+        [subnets.config.fees.strategy]
+        type = "max_fee_cap"
+        max_fee_per_gas = 1000000000000
+
+        [subnets.checkpointing]
+        top_down = false
+        look_ahead_epochs = 20
+        accounts = ["{ETH_ADDRESS}"]
+        submission_delay_epochs = 5
         "#
     )
 }
@@ -0,0 +1,172 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! In-process bus of the events of the agent.
+//!
+//! The checkpoint subsystem and the json rpc handlers publish to the bus as checkpoints are
+//! submitted and executed or subnets are operated through the agent. The json rpc server forwards
+//! the events to the websocket clients subscribed to their topic. Events published while nobody is
+//! subscribed are dropped, and a subscriber too slow to keep up misses the oldest ones.
+
+use anyhow::{anyhow, Result};
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// The number of events buffered for each subscriber.
+pub const EVENT_BUS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventTopic {
+    /// A checkpoint submitted by a validator managed by the agent was executed.
+    CheckpointSubmitted,
+    /// The last executed checkpoint of a subnet advanced.
+    CheckpointExecuted,
+    /// A child subnet was registered through the agent.
+    SubnetRegistered,
+    /// Funds were sent to a child subnet.
+    FundExecuted,
+    /// Funds were released to the parent subnet.
+    ReleaseExecuted,
+    /// A validator joined or left a subnet.
+    ValidatorSetChanged,
+    /// A subnet was killed through the agent.
+    SubnetKilled,
+    /// The config of the agent was reloaded.
+    ConfigReloaded,
+}
+
+impl Display for EventTopic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let topic = match self {
+            Self::CheckpointSubmitted => "checkpoint_submitted",
+            Self::CheckpointExecuted => "checkpoint_executed",
+            Self::SubnetRegistered => "subnet_registered",
+            Self::FundExecuted => "fund_executed",
+            Self::ReleaseExecuted => "release_executed",
+            Self::ValidatorSetChanged => "validator_set_changed",
+            Self::SubnetKilled => "subnet_killed",
+            Self::ConfigReloaded => "config_reloaded",
+        };
+        write!(f, "{topic:}")
+    }
+}
+
+impl FromStr for EventTopic {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "checkpoint_submitted" => Ok(Self::CheckpointSubmitted),
+            "checkpoint_executed" => Ok(Self::CheckpointExecuted),
+            "subnet_registered" => Ok(Self::SubnetRegistered),
+            "fund_executed" => Ok(Self::FundExecuted),
+            "release_executed" => Ok(Self::ReleaseExecuted),
+            "validator_set_changed" => Ok(Self::ValidatorSetChanged),
+            "subnet_killed" => Ok(Self::SubnetKilled),
+            "config_reloaded" => Ok(Self::ConfigReloaded),
+            _ => Err(anyhow!("unknown event topic: {s:}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub topic: EventTopic,
+    /// The subnet the event happened in, if any.
+    pub subnet: Option<String>,
+    /// The payload of the event, specific to its topic.
+    pub data: Value,
+    /// Unix timestamp in seconds of when the event was published.
+    pub timestamp: u64,
+}
+
+/// Broadcasts the events of the agent to all its subscribers.
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes the event to the current subscribers.
+    pub fn publish(&self, topic: EventTopic, subnet: Option<&SubnetID>, data: impl Serialize) {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(e) => {
+                log::error!("cannot serialize the {topic:} event: {e:}");
+                return;
+            }
+        };
+        let event = Event {
+            topic,
+            subnet: subnet.map(|s| s.to_string()),
+            data,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        log::debug!("publishing event: {event:?}");
+        // sending only fails if there are no subscribers.
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{EventBus, EventTopic};
+    use ipc_sdk::subnet_id::SubnetID;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_event_bus() {
+        let bus = EventBus::new();
+        // no subscribers yet, the event is dropped.
+        bus.publish(EventTopic::ConfigReloaded, None, ());
+
+        let mut receiver = bus.subscribe();
+        let subnet = SubnetID::new_root(123);
+        bus.publish(
+            EventTopic::FundExecuted,
+            Some(&subnet),
+            serde_json::json!({"epoch": 10}),
+        );
+
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.topic, EventTopic::FundExecuted);
+        assert_eq!(event.subnet, Some(subnet.to_string()));
+        assert_eq!(event.data["epoch"], 10);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_topics() {
+        let topic = EventTopic::from_str("validator_set_changed").unwrap();
+        assert_eq!(topic, EventTopic::ValidatorSetChanged);
+        assert_eq!(topic.to_string(), "validator_set_changed");
+        let topic = EventTopic::from_str("subnet_killed").unwrap();
+        assert_eq!(topic, EventTopic::SubnetKilled);
+        assert_eq!(topic.to_string(), "subnet_killed");
+        assert!(EventTopic::from_str("unknown").is_err());
+    }
+}
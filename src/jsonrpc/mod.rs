@@ -7,7 +7,7 @@ use async_channel::{Receiver, Sender};
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
 use reqwest::header::HeaderValue;
-use reqwest::Client;
+use reqwest::{Certificate, Client, Identity};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::json;
@@ -20,6 +20,9 @@ use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::{connect_async, WebSocketStream};
 use url::Url;
 
+use crate::config::{JSON_RPC_ENDPOINT, UNIX_SOCKET_SCHEME};
+use crate::metrics;
+
 #[cfg(test)]
 mod tests;
 
@@ -40,17 +43,20 @@ pub trait JsonRpcClient {
     /// Sends a JSON-RPC request with `method` and `params` via HTTP/HTTPS.
     async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T>;
 
-    /// Subscribes to notifications via a Websocket. This returns a [`Receiver`]
-    /// channel that is used to receive the messages sent by the server.
+    /// Subscribes to notifications via a Websocket with `method` and `params`. This returns a
+    /// [`Receiver`] channel that is used to receive the messages sent by the server.
     /// TODO: https://github.com/consensus-shipyard/ipc-agent/issues/7.
-    async fn subscribe(&self, method: &str) -> Result<Receiver<Value>>;
+    async fn subscribe(&self, method: &str, params: Value) -> Result<Receiver<Value>>;
 }
 
-/// The implementation of [`JsonRpcClient`].
+/// The implementation of [`JsonRpcClient`]. The requests to a `unix://` url are sent to the agent
+/// listening on that unix socket.
 pub struct JsonRpcClientImpl {
     http_client: Client,
     url: Url,
     bearer_token: Option<String>,
+    /// The backend label of the latency metric of the requests, if they are measured.
+    metrics_backend: Option<&'static str>,
 }
 
 impl JsonRpcClientImpl {
@@ -60,25 +66,85 @@ impl JsonRpcClientImpl {
             http_client: Client::default(),
             url,
             bearer_token: bearer_token.map(String::from),
+            metrics_backend: None,
+        }
+    }
+
+    /// Creates a client that sends all requests to `url` with the tls settings, on top of the
+    /// system root certificates.
+    pub fn new_with_tls(url: Url, bearer_token: Option<&str>, tls: &ClientTls) -> Result<Self> {
+        let mut builder = Client::builder().use_rustls_tls();
+        if let Some(path) = &tls.ca_cert_path {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("cannot read ca certificate {path:}: {e:}"))?;
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        if let Some(path) = &tls.identity_path {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("cannot read client identity {path:}: {e:}"))?;
+            builder = builder.identity(Identity::from_pem(&pem)?);
         }
+
+        Ok(Self {
+            http_client: builder.build()?,
+            url,
+            bearer_token: bearer_token.map(String::from),
+            metrics_backend: None,
+        })
+    }
+
+    /// Measures the latency of the requests under the `backend` label, e.g. `lotus`.
+    pub fn with_metrics_backend(mut self, backend: &'static str) -> Self {
+        self.metrics_backend = Some(backend);
+        self
+    }
+}
+
+/// The tls settings of a client, to connect to servers using a private CA or requiring a client
+/// certificate.
+#[derive(Debug, Clone, Default)]
+pub struct ClientTls {
+    /// The PEM file with the CA certificate the server is authenticated with.
+    pub ca_cert_path: Option<String>,
+    /// The PEM file with the certificate and the private key of the client.
+    pub identity_path: Option<String>,
+}
+
+impl ClientTls {
+    pub fn is_empty(&self) -> bool {
+        self.ca_cert_path.is_none() && self.identity_path.is_none()
     }
 }
 
 #[async_trait]
 impl JsonRpcClient for JsonRpcClientImpl {
     async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
-        let request_body = build_jsonrpc_request(method, params)?;
-        let mut builder = self.http_client.post(self.url.as_str()).json(&request_body);
-        builder = builder.timeout(DEFAULT_REQ_TIMEOUT);
+        let _timer = self.metrics_backend.map(|backend| {
+            metrics::RPC_CALL_LATENCY
+                .with_label_values(&[backend, method])
+                .start_timer()
+        });
 
-        // Add the authorization bearer token if present
-        if self.bearer_token.is_some() {
-            builder = builder.bearer_auth(self.bearer_token.as_ref().unwrap());
-        }
+        let request_body = build_jsonrpc_request(method, params)?;
+        let response_body = if self.url.scheme() == UNIX_SOCKET_SCHEME {
+            tokio::time::timeout(
+                DEFAULT_REQ_TIMEOUT,
+                unix_socket_request(self.url.path(), &request_body, self.bearer_token.as_deref()),
+            )
+            .await
+            .map_err(|_| anyhow!("json rpc request timed out"))??
+        } else {
+            let mut builder = self.http_client.post(self.url.as_str()).json(&request_body);
+            builder = builder.timeout(DEFAULT_REQ_TIMEOUT);
 
-        let response = builder.send().await?;
+            // Add the authorization bearer token if present
+            if self.bearer_token.is_some() {
+                builder = builder.bearer_auth(self.bearer_token.as_ref().unwrap());
+            }
 
-        let response_body = response.text().await?;
+            let response = builder.send().await?;
+            response.text().await?
+        };
         log::debug!("received raw response body: {:?}", response_body);
 
         let value =
@@ -98,7 +164,7 @@ impl JsonRpcClient for JsonRpcClientImpl {
         Result::from(value)
     }
 
-    async fn subscribe(&self, method: &str) -> Result<Receiver<Value>> {
+    async fn subscribe(&self, method: &str, params: Value) -> Result<Receiver<Value>> {
         let mut request = self.url.as_str().into_client_request()?;
 
         // Add the authorization bearer token if present
@@ -109,7 +175,7 @@ impl JsonRpcClient for JsonRpcClientImpl {
         }
 
         let (mut ws_stream, _) = connect_async(request).await?;
-        let request_body = build_jsonrpc_request(method, NO_PARAMS)?;
+        let request_body = build_jsonrpc_request(method, params)?;
         ws_stream
             .send(Message::text(request_body.to_string()))
             .await?;
@@ -121,6 +187,60 @@ impl JsonRpcClient for JsonRpcClientImpl {
     }
 }
 
+/// Sends the json rpc request to the agent listening on the unix socket at `path`, see
+/// [`crate::config::ServerUnixSocket`]. Returns the body of the response.
+#[cfg(unix)]
+async fn unix_socket_request(
+    path: &str,
+    request_body: &Value,
+    bearer_token: Option<&str>,
+) -> Result<String> {
+    let stream = tokio::net::UnixStream::connect(path)
+        .await
+        .map_err(|e| anyhow!("cannot connect to unix socket {path:}: {e:}"))?;
+    let (mut sender, connection) = hyper::client::conn::handshake(stream).await?;
+    spawn(async move {
+        if let Err(e) = connection.await {
+            log::debug!("unix socket connection failed: {e:}");
+        }
+    });
+
+    let mut builder = hyper::Request::post(format!("/{JSON_RPC_ENDPOINT:}"))
+        .header(hyper::header::HOST, "localhost")
+        .header(hyper::header::CONTENT_TYPE, "application/json");
+    // Add the authorization bearer token if present
+    if let Some(token) = bearer_token {
+        builder = builder.header(hyper::header::AUTHORIZATION, format!("Bearer {token:}"));
+    }
+    let request = builder.body(hyper::Body::from(request_body.to_string()))?;
+
+    let response = sender.send_request(request).await?;
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(String::from_utf8(body.to_vec())?)
+}
+
+#[cfg(not(unix))]
+async fn unix_socket_request(
+    path: &str,
+    _request_body: &Value,
+    _bearer_token: Option<&str>,
+) -> Result<String> {
+    Err(anyhow!(
+        "unix sockets are not supported on this platform: {path:}"
+    ))
+}
+
+/// The error object of a json rpc error response, returned by [`JsonRpcClientImpl`] as the error
+/// of the request: https://www.jsonrpc.org/specification#error_object
+#[derive(Debug, Clone, PartialEq, Deserialize, thiserror::Error)]
+#[error("json_rpc error: {message:} (code {code:})")]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
 /// JsonRpcResponse wraps the json rpc response.
 /// We could have encountered success or error, this struct handles the error and result and convert
 /// them into Result.
@@ -135,8 +255,11 @@ struct JsonRpcResponse<T> {
 
 impl<T: DeserializeOwned> From<JsonRpcResponse<T>> for Result<T> {
     fn from(j: JsonRpcResponse<T>) -> Self {
-        if j.error.is_some() {
-            return Err(anyhow!("json_rpc error: {:}", j.error.unwrap()));
+        if let Some(error) = j.error {
+            return match serde_json::from_value::<JsonRpcError>(error.clone()) {
+                Ok(e) => Err(e.into()),
+                Err(_) => Err(anyhow!("json_rpc error: {:}", error)),
+            };
         }
         if j.result.is_some() {
             Ok(j.result.unwrap())
@@ -163,10 +286,21 @@ async fn handle_stream(
             }
             Some(result) => match result {
                 Ok(msg) => {
-                    println!("{}", msg);
                     log::trace!("Read message from websocket stream: {}", msg);
-                    let value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
-                    chan.send(value).await.unwrap();
+                    if !msg.is_text() {
+                        continue;
+                    }
+                    let value = match serde_json::from_str(msg.to_text().unwrap()) {
+                        Ok(v) => v,
+                        Err(err) => {
+                            log::warn!("Cannot parse message from websocket stream: {:?}", err);
+                            continue;
+                        }
+                    };
+                    if chan.send(value).await.is_err() {
+                        log::trace!("Websocket stream receiver dropped, closing the stream.");
+                        break;
+                    }
                 }
                 Err(err) => {
                     log::error!("Error reading message from websocket stream: {:?}", err);
@@ -63,7 +63,10 @@ async fn test_request_with_params_error() {
 async fn test_subscribe() {
     let url = Url::parse(WS_ENDPOINT).unwrap();
     let client = JsonRpcClientImpl::new(url, None);
-    let mut chan = client.subscribe("Filecoin.ChainNotify").await.unwrap();
+    let mut chan = client
+        .subscribe("Filecoin.ChainNotify", NO_PARAMS)
+        .await
+        .unwrap();
     for _ in 1..=3 {
         chan.next().await.unwrap();
     }
@@ -7,9 +7,15 @@
 pub mod checkpoint;
 pub mod cli;
 pub mod config;
+pub mod events;
 pub mod jsonrpc;
 pub mod lotus;
 pub mod manager;
+pub mod metrics;
+pub mod relayer;
 pub mod sdk;
 mod serialization;
 pub mod server;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
+pub mod tracker;
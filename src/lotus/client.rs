@@ -36,7 +36,9 @@ use crate::lotus::message::state::{ReadStateResponse, StateWaitMsgResponse};
 use crate::lotus::message::wallet::{WalletKeyType, WalletListResponse};
 use crate::lotus::message::CIDMap;
 use crate::lotus::{LotusClient, NetworkVersion};
-use crate::manager::SubnetInfo;
+use crate::manager::{NonceManager, SubnetInfo};
+use crate::metrics;
+use crate::tracker::SubmissionSink;
 
 pub type DefaultLotusJsonRPCClient = LotusJsonRPCClient<JsonRpcClientImpl>;
 
@@ -103,6 +105,11 @@ pub struct LotusJsonRPCClient<T: JsonRpcClient> {
     client: T,
     subnet: SubnetID,
     wallet_store: Option<Arc<RwLock<Wallet>>>,
+    /// Assigns the nonces of the messages pushed without one, if set. Otherwise they are asked to
+    /// the node for every message.
+    nonces: Option<Arc<NonceManager>>,
+    /// Receives the cids of the messages pushed, if set.
+    submissions: Option<SubmissionSink>,
 }
 
 impl<T: JsonRpcClient> LotusJsonRPCClient<T> {
@@ -111,6 +118,8 @@ impl<T: JsonRpcClient> LotusJsonRPCClient<T> {
             client,
             subnet,
             wallet_store: None,
+            nonces: None,
+            submissions: None,
         }
     }
 
@@ -123,8 +132,23 @@ impl<T: JsonRpcClient> LotusJsonRPCClient<T> {
             client,
             subnet,
             wallet_store: Some(wallet_store),
+            nonces: None,
+            submissions: None,
         }
     }
+
+    /// Assigns the nonces of the messages pushed with the nonce manager shared with the other
+    /// clients sending from the same accounts.
+    pub fn with_nonce_manager(mut self, nonces: Arc<NonceManager>) -> Self {
+        self.nonces = Some(nonces);
+        self
+    }
+
+    /// Reports the cid of every message pushed to `submissions`.
+    pub fn with_submission_sink(mut self, submissions: SubmissionSink) -> Self {
+        self.submissions = Some(submissions);
+        self
+    }
 }
 
 #[async_trait]
@@ -179,38 +203,38 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
     }
 
     async fn mpool_push(&self, mut msg: MpoolPushMessage) -> Result<Cid> {
-        if msg.nonce.is_none() {
-            let nonce = self.mpool_nonce(&msg.from).await?;
-            log::info!(
-                "sender: {:} with nonce: {nonce:} in subnet: {:}",
-                msg.from,
-                self.subnet
-            );
-            msg.nonce = Some(nonce);
-        }
-
         if msg.version.is_none() {
             msg.version = Some(0);
         }
 
-        self.estimate_message_gas(&mut msg).await?;
-        log::debug!("estimated gas for message: {msg:?}");
-
-        let signature = self.sign_mpool_message(&msg)?;
-
-        let params = create_signed_message_params(msg, signature);
-        log::debug!(
-            "message to push to mpool: {params:?} in subnet: {:?}",
-            self.subnet
-        );
+        if msg.nonce.is_some() {
+            return self.sign_and_push(msg).await;
+        }
 
-        let r = self
-            .client
-            .request::<CIDMap>(methods::MPOOL_PUSH, params)
-            .await?;
-        log::debug!("received mpool_push_message response: {r:?}");
+        let from = msg.from;
+        let nonces = match &self.nonces {
+            Some(nonces) => nonces,
+            None => {
+                let nonce = self.mpool_nonce(&from).await?;
+                log::info!(
+                    "sender: {from:} with nonce: {nonce:} in subnet: {:}",
+                    self.subnet
+                );
+                msg.nonce = Some(nonce);
+                return self.sign_and_push(msg).await;
+            }
+        };
 
-        Cid::try_from(r)
+        nonces
+            .submit(&self.subnet, &from, self.mpool_nonce(&from), |nonce| {
+                log::info!(
+                    "sender: {from:} with nonce: {nonce:} in subnet: {:}",
+                    self.subnet
+                );
+                msg.nonce = Some(nonce);
+                self.sign_and_push(msg)
+            })
+            .await
     }
 
     async fn state_wait_msg(&self, cid: Cid) -> Result<StateWaitMsgResponse> {
@@ -370,7 +394,7 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
         gateway_addr: Address,
         validator: &Address,
         checkpoint: TopDownCheckpoint,
-    ) -> Result<ChainEpoch> {
+    ) -> Result<Cid> {
         let epoch = checkpoint.epoch;
 
         let message = MpoolPushMessage::new(
@@ -379,14 +403,10 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
             ipc_gateway::Method::SubmitTopDownCheckpoint as MethodNum,
             cbor::serialize(&checkpoint, "topdown_checkpoint")?.to_vec(),
         );
-        let message_cid = self.mpool_push(message).await.map_err(|e| {
+        self.mpool_push(message).await.map_err(|e| {
             log::error!("error submitting top down checkpoint at epoch {epoch:} at gateway: {gateway_addr:}");
             e
-        })?;
-
-        self.state_wait_msg(message_cid)
-            .await
-            .map(|r| r.height as ChainEpoch)
+        })
     }
 
     async fn ipc_get_prev_checkpoint_for_child(
@@ -592,6 +612,33 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
 }
 
 impl<T: JsonRpcClient + Send + Sync> LotusJsonRPCClient<T> {
+    /// Estimates the gas of the message with its nonce set, signs it and pushes it to the mpool.
+    async fn sign_and_push(&self, mut msg: MpoolPushMessage) -> Result<Cid> {
+        self.estimate_message_gas(&mut msg).await?;
+        log::debug!("estimated gas for message: {msg:?}");
+
+        let signature = self.sign_mpool_message(&msg)?;
+
+        let params = create_signed_message_params(msg, signature);
+        log::debug!(
+            "message to push to mpool: {params:?} in subnet: {:?}",
+            self.subnet
+        );
+
+        let r = self
+            .client
+            .request::<CIDMap>(methods::MPOOL_PUSH, params)
+            .await?;
+        log::debug!("received mpool_push_message response: {r:?}");
+
+        let cid = Cid::try_from(r)?;
+        if let Some(submissions) = &self.submissions {
+            // the receiver is gone if the caller stopped tracking the message.
+            let _ = submissions.send(cid.to_string());
+        }
+        Ok(cid)
+    }
+
     fn sign_mpool_message(&self, msg: &MpoolPushMessage) -> anyhow::Result<Signature> {
         if self.wallet_store.is_none() {
             return Err(anyhow!("key store not set, function not supported"));
@@ -636,6 +683,11 @@ impl<T: JsonRpcClient + Send + Sync> LotusJsonRPCClient<T> {
     }
 
     async fn estimate_message_gas(&self, msg: &mut MpoolPushMessage) -> anyhow::Result<()> {
+        // the fee cap of the estimate is lowered so that the message pays at most its max fee.
+        let spec = match &msg.max_fee {
+            Some(max_fee) => json!({ "MaxFee": max_fee.atto().to_string() }),
+            None => json!({}),
+        };
         let params = json!([
             {
                 "Version": msg.version.unwrap_or(0),
@@ -652,7 +704,7 @@ impl<T: JsonRpcClient + Send + Sync> LotusJsonRPCClient<T> {
 
                 "CID": CIDMap::from(msg.cid),
             },
-            {},
+            spec,
             []
         ]);
 
@@ -691,7 +743,8 @@ impl LotusJsonRPCClient<JsonRpcClientImpl> {
     pub fn from_subnet(subnet: &crate::config::Subnet) -> Self {
         let url = subnet.rpc_http().clone();
         let auth_token = subnet.auth_token();
-        let jsonrpc_client = JsonRpcClientImpl::new(url, auth_token.as_deref());
+        let jsonrpc_client = JsonRpcClientImpl::new(url, auth_token.as_deref())
+            .with_metrics_backend(metrics::LOTUS_BACKEND);
         LotusJsonRPCClient::new(jsonrpc_client, subnet.id.clone())
     }
 
@@ -701,7 +754,8 @@ impl LotusJsonRPCClient<JsonRpcClientImpl> {
     ) -> Self {
         let url = subnet.rpc_http().clone();
         let auth_token = subnet.auth_token();
-        let jsonrpc_client = JsonRpcClientImpl::new(url, auth_token.as_deref());
+        let jsonrpc_client = JsonRpcClientImpl::new(url, auth_token.as_deref())
+            .with_metrics_backend(metrics::LOTUS_BACKEND);
         LotusJsonRPCClient::new_with_wallet_store(jsonrpc_client, subnet.id.clone(), wallet_store)
     }
 }
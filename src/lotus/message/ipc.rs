@@ -11,6 +11,7 @@ use ipc_gateway::{BottomUpCheckpoint, CrossMsg, Status, StorableMsg};
 use ipc_sdk::address::IPCAddress;
 use ipc_sdk::subnet_id::SubnetID;
 use primitives::TCid;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::lotus::message::deserialize::{
@@ -65,25 +66,29 @@ pub struct Voting {
 ///
 /// Note that the serialization and deserialization casing are different. Reason because for deserialization,
 /// it is from the fvm actor, which is `PascalCase`. When serialize, we are using rust's default casing
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SubnetInfo {
     /// Id of the subnet.
     #[serde(rename(deserialize = "ID"))]
     #[serde(deserialize_with = "deserialize_subnet_id_from_map")]
     #[serde(serialize_with = "serialize_subnet_id_to_str")]
+    #[schemars(with = "String")]
     pub id: SubnetID,
     /// Collateral staked in the subnet.
     #[serde(rename(deserialize = "Stake"))]
     #[serde(deserialize_with = "deserialize_token_amount_from_str")]
     #[serde(serialize_with = "serialize_token_amount_to_atto")]
+    #[schemars(with = "String")]
     pub stake: TokenAmount,
     /// Circulating supply available in the subnet.
     #[serde(rename(deserialize = "CircSupply"))]
     #[serde(deserialize_with = "deserialize_token_amount_from_str")]
     #[serde(serialize_with = "serialize_token_amount_to_atto")]
+    #[schemars(with = "String")]
     pub circ_supply: TokenAmount,
     /// State of the Subnet (Initialized, Active, Killed)
     #[serde(rename(deserialize = "Status"))]
+    #[schemars(with = "i32")]
     pub status: Status,
 }
 
@@ -91,14 +96,14 @@ pub struct SubnetInfo {
 /// In the actor, it is `Deserialize_tuple`, but when returned from json rpc endpoints, it's
 /// actually `json` struct. The deserialization is not working because the agent is interpreting
 /// the tuple as json.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct ValidatorSet {
     pub validators: Option<Vec<Validator>>,
     // sequence number that uniquely identifies a validator set
     pub configuration_number: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct QueryValidatorSetResponse {
     /// The validator set for the subnet fetched from the parent.
     pub validator_set: ValidatorSet,
@@ -109,7 +114,7 @@ pub struct QueryValidatorSetResponse {
 }
 
 /// The validator struct. See `ValidatorSet` comment on why we need this duplicated definition.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Validator {
     pub addr: String,
     pub net_addr: String,
@@ -35,7 +35,6 @@ pub struct ReadStateResponse<State> {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Receipt {
-    #[allow(dead_code)]
     exit_code: u32,
     #[serde(rename = "Return")]
     pub result: Option<String>,
@@ -44,6 +43,14 @@ pub struct Receipt {
 }
 
 impl Receipt {
+    /// Fails if the message was not executed successfully, i.e. it reverted.
+    pub fn check_exit_code(&self) -> anyhow::Result<()> {
+        if self.exit_code != 0 {
+            return Err(anyhow!("exit code: {:}", self.exit_code));
+        }
+        Ok(())
+    }
+
     pub fn parse_result_into<T: Default + DeserializeOwned>(self) -> anyhow::Result<T> {
         if self.result.is_none() {
             return Ok(Default::default());
@@ -66,3 +73,19 @@ impl Receipt {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::lotus::message::state::Receipt;
+
+    #[test]
+    fn test_receipt_exit_code() {
+        let receipt: Receipt =
+            serde_json::from_str(r#"{"ExitCode": 0, "Return": null, "GasUsed": 10}"#).unwrap();
+        assert!(receipt.check_exit_code().is_ok());
+
+        let receipt: Receipt =
+            serde_json::from_str(r#"{"ExitCode": 16, "Return": null, "GasUsed": 10}"#).unwrap();
+        assert!(receipt.check_exit_code().is_err());
+    }
+}
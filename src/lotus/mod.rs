@@ -97,12 +97,14 @@ pub trait LotusClient {
         tip_set: Cid,
     ) -> Result<GetTipSetByHeightResponse>;
 
+    /// Pushes the top down checkpoint to the mpool and returns the cid of the message without
+    /// waiting for it to be executed.
     async fn ipc_submit_top_down_checkpoint(
         &self,
         gateway_addr: Address,
         validator: &Address,
         checkpoint: TopDownCheckpoint,
-    ) -> Result<ChainEpoch>;
+    ) -> Result<Cid>;
 
     async fn ipc_get_prev_checkpoint_for_child(
         &self,
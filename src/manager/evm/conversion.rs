@@ -6,43 +6,36 @@ use crate::checkpoint::{NativeBottomUpCheckpoint, NativeChildCheck};
 use crate::manager::evm::manager::agent_subnet_to_evm_addresses;
 use crate::manager::SubnetInfo;
 use anyhow::anyhow;
+use cid::multihash::Multihash;
+use cid::Cid;
 use ethers::abi::{ParamType, Token};
 use ethers::types::U256;
-use fvm_ipld_encoding::RawBytes;
+use fvm_ipld_encoding::{RawBytes, IPLD_RAW};
 use fvm_shared::address::{Address, Payload};
 use fvm_shared::bigint::BigInt;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::MethodNum;
 use ipc_gateway::checkpoint::{BatchCrossMsgs, CheckData, ChildCheck};
-use ipc_gateway::{BottomUpCheckpoint, CrossMsg, Status, StorableMsg};
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, Status, StorableMsg, TopDownCheckpoint};
 use ipc_sdk::address::IPCAddress;
 use ipc_sdk::subnet_id::SubnetID;
-use primitives::EthAddress;
+use primitives::{EthAddress, TCid};
 use std::str::FromStr;
 
 impl TryFrom<NativeChildCheck> for crate::manager::evm::subnet_contract::ChildCheck {
     type Error = anyhow::Error;
 
     fn try_from(value: NativeChildCheck) -> Result<Self, Self::Error> {
-        let vec_to_array = |v: Vec<u8>| {
-            let bytes = if v.len() > 32 {
-                log::warn!("child check more than 32 bytes, taking only first 32 bytes");
-                &v[0..32]
-            } else {
-                &v
-            };
-
-            let mut array = [0u8; 32];
-            array.copy_from_slice(bytes);
-
-            array
-        };
-        let checks: Vec<[u8; 32]> = value
+        let checks = value
             .checks
-            .into_iter()
-            .map(vec_to_array)
-            .collect::<Vec<_>>();
+            .iter()
+            .map(|c| {
+                let cid = Cid::try_from(c.as_slice())
+                    .map_err(|e| anyhow!("child check is not a valid cid: {e:}"))?;
+                cid_to_evm_hash(&cid)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
         Ok(Self {
             source: crate::manager::evm::subnet_contract::SubnetID::try_from(&value.source)?,
             checks,
@@ -56,7 +49,11 @@ impl TryFrom<crate::manager::evm::subnet_contract::ChildCheck> for NativeChildCh
     fn try_from(
         value: crate::manager::evm::subnet_contract::ChildCheck,
     ) -> Result<Self, Self::Error> {
-        let checks = value.checks.into_iter().map(|v| v.to_vec()).collect();
+        let checks = value
+            .checks
+            .iter()
+            .map(|c| evm_hash_to_cid(c).map(|cid| cid.to_bytes()))
+            .collect::<anyhow::Result<_>>()?;
         Ok(Self {
             source: SubnetID::try_from(value.source)?,
             checks,
@@ -199,6 +196,61 @@ impl TryFrom<CheckData> for crate::manager::evm::subnet_contract::BottomUpCheckp
     }
 }
 
+impl TryFrom<crate::manager::evm::subnet_contract::BottomUpCheckpoint> for BottomUpCheckpoint {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        value: crate::manager::evm::subnet_contract::BottomUpCheckpoint,
+    ) -> Result<Self, Self::Error> {
+        let children = value
+            .children
+            .into_iter()
+            .map(|i| {
+                ChildCheck::try_from(i)
+                    .map_err(|e| anyhow!("cannot convert child check due to: {e:}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let cross_msgs = value
+            .cross_msgs
+            .into_iter()
+            .map(|i| {
+                CrossMsg::try_from(i).map_err(|e| anyhow!("cannot convert cross msg due to: {e:}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut checkpoint =
+            BottomUpCheckpoint::new(SubnetID::try_from(value.source)?, value.epoch as ChainEpoch);
+        checkpoint.data.proof = value.proof.to_vec();
+        checkpoint.data.children = children;
+        checkpoint.data.cross_msgs = BatchCrossMsgs {
+            cross_msgs: Some(cross_msgs),
+            fee: eth_to_fil_amount(&value.fee)?,
+        };
+        if value.prev_hash != [0; 32] {
+            checkpoint.data.prev_check = TCid::from(evm_hash_to_cid(&value.prev_hash)?);
+        }
+        Ok(checkpoint)
+    }
+}
+
+impl TryFrom<crate::manager::evm::subnet_contract::ChildCheck> for ChildCheck {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        value: crate::manager::evm::subnet_contract::ChildCheck,
+    ) -> Result<Self, Self::Error> {
+        let checks = value
+            .checks
+            .iter()
+            .map(|c| evm_hash_to_cid(c).map(TCid::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ChildCheck {
+            source: SubnetID::try_from(value.source)?,
+            checks,
+        })
+    }
+}
+
 impl TryFrom<CrossMsg> for crate::manager::evm::subnet_contract::CrossMsg {
     type Error = anyhow::Error;
 
@@ -262,13 +314,8 @@ impl TryFrom<ChildCheck> for crate::manager::evm::subnet_contract::ChildCheck {
             checks: value
                 .checks
                 .iter()
-                .map(|c| {
-                    let mut v = [0; 32];
-                    // TODO: we should update the solidity contract to use bytes
-                    v.copy_from_slice(&c.cid().to_bytes()[0..32]);
-                    v
-                })
-                .collect(),
+                .map(|c| cid_to_evm_hash(&c.cid()))
+                .collect::<Result<_, _>>()?,
         };
         Ok(c)
     }
@@ -489,6 +536,68 @@ impl TryFrom<crate::manager::evm::gateway::CrossMsg> for CrossMsg {
     }
 }
 
+impl TryFrom<TopDownCheckpoint> for crate::manager::evm::gateway::TopDownCheckpoint {
+    type Error = anyhow::Error;
+
+    fn try_from(checkpoint: TopDownCheckpoint) -> Result<Self, Self::Error> {
+        let top_down_msgs = checkpoint
+            .top_down_msgs
+            .into_iter()
+            .map(|i| {
+                crate::manager::evm::gateway::CrossMsg::try_from(i)
+                    .map_err(|e| anyhow!("cannot convert cross msg due to: {e:}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(crate::manager::evm::gateway::TopDownCheckpoint {
+            epoch: checkpoint.epoch as u64,
+            top_down_msgs,
+        })
+    }
+}
+
+impl TryFrom<CrossMsg> for crate::manager::evm::gateway::CrossMsg {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CrossMsg) -> Result<Self, Self::Error> {
+        let c = crate::manager::evm::gateway::CrossMsg {
+            wrapped: value.wrapped,
+            message: crate::manager::evm::gateway::StorableMsg::try_from(value.msg)
+                .map_err(|e| anyhow!("cannot convert storable msg due to: {e:}"))?,
+        };
+        Ok(c)
+    }
+}
+
+impl TryFrom<IPCAddress> for crate::manager::evm::gateway::Ipcaddress {
+    type Error = anyhow::Error;
+
+    fn try_from(value: IPCAddress) -> Result<Self, Self::Error> {
+        Ok(crate::manager::evm::gateway::Ipcaddress {
+            subnet_id: crate::manager::evm::gateway::SubnetID::try_from(&value.subnet()?)?,
+            raw_address: crate::manager::evm::gateway::FvmAddress::try_from(value.raw_addr()?)?,
+        })
+    }
+}
+
+impl TryFrom<StorableMsg> for crate::manager::evm::gateway::StorableMsg {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StorableMsg) -> Result<Self, Self::Error> {
+        let c = crate::manager::evm::gateway::StorableMsg {
+            from: crate::manager::evm::gateway::Ipcaddress::try_from(value.from)
+                .map_err(|e| anyhow!("cannot convert `from` ipc address msg due to: {e:}"))?,
+            to: crate::manager::evm::gateway::Ipcaddress::try_from(value.to)
+                .map_err(|e| anyhow!("cannot convert `to` ipc address due to: {e:}"))?,
+            value: fil_to_eth_amount(&value.value)?,
+            nonce: value.nonce,
+            method: (value.method as u32).to_be_bytes(),
+            params: ethers::core::types::Bytes::from(value.params.to_vec()),
+        };
+        Ok(c)
+    }
+}
+
 impl TryFrom<crate::manager::evm::subnet_contract::Ipcaddress> for IPCAddress {
     type Error = anyhow::Error;
 
@@ -545,6 +654,25 @@ pub fn eth_to_fil_amount(amount: &U256) -> anyhow::Result<TokenAmount> {
     Ok(TokenAmount::from_atto(v))
 }
 
+/// The multihash code of keccak-256, the hash the evm contracts compute for the checkpoints.
+const KECCAK_256: u64 = 0x1b;
+
+/// Converts the cid of a checkpoint into the 32 bytes hash kept for it in the evm contracts, i.e.
+/// the digest of the cid.
+pub fn cid_to_evm_hash(cid: &Cid) -> anyhow::Result<[u8; 32]> {
+    <[u8; 32]>::try_from(cid.hash().digest())
+        .map_err(|_| anyhow!("cid {cid:} does not have a 32 bytes digest"))
+}
+
+/// Converts a checkpoint hash kept in the evm contracts into a cid. The contracts hash the abi
+/// encoding of the checkpoints with keccak-256, so the cid carries the keccak-256 multihash code
+/// and the raw codec: it identifies the checkpoint but does not resolve to its content.
+pub fn evm_hash_to_cid(hash: &[u8; 32]) -> anyhow::Result<Cid> {
+    let hash = Multihash::wrap(KECCAK_256, hash)
+        .map_err(|e| anyhow!("cannot wrap checkpoint hash due to: {e:}"))?;
+    Ok(Cid::new_v1(IPLD_RAW, hash))
+}
+
 pub fn ethers_address_to_fil_address(addr: &ethers::types::Address) -> anyhow::Result<Address> {
     let raw_addr = format!("{addr:?}");
     log::debug!("raw evm subnet addr: {raw_addr:}");
@@ -555,10 +683,16 @@ pub fn ethers_address_to_fil_address(addr: &ethers::types::Address) -> anyhow::R
 
 #[cfg(test)]
 mod tests {
-    use crate::manager::evm::conversion::eth_to_fil_amount;
+    use crate::checkpoint::NativeBottomUpCheckpoint;
+    use crate::manager::evm::conversion::{cid_to_evm_hash, eth_to_fil_amount, evm_hash_to_cid};
     use crate::manager::evm::subnet_contract::FvmAddress;
+    use fvm_ipld_encoding::RawBytes;
     use fvm_shared::{address::Address, bigint::BigInt, econ::TokenAmount};
-    use primitives::EthAddress;
+    use ipc_gateway::checkpoint::{BatchCrossMsgs, ChildCheck};
+    use ipc_gateway::{BottomUpCheckpoint, CrossMsg, StorableMsg, TopDownCheckpoint};
+    use ipc_sdk::address::IPCAddress;
+    use ipc_sdk::subnet_id::SubnetID;
+    use primitives::{EthAddress, TCid};
     use std::str::FromStr;
 
     use super::fil_to_eth_amount;
@@ -586,4 +720,118 @@ mod tests {
         let test_amount = eth_to_fil_amount(&eth_amount).unwrap();
         assert_eq!(test_amount, fil_amount);
     }
+
+    #[test]
+    fn test_bottom_up_checkpoint_conversion() {
+        let eth_addr = EthAddress::from_str("0x1A79385eAd0e873FE0C441C034636D3Edf7014cC").unwrap();
+        let subnet = SubnetID::new(0, vec![Address::from(eth_addr)]);
+
+        let mut checkpoint = BottomUpCheckpoint::new(subnet.clone(), 10);
+        checkpoint.data.proof = vec![1, 2, 3];
+        checkpoint.data.cross_msgs = BatchCrossMsgs {
+            cross_msgs: Some(vec![]),
+            fee: TokenAmount::from_atto(100),
+        };
+
+        let mut evm_checkpoint =
+            crate::manager::evm::subnet_contract::BottomUpCheckpoint::try_from(checkpoint).unwrap();
+        evm_checkpoint.proof = ethers::types::Bytes::from(vec![1, 2, 3]);
+
+        let converted = BottomUpCheckpoint::try_from(evm_checkpoint).unwrap();
+        assert_eq!(converted.data.source, subnet);
+        assert_eq!(converted.data.epoch, 10);
+        assert_eq!(converted.data.proof, vec![1, 2, 3]);
+        assert_eq!(converted.data.cross_msgs.fee, TokenAmount::from_atto(100));
+        assert!(converted.data.children.is_empty());
+    }
+
+    #[test]
+    fn test_checkpoint_hash_conversion() {
+        let eth_addr = EthAddress::from_str("0x1A79385eAd0e873FE0C441C034636D3Edf7014cC").unwrap();
+        let subnet = SubnetID::new(0, vec![Address::from(eth_addr)]);
+        let child = SubnetID::new(0, vec![Address::from(eth_addr), Address::from(eth_addr)]);
+
+        // the hashes of the evm contracts are kept as keccak-256 cids
+        let hash = [7; 32];
+        let cid = evm_hash_to_cid(&hash).unwrap();
+        assert_eq!(cid.hash().code(), 0x1b);
+        assert_eq!(cid_to_evm_hash(&cid).unwrap(), hash);
+
+        // fvm checkpoints are kept in the evm contracts by their digest
+        let child_checkpoint = BottomUpCheckpoint::new(child.clone(), 10);
+        let mut checkpoint = BottomUpCheckpoint::new(subnet.clone(), 20);
+        checkpoint.data.children = vec![ChildCheck {
+            source: child.clone(),
+            checks: vec![TCid::from(child_checkpoint.cid())],
+        }];
+        let evm_checkpoint =
+            crate::manager::evm::subnet_contract::BottomUpCheckpoint::try_from(checkpoint).unwrap();
+        assert_eq!(
+            evm_checkpoint.children[0].checks,
+            vec![cid_to_evm_hash(&child_checkpoint.cid()).unwrap()]
+        );
+
+        // the fvm and native conversions encode the same child check in the same way
+        let mut evm_checkpoint = crate::manager::evm::subnet_contract::BottomUpCheckpoint {
+            prev_hash: [1; 32],
+            ..evm_checkpoint
+        };
+        evm_checkpoint.children[0].checks = vec![hash];
+
+        let converted = BottomUpCheckpoint::try_from(evm_checkpoint.clone()).unwrap();
+        assert_eq!(
+            converted.data.prev_check.cid(),
+            evm_hash_to_cid(&[1; 32]).unwrap()
+        );
+        assert_eq!(converted.data.children[0].checks, vec![TCid::from(cid)]);
+
+        let native = NativeBottomUpCheckpoint::try_from(evm_checkpoint.clone()).unwrap();
+        assert_eq!(native.children[0].checks, vec![cid.to_bytes()]);
+        assert_eq!(
+            BottomUpCheckpoint::try_from(&native).unwrap().data.children,
+            converted.data.children
+        );
+
+        let back =
+            crate::manager::evm::subnet_contract::BottomUpCheckpoint::try_from(native).unwrap();
+        assert_eq!(back.children, evm_checkpoint.children);
+    }
+
+    #[test]
+    fn test_top_down_checkpoint_conversion() {
+        let eth_addr = EthAddress::from_str("0x1A79385eAd0e873FE0C441C034636D3Edf7014cC").unwrap();
+        let addr = Address::from(eth_addr);
+        let subnet = SubnetID::new(0, vec![addr]);
+        let ipc_addr = IPCAddress::new(&subnet, &addr).unwrap();
+
+        let msg = StorableMsg {
+            from: ipc_addr.clone(),
+            to: ipc_addr,
+            method: 2,
+            params: RawBytes::default(),
+            value: TokenAmount::from_atto(1000),
+            nonce: 3,
+        };
+        let checkpoint = TopDownCheckpoint {
+            epoch: 20,
+            top_down_msgs: vec![CrossMsg {
+                msg: msg.clone(),
+                wrapped: false,
+            }],
+        };
+
+        let evm_checkpoint =
+            crate::manager::evm::gateway::TopDownCheckpoint::try_from(checkpoint).unwrap();
+        assert_eq!(evm_checkpoint.epoch, 20);
+        assert_eq!(evm_checkpoint.top_down_msgs.len(), 1);
+
+        let converted =
+            CrossMsg::try_from(evm_checkpoint.top_down_msgs.into_iter().next().unwrap()).unwrap();
+        assert!(!converted.wrapped);
+        assert_eq!(converted.msg.from, msg.from);
+        assert_eq!(converted.msg.to, msg.to);
+        assert_eq!(converted.msg.method, msg.method);
+        assert_eq!(converted.msg.value, msg.value);
+        assert_eq!(converted.msg.nonce, msg.nonce);
+    }
 }
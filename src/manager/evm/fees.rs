@@ -0,0 +1,210 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Picks the eip-1559 fees of the transactions sent to an evm subnet, and the higher fees of the
+//! transactions replacing the ones stuck in the mempool.
+
+use anyhow::{anyhow, Result};
+use ethers::providers::{Middleware, Provider};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockNumber, FeeHistory, U256};
+
+use crate::config::subnet::{FeeStrategy, DEFAULT_FEE_HISTORY_BLOCKS, DEFAULT_FEE_PERCENTILE};
+use crate::manager::evm::transport::MeteredHttp;
+
+/// The maximum fee and priority fee per gas of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Eip1559Fees {
+    pub(crate) max_fee_per_gas: U256,
+    pub(crate) max_priority_fee_per_gas: U256,
+}
+
+impl Eip1559Fees {
+    /// Sets the fees of the transaction. Legacy transactions pay the maximum fee as gas price.
+    pub(crate) fn apply(&self, tx: &mut TypedTransaction) {
+        match tx.as_eip1559_mut() {
+            Some(tx) => {
+                tx.max_fee_per_gas = Some(self.max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+            }
+            None => {
+                tx.set_gas_price(self.max_fee_per_gas);
+            }
+        }
+    }
+}
+
+/// Estimates the fees of a new transaction according to the strategy.
+pub(crate) async fn estimate_fees(
+    provider: &Provider<MeteredHttp>,
+    strategy: &FeeStrategy,
+) -> Result<Eip1559Fees> {
+    match strategy {
+        FeeStrategy::Fixed {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        } => Ok(Eip1559Fees {
+            max_fee_per_gas: U256::from(*max_fee_per_gas),
+            max_priority_fee_per_gas: U256::from(*max_priority_fee_per_gas),
+        }),
+        FeeStrategy::OraclePercentile { percentile, blocks } => {
+            oracle_fees(provider, *percentile, *blocks).await
+        }
+        FeeStrategy::MaxFeeCap { max_fee_per_gas } => {
+            let fees =
+                oracle_fees(provider, DEFAULT_FEE_PERCENTILE, DEFAULT_FEE_HISTORY_BLOCKS).await?;
+            Ok(cap_fees(fees, U256::from(*max_fee_per_gas)))
+        }
+        FeeStrategy::Provider => {
+            let (max_fee_per_gas, max_priority_fee_per_gas) = provider
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|e| anyhow!("cannot estimate the fees: {e:}"))?;
+            Ok(Eip1559Fees {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            })
+        }
+    }
+}
+
+async fn oracle_fees(
+    provider: &Provider<MeteredHttp>,
+    percentile: u8,
+    blocks: u64,
+) -> Result<Eip1559Fees> {
+    let history = provider
+        .fee_history(blocks, BlockNumber::Latest, &[percentile as f64])
+        .await
+        .map_err(|e| anyhow!("cannot get the fee history: {e:}"))?;
+    fees_from_history(&history)
+}
+
+/// The priority fee is the average of the reward percentile of the blocks, and the maximum fee
+/// leaves room for the base fee of the next block to double.
+fn fees_from_history(history: &FeeHistory) -> Result<Eip1559Fees> {
+    let base_fee = history
+        .base_fee_per_gas
+        .last()
+        .copied()
+        .ok_or_else(|| anyhow!("fee history without base fee"))?;
+
+    let rewards = history
+        .reward
+        .iter()
+        .filter_map(|r| r.first())
+        .collect::<Vec<_>>();
+    let priority_fee = if rewards.is_empty() {
+        U256::zero()
+    } else {
+        rewards.iter().fold(U256::zero(), |sum, r| sum + **r) / U256::from(rewards.len())
+    };
+
+    Ok(Eip1559Fees {
+        max_fee_per_gas: base_fee * U256::from(2) + priority_fee,
+        max_priority_fee_per_gas: priority_fee,
+    })
+}
+
+fn cap_fees(fees: Eip1559Fees, cap: U256) -> Eip1559Fees {
+    let max_fee_per_gas = std::cmp::min(fees.max_fee_per_gas, cap);
+    Eip1559Fees {
+        max_fee_per_gas,
+        max_priority_fee_per_gas: std::cmp::min(fees.max_priority_fee_per_gas, max_fee_per_gas),
+    }
+}
+
+/// The fees of a transaction replacing one paying `previous`: the previous fees increased by
+/// `bump_percent`, or the `current` estimate if higher. Returns `None` if the strategy caps the
+/// fees below the replacement fees.
+pub(crate) fn replacement_fees(
+    previous: &Eip1559Fees,
+    current: &Eip1559Fees,
+    bump_percent: u64,
+    strategy: &FeeStrategy,
+) -> Option<Eip1559Fees> {
+    let bump = |fee: U256| fee * U256::from(100 + bump_percent) / U256::from(100) + U256::one();
+    let max_fee_per_gas = std::cmp::max(bump(previous.max_fee_per_gas), current.max_fee_per_gas);
+    let max_priority_fee_per_gas = std::cmp::min(
+        std::cmp::max(
+            bump(previous.max_priority_fee_per_gas),
+            current.max_priority_fee_per_gas,
+        ),
+        max_fee_per_gas,
+    );
+
+    if let FeeStrategy::MaxFeeCap {
+        max_fee_per_gas: cap,
+    } = strategy
+    {
+        if max_fee_per_gas > U256::from(*cap) {
+            return None;
+        }
+    }
+
+    Some(Eip1559Fees {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::subnet::FeeStrategy;
+    use crate::manager::evm::fees::{cap_fees, fees_from_history, replacement_fees, Eip1559Fees};
+    use ethers::types::{FeeHistory, U256};
+
+    fn fees(max_fee_per_gas: u64, max_priority_fee_per_gas: u64) -> Eip1559Fees {
+        Eip1559Fees {
+            max_fee_per_gas: U256::from(max_fee_per_gas),
+            max_priority_fee_per_gas: U256::from(max_priority_fee_per_gas),
+        }
+    }
+
+    #[test]
+    fn test_fees_from_history() {
+        let history = FeeHistory {
+            base_fee_per_gas: vec![U256::from(90), U256::from(100), U256::from(110)],
+            gas_used_ratio: vec![0.5, 0.5],
+            oldest_block: U256::from(1),
+            reward: vec![vec![U256::from(10)], vec![U256::from(20)]],
+        };
+        assert_eq!(fees_from_history(&history).unwrap(), fees(235, 15));
+
+        let empty = FeeHistory {
+            base_fee_per_gas: vec![],
+            gas_used_ratio: vec![],
+            oldest_block: U256::zero(),
+            reward: vec![],
+        };
+        assert!(fees_from_history(&empty).is_err());
+    }
+
+    #[test]
+    fn test_cap_fees() {
+        assert_eq!(cap_fees(fees(200, 20), U256::from(100)), fees(100, 20));
+        assert_eq!(cap_fees(fees(200, 20), U256::from(10)), fees(10, 10));
+        assert_eq!(cap_fees(fees(50, 20), U256::from(100)), fees(50, 20));
+    }
+
+    #[test]
+    fn test_replacement_fees() {
+        let strategy = FeeStrategy::default();
+
+        // the previous fees are bumped
+        let replacement = replacement_fees(&fees(100, 10), &fees(50, 5), 20, &strategy);
+        assert_eq!(replacement, Some(fees(121, 13)));
+
+        // unless the current estimate is higher
+        let replacement = replacement_fees(&fees(100, 10), &fees(300, 30), 20, &strategy);
+        assert_eq!(replacement, Some(fees(300, 30)));
+
+        // and never past the cap
+        let capped = FeeStrategy::MaxFeeCap {
+            max_fee_per_gas: 110,
+        };
+        assert_eq!(
+            replacement_fees(&fees(100, 10), &fees(50, 5), 20, &capped),
+            None
+        );
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
@@ -8,12 +9,14 @@ pub use crate::manager::evm::{ethers_address_to_fil_address, fil_to_eth_amount};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use cid::Cid;
-use ethers::abi::Tokenizable;
+use ethers::abi::{Detokenize, Tokenizable};
 use ethers::prelude::k256::ecdsa::SigningKey;
 use ethers::prelude::{abigen, Signer, SignerMiddleware};
-use ethers::providers::{Authorization, Http, Middleware, Provider};
+use ethers::providers::{Authorization, Http, Middleware, PendingTransaction, Provider};
 use ethers::signers::{LocalWallet, Wallet};
-use ethers::types::Eip1559TransactionRequest;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{BlockNumber, Eip1559TransactionRequest, TransactionReceipt, TxHash, U256};
+use ethers_contract::ContractCall;
 use fvm_shared::address::Payload;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::{address::Address, econ::TokenAmount};
@@ -22,13 +25,18 @@ use ipc_identity::{EvmKeyStore, PersistentKeyStore};
 use ipc_sdk::subnet_id::SubnetID;
 use ipc_subnet_actor::ConstructParams;
 use num_traits::ToPrimitive;
+use tokio::time::timeout;
 
-use crate::config::subnet::SubnetConfig;
+use crate::config::subnet::{EvmFeeConfig, SubnetConfig};
 use crate::config::Subnet;
 use crate::lotus::message::ipc::{QueryValidatorSetResponse, SubnetInfo, Validator, ValidatorSet};
-use crate::manager::{EthManager, SubnetManager};
+use crate::manager::evm::fees::{estimate_fees, replacement_fees, Eip1559Fees};
+use crate::manager::evm::transport::MeteredHttp;
+use crate::manager::{EthManager, NonceManager, SubnetManager};
+use crate::server::error::AgentError;
+use crate::tracker::SubmissionSink;
 
-pub type DefaultSignerMiddleware = SignerMiddleware<Provider<Http>, Wallet<SigningKey>>;
+pub type DefaultSignerMiddleware = SignerMiddleware<Provider<MeteredHttp>, Wallet<SigningKey>>;
 
 /// Default polling time used by the Ethers provider to check for pending
 /// transactions and events. Default is 7, and for our child subnets we
@@ -55,6 +63,13 @@ abigen!(SubnetRegistry, "contracts/SubnetRegistry.json");
 pub struct EthSubnetManager {
     keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
     ipc_contract_info: IPCContractInfo,
+    /// The fees of the transactions and the replacement of the stuck ones.
+    fees: EvmFeeConfig,
+    /// The subnet of the manager and the nonce manager assigning the nonces of its transactions,
+    /// if set. Otherwise the signer asks the node for the nonce of every transaction.
+    nonces: Option<(SubnetID, Arc<NonceManager>)>,
+    /// Receives the hashes of the transactions sent, if set.
+    submissions: Option<SubmissionSink>,
 }
 
 /// Keep track of the on chain information for the subnet manager
@@ -62,7 +77,7 @@ struct IPCContractInfo {
     gateway_addr: ethers::types::Address,
     registry_addr: ethers::types::Address,
     chain_id: u64,
-    provider: Provider<Http>,
+    provider: Provider<MeteredHttp>,
 }
 
 #[async_trait]
@@ -110,35 +125,30 @@ impl SubnetManager for EthSubnetManager {
         let registry_contract =
             SubnetRegistry::new(self.ipc_contract_info.registry_addr, Arc::new(signer));
 
-        let call = registry_contract.new_subnet_actor(params);
-        let pending_tx = call.send().await?;
+        let mut call = registry_contract.new_subnet_actor(params);
+        self.set_fees(&mut call.tx).await?;
+        let tx_hash = self.send_call(&from, call).await?;
         // We need the retry to parse the deployment event. At the time of this writing, it's a bug
         // in current FEVM that without the retries, events are not picked up.
         // See https://github.com/filecoin-project/community/discussions/638 for more info and updates.
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        match receipt {
-            Some(r) => {
-                for log in r.logs {
-                    log::debug!("log: {log:?}");
-
-                    match ethers_contract::parse_log::<subnet_registry::SubnetDeployedFilter>(log) {
-                        Ok(subnet_deploy) => {
-                            let subnet_registry::SubnetDeployedFilter { subnet_addr } =
-                                subnet_deploy;
-
-                            log::debug!("subnet deployed at {subnet_addr:?}");
-                            return ethers_address_to_fil_address(&subnet_addr);
-                        }
-                        Err(_) => {
-                            log::debug!("no event for subnet actor published yet, continue");
-                            continue;
-                        }
-                    }
+        let receipt = self.wait_or_replace(tx_hash).await?;
+        for log in receipt.logs {
+            log::debug!("log: {log:?}");
+
+            match ethers_contract::parse_log::<subnet_registry::SubnetDeployedFilter>(log) {
+                Ok(subnet_deploy) => {
+                    let subnet_registry::SubnetDeployedFilter { subnet_addr } = subnet_deploy;
+
+                    log::debug!("subnet deployed at {subnet_addr:?}");
+                    return ethers_address_to_fil_address(&subnet_addr);
+                }
+                Err(_) => {
+                    log::debug!("no event for subnet actor published yet, continue");
+                    continue;
                 }
-                Err(anyhow!("no logs receipt"))
             }
-            None => Err(anyhow!("no receipt for event, txn not successful")),
         }
+        Err(anyhow!("no logs receipt"))
     }
 
     async fn join_subnet(
@@ -167,8 +177,10 @@ impl SubnetManager for EthSubnetManager {
             subnet_contract::FvmAddress::from(worker_addr),
         );
         txn.tx.set_value(collateral);
+        self.set_fees(&mut txn.tx).await?;
 
-        txn.send().await?.await?;
+        let tx_hash = self.send_call(&from, txn).await?;
+        self.wait_or_replace(tx_hash).await?;
 
         Ok(())
     }
@@ -180,7 +192,11 @@ impl SubnetManager for EthSubnetManager {
         let signer = self.get_signer(&from)?;
         let contract = SubnetContract::new(address, Arc::new(signer));
 
-        contract.leave().send().await?.await?;
+        let mut txn = contract.leave();
+        self.set_fees(&mut txn.tx).await?;
+
+        let tx_hash = self.send_call(&from, txn).await?;
+        self.wait_or_replace(tx_hash).await?;
 
         Ok(())
     }
@@ -192,7 +208,11 @@ impl SubnetManager for EthSubnetManager {
         let signer = self.get_signer(&from)?;
         let contract = SubnetContract::new(address, Arc::new(signer));
 
-        contract.kill().send().await?.await?;
+        let mut txn = contract.kill();
+        self.set_fees(&mut txn.tx).await?;
+
+        let tx_hash = self.send_call(&from, txn).await?;
+        self.wait_or_replace(tx_hash).await?;
 
         Ok(())
     }
@@ -243,10 +263,11 @@ impl SubnetManager for EthSubnetManager {
         let gateway_contract = Gateway::new(self.ipc_contract_info.gateway_addr, Arc::new(signer));
         let mut txn = gateway_contract.fund(evm_subnet_id, gateway::FvmAddress::try_from(to)?);
         txn.tx.set_value(value);
+        self.set_fees(&mut txn.tx).await?;
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        block_number_from_receipt(receipt)
+        let tx_hash = self.send_call(&from, txn).await?;
+        let receipt = self.wait_or_replace(tx_hash).await?;
+        block_number_from_receipt(Some(receipt))
     }
 
     async fn release(
@@ -270,10 +291,11 @@ impl SubnetManager for EthSubnetManager {
         let gateway_contract = Gateway::new(self.ipc_contract_info.gateway_addr, Arc::new(signer));
         let mut txn = gateway_contract.release(gateway::FvmAddress::try_from(to)?);
         txn.tx.set_value(value);
+        self.set_fees(&mut txn.tx).await?;
 
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        block_number_from_receipt(receipt)
+        let tx_hash = self.send_call(&from, txn).await?;
+        let receipt = self.wait_or_replace(tx_hash).await?;
+        block_number_from_receipt(Some(receipt))
     }
 
     async fn propagate(
@@ -308,18 +330,25 @@ impl SubnetManager for EthSubnetManager {
 
     /// Send value between two addresses in a subnet
     async fn send_value(&self, from: Address, to: Address, amount: TokenAmount) -> Result<()> {
-        let tx = Eip1559TransactionRequest::new()
-            .to(payload_to_evm_address(to.payload())?)
-            .value(fil_to_eth_amount(&amount)?);
+        let mut tx = TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .to(payload_to_evm_address(to.payload())?)
+                .value(fil_to_eth_amount(&amount)?),
+        );
+        self.set_fees(&mut tx).await?;
 
         let signer = self.get_signer(&from)?;
-        let tx_pending = signer.send_transaction(tx, None).await?;
+        let tx_hash = self
+            .send_with_nonce(&from, |nonce| async move {
+                if let Some(nonce) = nonce {
+                    tx.set_nonce(nonce);
+                }
+                Ok(signer.send_transaction(tx, None).await?.tx_hash())
+            })
+            .await?;
 
-        log::info!(
-            "sending FIL from {from:} to {to:} in tx {:?}",
-            tx_pending.tx_hash()
-        );
-        tx_pending.await?;
+        log::info!("sending FIL from {from:} to {to:} in tx {tx_hash:?}");
+        self.wait_or_replace(tx_hash).await?;
         Ok(())
     }
 
@@ -440,23 +469,22 @@ impl EthManager for EthSubnetManager {
         &self,
         from: &Address,
         checkpoint: TopDownCheckpoint,
-    ) -> Result<ChainEpoch> {
+    ) -> Result<TxHash> {
         log::debug!("submit top down checkpoint: {:?}", checkpoint);
 
         let signer = self.get_signer(from)?;
         let gateway_contract = Gateway::new(self.ipc_contract_info.gateway_addr, Arc::new(signer));
 
-        let txn = gateway_contract.submit_top_down_checkpoint(checkpoint);
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        block_number_from_receipt(receipt)
+        let mut txn = gateway_contract.submit_top_down_checkpoint(checkpoint);
+        self.set_fees(&mut txn.tx).await?;
+        self.send_call(from, txn).await
     }
 
     async fn submit_bottom_up_checkpoint(
         &self,
         from: &Address,
         checkpoint: subnet_contract::BottomUpCheckpoint,
-    ) -> Result<ChainEpoch> {
+    ) -> Result<TxHash> {
         let route = &checkpoint.source.route;
 
         log::debug!(
@@ -468,10 +496,14 @@ impl EthManager for EthSubnetManager {
         let signer = self.get_signer(from)?;
         let contract = SubnetContract::new(route[route.len() - 1], Arc::new(signer));
 
-        let txn = contract.submit_checkpoint(checkpoint);
-        let pending_tx = txn.send().await?;
-        let receipt = pending_tx.retries(TRANSACTION_RECEIPT_RETRIES).await?;
-        block_number_from_receipt(receipt)
+        let mut txn = contract.submit_checkpoint(checkpoint);
+        self.set_fees(&mut txn.tx).await?;
+        self.send_call(from, txn).await
+    }
+
+    async fn wait_transaction(&self, tx_hash: TxHash) -> Result<ChainEpoch> {
+        let receipt = self.wait_or_replace(tx_hash).await?;
+        block_number_from_receipt(Some(receipt))
     }
 
     async fn has_voted_in_subnet(
@@ -557,6 +589,19 @@ impl EthManager for EthSubnetManager {
         }
     }
 
+    async fn gateway_applied_top_down_nonce(&self) -> Result<u64> {
+        let gateway_contract = Gateway::new(
+            self.ipc_contract_info.gateway_addr,
+            Arc::new(self.ipc_contract_info.provider.clone()),
+        );
+        let nonce = gateway_contract
+            .applied_top_down_nonce()
+            .call()
+            .await
+            .map_err(|e| anyhow!("cannot get gateway applied top down nonce due to: {e:}"))?;
+        Ok(nonce)
+    }
+
     async fn top_down_msgs(
         &self,
         subnet_id: &SubnetID,
@@ -607,6 +652,15 @@ impl EthManager for EthSubnetManager {
             .collect::<Result<Vec<_>>>()
     }
 
+    async fn validator_set_configuration_number(&self, subnet_id: &SubnetID) -> Result<u64> {
+        let address = contract_address_from_subnet(subnet_id)?;
+        let contract =
+            SubnetContract::new(address, Arc::new(self.ipc_contract_info.provider.clone()));
+
+        let configuration_number = contract.configuration_number().call().await?;
+        Ok(configuration_number)
+    }
+
     async fn gateway_initialized(&self) -> Result<bool> {
         let gateway_contract = Gateway::new(
             self.ipc_contract_info.gateway_addr,
@@ -666,8 +720,9 @@ impl EthSubnetManager {
         gateway_addr: ethers::types::Address,
         registry_addr: ethers::types::Address,
         chain_id: u64,
-        provider: Provider<Http>,
+        provider: Provider<MeteredHttp>,
         keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        fees: EvmFeeConfig,
     ) -> Self {
         Self {
             keystore,
@@ -677,9 +732,25 @@ impl EthSubnetManager {
                 chain_id,
                 provider,
             },
+            fees,
+            nonces: None,
+            submissions: None,
         }
     }
 
+    /// Assigns the nonces of the transactions sent in `subnet` with the nonce manager shared with
+    /// the other managers sending from the same accounts.
+    pub fn with_nonce_manager(mut self, subnet: SubnetID, nonces: Arc<NonceManager>) -> Self {
+        self.nonces = Some((subnet, nonces));
+        self
+    }
+
+    /// Reports the hash of every transaction sent, replacements included, to `submissions`.
+    pub fn with_submission_sink(mut self, submissions: SubmissionSink) -> Self {
+        self.submissions = Some(submissions);
+        self
+    }
+
     pub fn ensure_same_gateway(&self, gateway: &Address) -> Result<()> {
         let evm_gateway_addr = payload_to_evm_address(gateway.payload())?;
         if evm_gateway_addr != self.ipc_contract_info.gateway_addr {
@@ -698,7 +769,7 @@ impl EthSubnetManager {
         let keystore = self.keystore.read().unwrap();
         let private_key = keystore
             .get(&addr)?
-            .ok_or_else(|| anyhow!("address {addr:} does not have private key in key store"))?;
+            .ok_or_else(|| AgentError::key_not_found(format!("{addr:?}")))?;
         let wallet = LocalWallet::from_bytes(private_key.private_key())?
             .with_chain_id(self.ipc_contract_info.chain_id);
 
@@ -707,6 +778,172 @@ impl EthSubnetManager {
             wallet,
         ))
     }
+
+    /// Sends the contract call from the account, returning the hash of the transaction.
+    async fn send_call<D: Detokenize>(
+        &self,
+        from: &Address,
+        mut call: ContractCall<DefaultSignerMiddleware, D>,
+    ) -> Result<TxHash> {
+        self.send_with_nonce(from, |nonce| async move {
+            if let Some(nonce) = nonce {
+                call.tx.set_nonce(nonce);
+            }
+            // the transaction reverts already when its gas is estimated.
+            let pending = call.send().await.map_err(|e| {
+                if e.is_revert() {
+                    anyhow::Error::new(AgentError::Reverted {
+                        reason: e.decode_revert::<String>().unwrap_or_else(|| e.to_string()),
+                    })
+                } else {
+                    anyhow::Error::new(e)
+                }
+            })?;
+            Ok(pending.tx_hash())
+        })
+        .await
+    }
+
+    /// Sends a transaction from the account with `send`, which is given the nonce assigned by the
+    /// nonce manager, if any.
+    async fn send_with_nonce<F, Fut>(&self, from: &Address, send: F) -> Result<TxHash>
+    where
+        F: FnOnce(Option<U256>) -> Fut,
+        Fut: Future<Output = Result<TxHash>>,
+    {
+        let tx_hash = match &self.nonces {
+            Some((subnet, nonces)) => {
+                let pending = self.pending_nonce(from);
+                nonces
+                    .submit(subnet, from, pending, |nonce| send(Some(U256::from(nonce))))
+                    .await?
+            }
+            None => send(None).await?,
+        };
+        self.report_submission(tx_hash);
+        Ok(tx_hash)
+    }
+
+    fn report_submission(&self, tx_hash: TxHash) {
+        if let Some(submissions) = &self.submissions {
+            // the receiver is gone if the caller stopped tracking the transaction.
+            let _ = submissions.send(format!("{tx_hash:?}"));
+        }
+    }
+
+    /// The next nonce of the account, including its transactions in the mempool.
+    async fn pending_nonce(&self, from: &Address) -> Result<u64> {
+        let address = payload_to_evm_address(from.payload())?;
+        let nonce = self
+            .ipc_contract_info
+            .provider
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await?;
+        Ok(nonce.as_u64())
+    }
+
+    /// Sets the fees of a new transaction according to the fee strategy of the subnet.
+    async fn set_fees(&self, tx: &mut TypedTransaction) -> Result<()> {
+        let fees = estimate_fees(&self.ipc_contract_info.provider, &self.fees.strategy).await?;
+        log::debug!("fees of the transaction: {fees:?}");
+        fees.apply(tx);
+        Ok(())
+    }
+
+    /// Waits for the transaction to be mined. A transaction still pending after the replacement
+    /// timeout is replaced by one with the same nonce and higher fees, up to the maximum number of
+    /// replacements. Returns the receipt of whichever of them was mined, and fails if it reverted.
+    async fn wait_or_replace(&self, tx_hash: TxHash) -> Result<TransactionReceipt> {
+        let provider = &self.ipc_contract_info.provider;
+        let deadline = Duration::from_secs(self.fees.replacement_timeout_secs);
+
+        let mut sent = vec![tx_hash];
+        let mut replaceable = true;
+        loop {
+            let current = *sent.last().unwrap();
+            let pending =
+                PendingTransaction::new(current, provider).retries(TRANSACTION_RECEIPT_RETRIES);
+            if !replaceable || sent.len() > self.fees.max_replacements as usize {
+                let receipt = pending.await?.ok_or_else(|| {
+                    anyhow!("txn {current:?} sent to network, but receipt cannot be obtained")
+                })?;
+                return check_receipt_status(receipt);
+            }
+
+            match timeout(deadline, pending).await {
+                Ok(receipt) => {
+                    let receipt =
+                        receipt?.ok_or_else(|| anyhow!("txn {current:?} dropped from mempool"))?;
+                    return check_receipt_status(receipt);
+                }
+                Err(_) => log::warn!("txn {current:?} still pending after {deadline:?}"),
+            }
+
+            // one of the replaced transactions might have been mined in the meantime.
+            for hash in sent.iter() {
+                if let Some(receipt) = provider.get_transaction_receipt(*hash).await? {
+                    return check_receipt_status(receipt);
+                }
+            }
+
+            match self.replace_transaction(current).await? {
+                Some(replacement) => sent.push(replacement),
+                None => replaceable = false,
+            }
+        }
+    }
+
+    /// Sends a transaction with the same nonce and higher fees than the pending transaction.
+    /// Returns `None` if the fee strategy does not allow higher fees.
+    async fn replace_transaction(&self, tx_hash: TxHash) -> Result<Option<TxHash>> {
+        let provider = &self.ipc_contract_info.provider;
+        let tx = provider
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or_else(|| anyhow!("txn {tx_hash:?} not found"))?;
+
+        let previous = Eip1559Fees {
+            max_fee_per_gas: tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default(),
+            max_priority_fee_per_gas: tx
+                .max_priority_fee_per_gas
+                .or(tx.gas_price)
+                .unwrap_or_default(),
+        };
+        let current = estimate_fees(provider, &self.fees.strategy).await?;
+        let fees = match replacement_fees(
+            &previous,
+            &current,
+            self.fees.replacement_fee_bump_percent,
+            &self.fees.strategy,
+        ) {
+            Some(fees) => fees,
+            None => {
+                log::warn!("txn {tx_hash:?} cannot be replaced without exceeding the fee cap");
+                return Ok(None);
+            }
+        };
+
+        let mut replacement = Eip1559TransactionRequest::new()
+            .from(tx.from)
+            .nonce(tx.nonce)
+            .value(tx.value)
+            .data(tx.input)
+            .gas(tx.gas)
+            .max_fee_per_gas(fees.max_fee_per_gas)
+            .max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        if let Some(to) = tx.to {
+            replacement = replacement.to(to);
+        }
+
+        let signer = self.get_signer(&ethers_address_to_fil_address(&tx.from)?)?;
+        let replacement_hash = signer.send_transaction(replacement, None).await?.tx_hash();
+        log::warn!(
+            "replaced txn {tx_hash:?} with {replacement_hash:?} with nonce {:} and fees {fees:?}",
+            tx.nonce
+        );
+        self.report_submission(replacement_hash);
+        Ok(Some(replacement_hash))
+    }
 }
 
 impl EthSubnetManager {
@@ -729,7 +966,7 @@ impl EthSubnetManager {
             Http::new(url)
         };
 
-        let mut provider = Provider::new(provider);
+        let mut provider = Provider::new(MeteredHttp::from(provider));
         // set polling interval for provider to fit fast child subnets block times.
         // TODO: We may want to make it dynamic so it adjusts depending on the type of network
         // so we don't have a too slow or too fast polling for the underlying block times.
@@ -743,11 +980,24 @@ impl EthSubnetManager {
             subnet.id.chain_id(),
             provider,
             keystore,
+            config.fee_config(),
         ))
     }
 }
 
 /// Get the block number from the transaction receipt
+/// Fails with [`AgentError::Reverted`] if the transaction of the receipt was not executed
+/// successfully.
+fn check_receipt_status(receipt: TransactionReceipt) -> Result<TransactionReceipt> {
+    if receipt.status == Some(0.into()) {
+        return Err(AgentError::Reverted {
+            reason: format!("txn {:?} failed", receipt.transaction_hash),
+        }
+        .into());
+    }
+    Ok(receipt)
+}
+
 fn block_number_from_receipt(
     receipt: Option<ethers::types::TransactionReceipt>,
 ) -> Result<ChainEpoch> {
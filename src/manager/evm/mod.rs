@@ -2,16 +2,22 @@
 // SPDX-License-Identifier: MIT
 
 mod conversion;
+mod fees;
 mod manager;
+mod transport;
 
 use async_trait::async_trait;
+use ethers::types::TxHash;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_gateway::CrossMsg;
 use ipc_sdk::subnet_id::SubnetID;
 
 use super::subnet::SubnetManager;
-pub use conversion::{eth_to_fil_amount, ethers_address_to_fil_address, fil_to_eth_amount};
+pub use conversion::{
+    cid_to_evm_hash, eth_to_fil_amount, ethers_address_to_fil_address, evm_hash_to_cid,
+    fil_to_eth_amount,
+};
 pub use manager::{gateway, subnet_contract, EthSubnetManager};
 
 #[async_trait]
@@ -28,19 +34,24 @@ pub trait EthManager: SubnetManager {
     /// The current epoch/block number of the blockchain that the manager connects to.
     async fn current_epoch(&self) -> anyhow::Result<ChainEpoch>;
 
-    /// Submit top down checkpoint the gateway.
+    /// Submit top down checkpoint the gateway. Returns the hash of the transaction without waiting
+    /// for it to be executed.
     async fn submit_top_down_checkpoint(
         &self,
         from: &Address,
         checkpoint: gateway::TopDownCheckpoint,
-    ) -> anyhow::Result<ChainEpoch>;
+    ) -> anyhow::Result<TxHash>;
 
-    /// Submit bottom up checkpoint to the subnet actor.
+    /// Submit bottom up checkpoint to the subnet actor. Returns the hash of the transaction without
+    /// waiting for it to be executed.
     async fn submit_bottom_up_checkpoint(
         &self,
         from: &Address,
         checkpoint: subnet_contract::BottomUpCheckpoint,
-    ) -> anyhow::Result<ChainEpoch>;
+    ) -> anyhow::Result<TxHash>;
+
+    /// Waits for the transaction to be executed and returns the block number it was included in.
+    async fn wait_transaction(&self, tx_hash: TxHash) -> anyhow::Result<ChainEpoch>;
 
     /// Has the validator voted in subnet contract at epoch
     async fn has_voted_in_subnet(
@@ -66,6 +77,9 @@ pub trait EthManager: SubnetManager {
     /// Get the latest applied top down nonce
     async fn get_applied_top_down_nonce(&self, subnet_id: &SubnetID) -> anyhow::Result<u64>;
 
+    /// Get the latest top down nonce applied by the gateway of the subnet the manager connects to
+    async fn gateway_applied_top_down_nonce(&self) -> anyhow::Result<u64>;
+
     /// Get the bottom up checkpoint a certain epoch
     async fn top_down_msgs(
         &self,
@@ -77,6 +91,11 @@ pub trait EthManager: SubnetManager {
     /// Get the list of validators in a subnet
     async fn validators(&self, subnet_id: &SubnetID) -> anyhow::Result<Vec<Address>>;
 
+    /// Get the configuration number of the validator set in a subnet. It changes every time a
+    /// validator joins or leaves.
+    async fn validator_set_configuration_number(&self, subnet_id: &SubnetID)
+        -> anyhow::Result<u64>;
+
     /// Checks if the gateway is initialized
     async fn gateway_initialized(&self) -> anyhow::Result<bool>;
 
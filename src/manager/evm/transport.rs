@@ -0,0 +1,37 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The http transport of the ethers provider, recording the latency of every call in the metrics.
+
+use async_trait::async_trait;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+use crate::metrics;
+
+/// Wraps the ethers http transport to time its json rpc calls.
+#[derive(Debug, Clone)]
+pub struct MeteredHttp(Http);
+
+impl From<Http> for MeteredHttp {
+    fn from(http: Http) -> Self {
+        Self(http)
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for MeteredHttp {
+    type Error = HttpClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let _timer = metrics::RPC_CALL_LATENCY
+            .with_label_values(&[metrics::EVM_BACKEND, method])
+            .start_timer();
+        self.0.request(method, params).await
+    }
+}
@@ -2,31 +2,32 @@ use std::str::FromStr;
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use crate::checkpoint::{
-    create_proof, BottomUpHandler, CheckpointQuery, NativeBottomUpCheckpoint, TopDownHandler,
-    VoteQuery,
+    create_proof, BottomUpHandler, CheckpointQuery, NativeBottomUpCheckpoint, SubmissionQuery,
+    TopDownHandler, VoteQuery,
 };
 use crate::jsonrpc::JsonRpcClientImpl;
 use crate::lotus::client::LotusJsonRPCClient;
-use crate::manager::evm::subnet_contract;
-use crate::manager::{EthManager, EthSubnetManager, SubnetManager};
+use crate::lotus::LotusClient;
+use crate::manager::evm::{gateway, subnet_contract};
+use crate::manager::{EthManager, EthSubnetManager};
 use anyhow::anyhow;
 use async_trait::async_trait;
+use ethers::types::TxHash;
 use fil_actors_runtime::cbor;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_gateway::{CrossMsg, TopDownCheckpoint};
 use ipc_sdk::subnet_id::SubnetID;
 
-pub struct FevmSubnetManager {
-    evm_subnet_manager: EthSubnetManager,
-    lotus_client: LotusJsonRPCClient<JsonRpcClientImpl>,
+/// The checkpoint handlers of an fevm subnet. The evm manager reads and submits the checkpoints to
+/// the contracts, while the lotus client of the subnet generates the proofs of its checkpoints.
+pub struct FevmSubnetManager<E = EthSubnetManager, L = LotusJsonRPCClient<JsonRpcClientImpl>> {
+    evm_subnet_manager: E,
+    lotus_client: L,
 }
 
-impl FevmSubnetManager {
-    pub fn new(
-        evm_subnet_manager: EthSubnetManager,
-        lotus_client: LotusJsonRPCClient<JsonRpcClientImpl>,
-    ) -> Self {
+impl<E: EthManager, L: LotusClient + Send + Sync> FevmSubnetManager<E, L> {
+    pub fn new(evm_subnet_manager: E, lotus_client: L) -> Self {
         Self {
             evm_subnet_manager,
             lotus_client,
@@ -35,7 +36,9 @@ impl FevmSubnetManager {
 }
 
 #[async_trait]
-impl VoteQuery<NativeBottomUpCheckpoint> for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> VoteQuery<NativeBottomUpCheckpoint>
+    for FevmSubnetManager<E, L>
+{
     async fn last_executed_epoch(&self, subnet_id: &SubnetID) -> anyhow::Result<ChainEpoch> {
         self.evm_subnet_manager
             .subnet_last_voting_executed_epoch(subnet_id)
@@ -59,7 +62,9 @@ impl VoteQuery<NativeBottomUpCheckpoint> for FevmSubnetManager {
 }
 
 #[async_trait]
-impl CheckpointQuery<NativeBottomUpCheckpoint> for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> CheckpointQuery<NativeBottomUpCheckpoint>
+    for FevmSubnetManager<E, L>
+{
     async fn checkpoint_period(&self, subnet_id: &SubnetID) -> anyhow::Result<ChainEpoch> {
         self.evm_subnet_manager
             .subnet_bottom_up_checkpoint_period(subnet_id)
@@ -69,10 +74,16 @@ impl CheckpointQuery<NativeBottomUpCheckpoint> for FevmSubnetManager {
     async fn validators(&self, subnet_id: &SubnetID) -> anyhow::Result<Vec<Address>> {
         self.evm_subnet_manager.validators(subnet_id).await
     }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> anyhow::Result<u64> {
+        self.evm_subnet_manager
+            .validator_set_configuration_number(subnet_id)
+            .await
+    }
 }
 
 #[async_trait]
-impl BottomUpHandler for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> BottomUpHandler for FevmSubnetManager<E, L> {
     async fn checkpoint_template(
         &self,
         epoch: ChainEpoch,
@@ -108,18 +119,30 @@ impl BottomUpHandler for FevmSubnetManager {
         &self,
         validator: &Address,
         checkpoint: NativeBottomUpCheckpoint,
-    ) -> anyhow::Result<ChainEpoch> {
-        self.evm_subnet_manager
+    ) -> anyhow::Result<String> {
+        let tx_hash = self
+            .evm_subnet_manager
             .submit_bottom_up_checkpoint(
                 validator,
                 subnet_contract::BottomUpCheckpoint::try_from(checkpoint)?,
             )
-            .await
+            .await?;
+        Ok(format!("{tx_hash:?}"))
     }
 }
 
 #[async_trait]
-impl VoteQuery<TopDownCheckpoint> for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> SubmissionQuery for FevmSubnetManager<E, L> {
+    async fn wait_submission(&self, submission: &str) -> anyhow::Result<ChainEpoch> {
+        let tx_hash = TxHash::from_str(submission)?;
+        self.evm_subnet_manager.wait_transaction(tx_hash).await
+    }
+}
+
+#[async_trait]
+impl<E: EthManager, L: LotusClient + Send + Sync> VoteQuery<TopDownCheckpoint>
+    for FevmSubnetManager<E, L>
+{
     async fn last_executed_epoch(&self, _subnet_id: &SubnetID) -> anyhow::Result<ChainEpoch> {
         self.evm_subnet_manager
             .gateway_last_voting_executed_epoch()
@@ -147,7 +170,9 @@ impl VoteQuery<TopDownCheckpoint> for FevmSubnetManager {
 }
 
 #[async_trait]
-impl CheckpointQuery<TopDownCheckpoint> for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> CheckpointQuery<TopDownCheckpoint>
+    for FevmSubnetManager<E, L>
+{
     async fn checkpoint_period(&self, _subnet_id: &SubnetID) -> anyhow::Result<ChainEpoch> {
         self.evm_subnet_manager
             .gateway_top_down_check_period()
@@ -170,17 +195,24 @@ impl CheckpointQuery<TopDownCheckpoint> for FevmSubnetManager {
             Ok(vec![])
         }
     }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> anyhow::Result<u64> {
+        self.evm_subnet_manager
+            .validator_set_configuration_number(subnet_id)
+            .await
+    }
 }
 
 #[async_trait]
-impl TopDownHandler for FevmSubnetManager {
+impl<E: EthManager, L: LotusClient + Send + Sync> TopDownHandler for FevmSubnetManager<E, L> {
     async fn gateway_initialized(&self) -> anyhow::Result<bool> {
         self.evm_subnet_manager.gateway_initialized().await
     }
 
-    async fn applied_topdown_nonce(&self, subnet_id: &SubnetID) -> anyhow::Result<u64> {
+    async fn applied_topdown_nonce(&self, _subnet_id: &SubnetID) -> anyhow::Result<u64> {
+        // the nonce is read from the gateway of the child subnet this handler submits to
         self.evm_subnet_manager
-            .get_applied_top_down_nonce(subnet_id)
+            .gateway_applied_top_down_nonce()
             .await
     }
 
@@ -197,9 +229,16 @@ impl TopDownHandler for FevmSubnetManager {
 
     async fn submit(
         &self,
-        _validator: &Address,
-        _checkpoint: TopDownCheckpoint,
-    ) -> anyhow::Result<ChainEpoch> {
-        todo!()
+        validator: &Address,
+        checkpoint: TopDownCheckpoint,
+    ) -> anyhow::Result<String> {
+        let tx_hash = self
+            .evm_subnet_manager
+            .submit_top_down_checkpoint(
+                validator,
+                gateway::TopDownCheckpoint::try_from(checkpoint)?,
+            )
+            .await?;
+        Ok(format!("{tx_hash:?}"))
     }
 }
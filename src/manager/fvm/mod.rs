@@ -8,8 +8,8 @@ use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
 use crate::checkpoint::{
-    create_proof, BottomUpHandler, CheckpointQuery, NativeBottomUpCheckpoint, TopDownHandler,
-    VoteQuery,
+    create_proof, BottomUpHandler, CheckpointQuery, NativeBottomUpCheckpoint, SubmissionQuery,
+    TopDownHandler, VoteQuery,
 };
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -37,6 +37,8 @@ use crate::lotus::message::ipc::{
 use crate::lotus::message::mpool::MpoolPushMessage;
 use crate::lotus::message::state::StateWaitMsgResponse;
 use crate::lotus::LotusClient;
+use crate::relayer::PostboxHandler;
+use crate::server::error::AgentError;
 
 use super::subnet::SubnetManager;
 
@@ -414,12 +416,14 @@ impl<T: JsonRpcClient + Send + Sync> LotusSubnetManager<T> {
             .await
     }
 
-    /// Publish the message to memory pool and wait for the response
+    /// Publish the message to memory pool and wait for the response. Fails if the message reverted.
     async fn mpool_push_and_wait(&self, message: MpoolPushMessage) -> Result<StateWaitMsgResponse> {
         let message_cid = self.lotus_client.mpool_push(message).await?;
         log::debug!("message published with cid: {message_cid:?}");
 
-        self.lotus_client.state_wait_msg(message_cid).await
+        let response = self.lotus_client.state_wait_msg(message_cid).await?;
+        check_receipt(&message_cid, &response)?;
+        Ok(response)
     }
 
     /// Checks the `network` is the one we are currently talking to.
@@ -494,6 +498,11 @@ impl<T: JsonRpcClient + Send + Sync> LotusSubnetManager<T> {
             .map(|f| Address::from_str(&f.addr).map_err(|e| anyhow!("cannot create address: {e:}")))
             .collect::<Result<_>>()
     }
+
+    async fn get_validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64> {
+        let subnet_actor_state = self.get_subnet_state(subnet_id).await?;
+        Ok(subnet_actor_state.validator_set.configuration_number)
+    }
 }
 
 #[async_trait]
@@ -552,6 +561,10 @@ impl<T: JsonRpcClient + Send + Sync> CheckpointQuery<NativeBottomUpCheckpoint>
     async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>> {
         self.get_validators(subnet_id).await
     }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64> {
+        self.get_validator_set_version(subnet_id).await
+    }
 }
 
 #[async_trait]
@@ -613,7 +626,7 @@ impl<T: JsonRpcClient + Send + Sync> BottomUpHandler for LotusSubnetManager<T> {
         &self,
         validator: &Address,
         checkpoint: NativeBottomUpCheckpoint,
-    ) -> Result<ChainEpoch> {
+    ) -> Result<String> {
         let to = checkpoint.source.subnet_actor();
         let message = MpoolPushMessage::new(
             to,
@@ -630,6 +643,14 @@ impl<T: JsonRpcClient + Send + Sync> BottomUpHandler for LotusSubnetManager<T> {
         })?;
         log::debug!("checkpoint message published with cid: {message_cid:?}");
 
+        Ok(message_cid.to_string())
+    }
+}
+
+#[async_trait]
+impl<T: JsonRpcClient + Send + Sync> SubmissionQuery for LotusSubnetManager<T> {
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch> {
+        let message_cid = Cid::from_str(submission)?;
         Ok(self.lotus_client.state_wait_msg(message_cid).await?.height as ChainEpoch)
     }
 }
@@ -680,6 +701,10 @@ impl<T: JsonRpcClient + Send + Sync> CheckpointQuery<TopDownCheckpoint> for Lotu
     async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>> {
         self.get_validators(subnet_id).await
     }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64> {
+        self.get_validator_set_version(subnet_id).await
+    }
 }
 
 #[async_trait]
@@ -717,17 +742,67 @@ impl<T: JsonRpcClient + Send + Sync> TopDownHandler for LotusSubnetManager<T> {
         Ok(top_down_msgs)
     }
 
-    async fn submit(
-        &self,
-        validator: &Address,
-        checkpoint: TopDownCheckpoint,
-    ) -> Result<ChainEpoch> {
-        let submitted_epoch = self
+    async fn submit(&self, validator: &Address, checkpoint: TopDownCheckpoint) -> Result<String> {
+        let message_cid = self
             .lotus_client
             .ipc_submit_top_down_checkpoint(self.gateway_addr, validator, checkpoint)
             .await?;
 
-        Ok(submitted_epoch)
+        Ok(message_cid.to_string())
+    }
+}
+
+#[async_trait]
+impl<T: JsonRpcClient + Send + Sync> PostboxHandler for LotusSubnetManager<T> {
+    async fn child_subnets(&self) -> Result<Vec<SubnetID>> {
+        let subnets = self
+            .lotus_client
+            .ipc_list_child_subnets(self.gateway_addr)
+            .await?;
+        Ok(subnets.into_iter().map(|s| s.id).collect())
+    }
+
+    async fn last_committed_epoch(&self, child: &SubnetID) -> Result<ChainEpoch> {
+        VoteQuery::<NativeBottomUpCheckpoint>::last_executed_epoch(self, child).await
+    }
+
+    async fn committed_checkpoints(
+        &self,
+        child: &SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        self.lotus_client
+            .ipc_list_checkpoints(child.clone(), from_epoch, to_epoch)
+            .await
+    }
+
+    async fn propagate(
+        &self,
+        from: &Address,
+        postbox_msg_cid: Cid,
+        max_fee: TokenAmount,
+    ) -> Result<String> {
+        let params = cbor::serialize(
+            &PropagateParams {
+                postbox_cid: postbox_msg_cid,
+            },
+            "propagate params",
+        )?;
+
+        let mut message = MpoolPushMessage::new(
+            self.gateway_addr,
+            *from,
+            ipc_gateway::Method::Propagate as MethodNum,
+            params.to_vec(),
+        );
+        message.max_fee = Some(max_fee);
+
+        let message_cid = self.lotus_client.mpool_push(message).await?;
+        let response = self.lotus_client.state_wait_msg(message_cid).await?;
+        check_receipt(&message_cid, &response)
+            .map_err(|e| e.context(format!("propagation of {postbox_msg_cid:}")))?;
+        Ok(message_cid.to_string())
     }
 }
 
@@ -748,3 +823,13 @@ pub async fn chain_head_cid(client: &(impl LotusClient + Sync)) -> anyhow::Resul
     let cid_map = child_head.cids.first().unwrap();
     Cid::try_from(cid_map)
 }
+
+/// Fails with [`AgentError::Reverted`] if the message was not executed successfully.
+fn check_receipt(message_cid: &Cid, response: &StateWaitMsgResponse) -> Result<()> {
+    response.receipt.check_exit_code().map_err(|e| {
+        AgentError::Reverted {
+            reason: format!("message {message_cid:} failed with {e:}"),
+        }
+        .into()
+    })
+}
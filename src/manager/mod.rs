@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 pub use evm::{gateway, EthManager, EthSubnetManager};
 pub use fvm::LotusSubnetManager;
+pub use nonce::NonceManager;
 pub use subnet::SubnetManager;
 
 pub use crate::lotus::message::ipc::SubnetInfo;
@@ -9,4 +10,5 @@ pub use crate::lotus::message::ipc::SubnetInfo;
 pub mod evm;
 pub mod fevm;
 pub mod fvm;
+mod nonce;
 mod subnet;
@@ -0,0 +1,315 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Assigns the nonces of the messages sent from the accounts of the agent.
+//!
+//! The checkpoint managers and the json rpc handlers can send messages from the same account at
+//! the same time. Asking the node for the next nonce of every message would hand out the same nonce
+//! to both of them, so the nonces are assigned locally instead and only checked against the node:
+//! an account used outside the agent moves the local nonce forward, and a nonce the node is still
+//! missing below the local one after a grace period is handed out again so that the messages stuck
+//! behind it can be executed.
+
+use anyhow::Result;
+use fvm_shared::address::Address;
+use ipc_sdk::subnet_id::SubnetID;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long the node can miss the nonce of a pushed message before the message is considered
+/// dropped. The node might not have seen a message that was just pushed yet.
+const GAP_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// The nonces of every account, keyed by subnet and account. A single instance is shared by all the
+/// subnet managers of the agent.
+#[derive(Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<(SubnetID, Address), Arc<AccountNonces>>>,
+}
+
+#[derive(Default)]
+struct AccountNonces {
+    /// Serializes the reservations, which query the node for the next nonce of the account.
+    reserving: tokio::sync::Mutex<()>,
+    state: Mutex<NonceState>,
+}
+
+#[derive(Default)]
+struct NonceState {
+    /// The lowest nonce never handed out, `None` until the first reservation.
+    next: Option<u64>,
+    /// The nonces handed out whose messages are being pushed.
+    pushing: BTreeSet<u64>,
+    /// The nonces of the messages pushed, with the time they were pushed at, until the next nonce
+    /// of the node moves past them.
+    pushed: BTreeMap<u64, Instant>,
+    /// The nonces below `next` to hand out again before `next`.
+    free: BTreeSet<u64>,
+}
+
+impl NonceState {
+    /// Hands out a nonce given `pending`, the next nonce of the account according to the node.
+    fn reserve(&mut self, pending: u64, now: Instant) -> u64 {
+        let mut next = match self.next {
+            None => pending,
+            Some(next) if pending > next => {
+                log::warn!("next nonce {pending:} is ahead of the local nonce {next:}, the account is used outside the agent");
+                pending
+            }
+            Some(next) => next,
+        };
+        // the nonces given back but since taken by other messages.
+        self.free.retain(|n| *n >= pending && *n < next);
+        // the messages the node has seen.
+        self.pushed.retain(|n, _| *n >= pending);
+
+        // the node is missing a nonce below the local one that is not being pushed. Once it has
+        // been missing for the grace period, its message was dropped and the following ones are
+        // stuck.
+        if pending < next && !self.pushing.contains(&pending) {
+            let dropped = self.pushed.get(&pending).map_or(true, |pushed_at| {
+                now.duration_since(*pushed_at) >= GAP_GRACE_PERIOD
+            });
+            if dropped && self.free.insert(pending) {
+                self.pushed.remove(&pending);
+                log::warn!(
+                    "gap at nonce {pending:} below the local nonce {next:}, resubmitting it"
+                );
+            }
+        }
+
+        let nonce = match self.free.iter().next().copied() {
+            Some(nonce) => {
+                self.free.remove(&nonce);
+                nonce
+            }
+            None => {
+                next += 1;
+                next - 1
+            }
+        };
+        self.next = Some(next);
+        self.pushing.insert(nonce);
+        nonce
+    }
+
+    fn confirm(&mut self, nonce: u64, now: Instant) {
+        self.pushing.remove(&nonce);
+        self.pushed.insert(nonce, now);
+    }
+
+    /// Gives back the nonce of a message that could not be pushed.
+    fn release(&mut self, nonce: u64) {
+        self.pushing.remove(&nonce);
+        if self.next.map(|next| nonce < next).unwrap_or_default() {
+            self.free.insert(nonce);
+        }
+
+        // the nonces given back at the top are not handed out ahead of the node.
+        while let Some(next) = self.next {
+            if next == 0 || !self.free.remove(&(next - 1)) {
+                break;
+            }
+            self.next = Some(next - 1);
+        }
+    }
+}
+
+/// A nonce handed out to a message. Unless the message was pushed, the nonce is released when the
+/// reservation is dropped, including when the push is aborted.
+struct Reservation {
+    account: Arc<AccountNonces>,
+    nonce: u64,
+    pushed: bool,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut state = self.account.state.lock().unwrap();
+        if self.pushed {
+            state.confirm(self.nonce, Instant::now());
+        } else {
+            state.release(self.nonce);
+        }
+    }
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes a message from `account` in `subnet` with a nonce assigned locally. `pending` queries
+    /// the node for the next nonce of the account and `push` sends the message with the nonce.
+    pub async fn submit<T, P, F, Fut>(
+        &self,
+        subnet: &SubnetID,
+        account: &Address,
+        pending: P,
+        push: F,
+    ) -> Result<T>
+    where
+        P: Future<Output = Result<u64>>,
+        F: FnOnce(u64) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut reservation = self.reserve(subnet, account, pending).await?;
+        log::debug!(
+            "assigned nonce {:} to account {account:} in subnet {subnet:}",
+            reservation.nonce
+        );
+
+        let r = push(reservation.nonce).await;
+        reservation.pushed = r.is_ok();
+        r
+    }
+
+    async fn reserve<P>(
+        &self,
+        subnet: &SubnetID,
+        account: &Address,
+        pending: P,
+    ) -> Result<Reservation>
+    where
+        P: Future<Output = Result<u64>>,
+    {
+        let account_nonces = self
+            .accounts
+            .lock()
+            .unwrap()
+            .entry((subnet.clone(), *account))
+            .or_default()
+            .clone();
+
+        let _reserving = account_nonces.reserving.lock().await;
+        let pending = pending.await?;
+        let nonce = account_nonces
+            .state
+            .lock()
+            .unwrap()
+            .reserve(pending, Instant::now());
+
+        Ok(Reservation {
+            account: account_nonces.clone(),
+            nonce,
+            pushed: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manager::nonce::{NonceManager, NonceState, GAP_GRACE_PERIOD};
+    use anyhow::anyhow;
+    use fvm_shared::address::Address;
+    use ipc_sdk::subnet_id::SubnetID;
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    #[test]
+    fn test_nonces_assigned_locally() {
+        let mut state = NonceState::default();
+        let now = Instant::now();
+        assert_eq!(state.reserve(5, now), 5);
+        // the node does not know about the message being pushed yet
+        assert_eq!(state.reserve(5, now), 6);
+        state.confirm(5, now);
+        state.confirm(6, now);
+        assert_eq!(state.reserve(7, now), 7);
+        state.confirm(7, now);
+
+        // the account was used outside the agent
+        assert_eq!(state.reserve(10, now), 10);
+        state.confirm(10, now);
+    }
+
+    #[test]
+    fn test_nonce_resync_after_failure() {
+        let mut state = NonceState::default();
+        let now = Instant::now();
+        assert_eq!(state.reserve(0, now), 0);
+        assert_eq!(state.reserve(0, now), 1);
+        state.confirm(0, now);
+        state.release(1);
+        assert_eq!(state.reserve(1, now), 1);
+        state.confirm(1, now);
+
+        // the nonce was taken by another message
+        assert_eq!(state.reserve(2, now), 2);
+        state.release(2);
+        assert_eq!(state.reserve(3, now), 3);
+        state.confirm(3, now);
+
+        // a failed push below a pushed message leaves a gap to fill
+        assert_eq!(state.reserve(4, now), 4);
+        assert_eq!(state.reserve(4, now), 5);
+        state.release(4);
+        state.confirm(5, now);
+        assert_eq!(state.reserve(4, now), 4);
+    }
+
+    #[test]
+    fn test_nonce_gap() {
+        let mut state = NonceState::default();
+        let now = Instant::now();
+        for n in 0..3 {
+            assert_eq!(state.reserve(n, now), n);
+            state.confirm(n, now);
+        }
+
+        // the node has not seen the message with nonce 1 yet
+        assert_eq!(state.reserve(1, now), 3);
+        state.confirm(3, now);
+
+        // it is still missing after the grace period: the message was dropped by the node
+        let later = now + GAP_GRACE_PERIOD;
+        assert_eq!(state.reserve(1, later), 1);
+        state.confirm(1, later);
+        assert_eq!(state.reserve(4, later), 4);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_submissions() {
+        let nonces = NonceManager::new();
+        let subnet = SubnetID::new_root(123);
+        let account = Address::new_id(100);
+
+        // the nonces of the messages in the node, which reports the lowest one missing
+        let node = &Mutex::new(BTreeSet::new());
+        let pending = || async move {
+            let node = node.lock().unwrap();
+            Ok::<_, anyhow::Error>((0..).find(|n| !node.contains(n)).unwrap())
+        };
+        let submit = |n: u64| {
+            nonces.submit(&subnet, &account, pending(), move |nonce| async move {
+                tokio::task::yield_now().await;
+                if n == 1 {
+                    return Err(anyhow!("push failed"));
+                }
+                node.lock().unwrap().insert(nonce);
+                Ok(nonce)
+            })
+        };
+        let (a, b, c) = tokio::join!(submit(0), submit(1), submit(2));
+        assert!(b.is_err());
+
+        let mut assigned = vec![a.unwrap(), c.unwrap()];
+        assigned.sort();
+        assert_eq!(assigned.len(), 2);
+        assert_ne!(assigned[0], assigned[1]);
+
+        // the nonce of the failed push is handed out again
+        let next = nonces
+            .submit(
+                &subnet,
+                &account,
+                pending(),
+                |nonce| async move { Ok(nonce) },
+            )
+            .await
+            .unwrap();
+        assert!(next < 3 && !assigned.contains(&next));
+    }
+}
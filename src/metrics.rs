@@ -0,0 +1,174 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Prometheus metrics of the agent daemon, exposed by the json rpc server under `/metrics`.
+
+use crate::checkpoint::CheckpointManager;
+use anyhow::Result;
+use fvm_shared::clock::ChainEpoch;
+use ipc_sdk::subnet_id::SubnetID;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+
+/// The labels identifying a checkpoint manager.
+const MANAGER_LABELS: &[&str] = &["direction", "parent", "child"];
+
+/// The backend label of the rpc calls to the lotus endpoints of the subnets.
+pub const LOTUS_BACKEND: &str = "lotus";
+/// The backend label of the rpc calls to the evm endpoints of the subnets.
+pub const EVM_BACKEND: &str = "evm";
+
+lazy_static! {
+    pub static ref JSON_RPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "ipc_agent_json_rpc_requests_total",
+        "Number of json rpc requests received per method",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref JSON_RPC_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "ipc_agent_json_rpc_errors_total",
+        "Number of json rpc requests that returned an error per method",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref JSON_RPC_LATENCY: HistogramVec = register_histogram_vec!(
+        "ipc_agent_json_rpc_request_duration_seconds",
+        "Time taken to handle a json rpc request per method",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref CHECKPOINT_SUBMISSIONS: IntCounterVec = register_int_counter_vec!(
+        "ipc_agent_checkpoint_submissions_total",
+        "Number of checkpoints submitted and executed per manager",
+        MANAGER_LABELS
+    )
+    .unwrap();
+    pub static ref CHECKPOINT_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "ipc_agent_checkpoint_failures_total",
+        "Number of failed checkpoint runs per manager",
+        MANAGER_LABELS
+    )
+    .unwrap();
+    pub static ref CHECKPOINT_LAST_EXECUTED_EPOCH: IntGaugeVec = register_int_gauge_vec!(
+        "ipc_agent_checkpoint_last_executed_epoch",
+        "The epoch of the last executed checkpoint per manager",
+        MANAGER_LABELS
+    )
+    .unwrap();
+    pub static ref CHECKPOINT_EPOCH_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "ipc_agent_checkpoint_epoch_lag",
+        "The number of epochs between the current epoch and the last executed checkpoint per manager",
+        MANAGER_LABELS
+    )
+    .unwrap();
+    pub static ref TOP_DOWN_BACKLOG: IntGaugeVec = register_int_gauge_vec!(
+        "ipc_agent_top_down_backlog",
+        "The number of top-down messages left out of the last checkpoint per manager",
+        MANAGER_LABELS
+    )
+    .unwrap();
+    pub static ref RELAYED_CROSS_MSGS: IntCounterVec = register_int_counter_vec!(
+        "ipc_agent_relayed_cross_msgs_total",
+        "Number of cross-net messages propagated out of the postbox per subnet",
+        &["subnet"]
+    )
+    .unwrap();
+    pub static ref RPC_CALL_LATENCY: HistogramVec = register_histogram_vec!(
+        "ipc_agent_rpc_call_duration_seconds",
+        "Time taken by the json rpc calls to the lotus and evm endpoints of the subnets",
+        &["backend", "method"]
+    )
+    .unwrap();
+}
+
+/// Encodes all the registered metrics in the prometheus text format.
+pub fn encode() -> Result<String> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Records the last executed and current epochs read by the checkpoint manager.
+pub(crate) fn observe_checkpoint_epochs(
+    manager: &dyn CheckpointManager,
+    last_executed_epoch: ChainEpoch,
+    current_epoch: ChainEpoch,
+) {
+    let [direction, parent, child] = manager_labels(manager);
+    let labels = [direction.as_str(), parent.as_str(), child.as_str()];
+    CHECKPOINT_LAST_EXECUTED_EPOCH
+        .with_label_values(&labels)
+        .set(last_executed_epoch);
+    CHECKPOINT_EPOCH_LAG
+        .with_label_values(&labels)
+        .set(current_epoch - last_executed_epoch);
+}
+
+/// Records a checkpoint submitted and executed by the checkpoint manager.
+pub(crate) fn inc_checkpoint_submissions(manager: &dyn CheckpointManager) {
+    let [direction, parent, child] = manager_labels(manager);
+    CHECKPOINT_SUBMISSIONS
+        .with_label_values(&[&direction, &parent, &child])
+        .inc();
+}
+
+/// Records a failed run of the checkpoint manager.
+pub(crate) fn inc_checkpoint_failures(manager: &dyn CheckpointManager) {
+    let [direction, parent, child] = manager_labels(manager);
+    CHECKPOINT_FAILURES
+        .with_label_values(&[&direction, &parent, &child])
+        .inc();
+}
+
+/// Records the top-down messages left out of the last checkpoint of the manager.
+pub(crate) fn observe_top_down_backlog(manager: &dyn CheckpointManager, backlog: usize) {
+    let [direction, parent, child] = manager_labels(manager);
+    TOP_DOWN_BACKLOG
+        .with_label_values(&[&direction, &parent, &child])
+        .set(backlog as i64);
+}
+
+/// Records a cross-net message propagated out of the postbox of the subnet by the relayer.
+pub(crate) fn inc_relayed_cross_msgs(subnet: &SubnetID) {
+    RELAYED_CROSS_MSGS
+        .with_label_values(&[&subnet.to_string()])
+        .inc();
+}
+
+/// Drops the gauges of the managers that are no longer active, i.e. after the config is reloaded.
+pub(crate) fn reset_checkpoint_epochs() {
+    CHECKPOINT_LAST_EXECUTED_EPOCH.reset();
+    CHECKPOINT_EPOCH_LAG.reset();
+    TOP_DOWN_BACKLOG.reset();
+}
+
+fn manager_labels(manager: &dyn CheckpointManager) -> [String; 3] {
+    [
+        manager.direction().to_string(),
+        manager.parent_subnet().id.to_string(),
+        manager.child_subnet().id.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{encode, JSON_RPC_REQUESTS, RPC_CALL_LATENCY};
+
+    #[test]
+    fn test_encode() {
+        JSON_RPC_REQUESTS
+            .with_label_values(&["ipc_checkpointStatus"])
+            .inc();
+        RPC_CALL_LATENCY
+            .with_label_values(&["lotus", "test_encode"])
+            .observe(0.5);
+
+        let text = encode().unwrap();
+        assert!(text.contains("ipc_agent_json_rpc_requests_total{method=\"ipc_checkpointStatus\"}"));
+        assert!(text.contains(
+            "ipc_agent_rpc_call_duration_seconds_count{backend=\"lotus\",method=\"test_encode\"} 1"
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Persistent journal of the cross-net messages relayed by the agent.
+//!
+//! For every subnet, the journal keeps the last epoch scanned for the checkpoints of each child
+//! and the messages propagated out of the postbox along with the fee charged for them. After a
+//! restart, the relayer resumes the scans where it left off, never propagates a message twice and
+//! keeps on accounting the fees of the current budget period.
+
+use crate::config::ReloadableConfig;
+use anyhow::{anyhow, Result};
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_shared::bigint::BigInt;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_sdk::subnet_id::SubnetID;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the journal file in the agent repo directory.
+pub const RELAYER_JOURNAL_FILE_NAME: &str = "relayer_journal.json";
+
+/// A cross-net message propagated out of the postbox of a subnet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayedMsg {
+    pub subnet: String,
+    /// The cid of the message in the postbox.
+    pub postbox_cid: String,
+    /// The epoch of the checkpoint that committed the message in the subnet.
+    pub epoch: ChainEpoch,
+    pub from: String,
+    /// The message cid of the propagation.
+    pub submission: String,
+    /// The fee in attoFIL charged against the budget of the subnet.
+    pub fee: String,
+    /// Unix timestamp in seconds of the propagation.
+    pub relayed_at: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct JournalContent {
+    /// The last epoch scanned, keyed by subnet and child.
+    cursors: Vec<(String, String, ChainEpoch)>,
+    relayed: Vec<RelayedMsg>,
+}
+
+#[derive(Default)]
+struct JournalState {
+    cursors: HashMap<(String, String), ChainEpoch>,
+    relayed: HashMap<(String, String), RelayedMsg>,
+}
+
+/// The on-disk journal of the relayed messages. Like the checkpoint journal, every update is
+/// persisted by replacing the whole file.
+pub struct RelayerJournal {
+    path: PathBuf,
+    state: Mutex<JournalState>,
+}
+
+impl RelayerJournal {
+    /// Loads the journal stored in `path`, or creates an empty one if the file does not exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let content = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("cannot parse relayer journal {path:?}: {e:}"))?
+        } else {
+            JournalContent::default()
+        };
+
+        let state = JournalState {
+            cursors: content
+                .cursors
+                .into_iter()
+                .map(|(subnet, child, epoch)| ((subnet, child), epoch))
+                .collect(),
+            relayed: content
+                .relayed
+                .into_iter()
+                .map(|r| ((r.subnet.clone(), r.postbox_cid.clone()), r))
+                .collect(),
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Loads the journal from the repo directory of the agent config.
+    pub fn from_config(config: &ReloadableConfig) -> Result<Self> {
+        let repo = config
+            .get_config_repo()
+            .ok_or_else(|| anyhow!("No repo found in config to store the relayer journal"))?;
+        Self::load(Path::new(&repo).join(RELAYER_JOURNAL_FILE_NAME))
+    }
+
+    /// The last epoch scanned for the checkpoints of the child committed in the subnet.
+    pub fn cursor(&self, subnet: &SubnetID, child: &SubnetID) -> Option<ChainEpoch> {
+        let state = self.state.lock().unwrap();
+        state
+            .cursors
+            .get(&(subnet.to_string(), child.to_string()))
+            .copied()
+    }
+
+    pub fn set_cursor(&self, subnet: &SubnetID, child: &SubnetID, epoch: ChainEpoch) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state
+            .cursors
+            .insert((subnet.to_string(), child.to_string()), epoch);
+        if previous == Some(epoch) {
+            return Ok(());
+        }
+        self.persist(&state)
+    }
+
+    /// Returns the propagation of the message in the postbox of the subnet, if any.
+    pub fn get(&self, subnet: &SubnetID, postbox_cid: &Cid) -> Option<RelayedMsg> {
+        let state = self.state.lock().unwrap();
+        state
+            .relayed
+            .get(&(subnet.to_string(), postbox_cid.to_string()))
+            .cloned()
+    }
+
+    /// Records the propagation of a message out of the postbox of the subnet.
+    pub fn record_relayed(
+        &self,
+        subnet: &SubnetID,
+        postbox_cid: &Cid,
+        epoch: ChainEpoch,
+        from: &Address,
+        submission: String,
+        fee: &TokenAmount,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.relayed.insert(
+            (subnet.to_string(), postbox_cid.to_string()),
+            RelayedMsg {
+                subnet: subnet.to_string(),
+                postbox_cid: postbox_cid.to_string(),
+                epoch,
+                from: from.to_string(),
+                submission,
+                fee: fee.atto().to_string(),
+                relayed_at: now(),
+            },
+        );
+        self.persist(&state)
+    }
+
+    /// The fees charged for the messages relayed in the subnet in the last `period_secs` seconds.
+    pub fn spent_since(&self, subnet: &SubnetID, period_secs: u64) -> TokenAmount {
+        let since = now().saturating_sub(period_secs);
+        let subnet = subnet.to_string();
+
+        let state = self.state.lock().unwrap();
+        state
+            .relayed
+            .values()
+            .filter(|r| r.subnet == subnet && r.relayed_at >= since)
+            .map(|r| TokenAmount::from_atto(BigInt::from_str(&r.fee).unwrap_or_default()))
+            .fold(TokenAmount::from_atto(0), |spent, fee| spent + fee)
+    }
+
+    /// Removes the messages of the subnet relayed more than `period_secs` seconds ago. They no
+    /// longer count against the budget and their checkpoints are not scanned again once every
+    /// cursor of the subnet moved past them.
+    pub fn prune(&self, subnet: &SubnetID, period_secs: u64) -> Result<()> {
+        let before = now().saturating_sub(period_secs);
+        let subnet = subnet.to_string();
+
+        let mut state = self.state.lock().unwrap();
+        let scanned = state
+            .cursors
+            .iter()
+            .filter(|((s, _), _)| *s == subnet)
+            .map(|(_, epoch)| *epoch)
+            .min()
+            .unwrap_or_default();
+        let len = state.relayed.len();
+        state
+            .relayed
+            .retain(|_, r| r.subnet != subnet || r.relayed_at >= before || r.epoch > scanned);
+        if state.relayed.len() == len {
+            return Ok(());
+        }
+        self.persist(&state)
+    }
+
+    fn persist(&self, state: &JournalState) -> Result<()> {
+        let mut cursors = state
+            .cursors
+            .iter()
+            .map(|((subnet, child), epoch)| (subnet.clone(), child.clone(), *epoch))
+            .collect::<Vec<_>>();
+        cursors.sort();
+        let mut relayed = state.relayed.values().cloned().collect::<Vec<_>>();
+        relayed.sort_by(|a, b| (&a.subnet, a.epoch).cmp(&(&b.subnet, b.epoch)));
+        let content = serde_json::to_string_pretty(&JournalContent { cursors, relayed })?;
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::relayer::journal::{RelayerJournal, RELAYER_JOURNAL_FILE_NAME};
+    use cid::multihash::{Code, MultihashDigest};
+    use cid::Cid;
+    use fvm_ipld_encoding::DAG_CBOR;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use ipc_sdk::subnet_id::SubnetID;
+
+    #[test]
+    fn test_journal_persists_relayed_msgs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(RELAYER_JOURNAL_FILE_NAME);
+        let subnet = SubnetID::new_root(123);
+        let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+        let cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b"postbox msg"));
+        let from = Address::new_id(100);
+
+        let journal = RelayerJournal::load(&path).unwrap();
+        assert!(journal.get(&subnet, &cid).is_none());
+        assert!(journal.cursor(&subnet, &child).is_none());
+
+        journal
+            .record_relayed(
+                &subnet,
+                &cid,
+                10,
+                &from,
+                String::from("cid"),
+                &TokenAmount::from_atto(5),
+            )
+            .unwrap();
+        journal.set_cursor(&subnet, &child, 20).unwrap();
+
+        let journal = RelayerJournal::load(&path).unwrap();
+        assert_eq!(journal.get(&subnet, &cid).unwrap().submission, "cid");
+        assert_eq!(journal.cursor(&subnet, &child), Some(20));
+        assert_eq!(journal.spent_since(&subnet, 60), TokenAmount::from_atto(5));
+        assert_eq!(journal.spent_since(&child, 60), TokenAmount::from_atto(0));
+
+        // the message is still within the budget period
+        journal.prune(&subnet, 60).unwrap();
+        assert!(journal.get(&subnet, &cid).is_some());
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Relays the cross-net messages waiting in the postbox of the subnets.
+//!
+//! A bottom-up message committed in a subnet, whose destination is in another branch below it, is
+//! stored in the postbox of its gateway until someone propagates it. The relayer scans the
+//! bottom-up checkpoints of the children committed in every subnet with a `[subnets.relayer]`
+//! section, and propagates the messages sent by the managed accounts within the fee budget of the
+//! subnet.
+
+use crate::config::subnet::{NetworkType, RelayerConfig};
+use crate::config::{ReloadableConfig, Subnet};
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::manager::{LotusSubnetManager, NonceManager};
+use crate::metrics;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fil_actors_runtime::cbor;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg};
+use ipc_identity::Wallet;
+use ipc_sdk::subnet_id::SubnetID;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::select;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+
+pub use journal::{RelayedMsg, RelayerJournal, RELAYER_JOURNAL_FILE_NAME};
+
+mod journal;
+
+/// The maximum number of epochs of checkpoints scanned for each child in a single run.
+const MAX_SCAN_EPOCHS: ChainEpoch = 1000;
+/// The number of failed propagations of a message before the relayer gives up on it, e.g. because
+/// someone else already propagated it.
+const MAX_PROPAGATE_ATTEMPTS: u32 = 3;
+/// The timeout of a single run of a relayer, so that a hanging endpoint does not block it forever.
+const RELAY_RUN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Queries the cross-net messages committed in a subnet and propagates them out of its postbox.
+#[async_trait]
+pub trait PostboxHandler: Send + Sync {
+    /// The child subnets registered in the gateway of the subnet.
+    async fn child_subnets(&self) -> Result<Vec<SubnetID>>;
+    /// The epoch of the last bottom-up checkpoint of the child committed in the subnet.
+    async fn last_committed_epoch(&self, child: &SubnetID) -> Result<ChainEpoch>;
+    /// The bottom-up checkpoints of the child committed in the subnet in the range of epochs.
+    async fn committed_checkpoints(
+        &self,
+        child: &SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>>;
+    /// Propagates the message in the postbox from `from`, paying at most `max_fee` in gas. Returns
+    /// the message cid of the propagation once it is executed, and fails if it reverted.
+    async fn propagate(
+        &self,
+        from: &Address,
+        postbox_msg_cid: Cid,
+        max_fee: TokenAmount,
+    ) -> Result<String>;
+}
+
+/// The cid of a cross-net message in the postbox of the gateway.
+pub(crate) fn postbox_msg_cid(msg: &CrossMsg) -> Result<Cid> {
+    let bytes = cbor::serialize(msg, "postbox cross msg")?;
+    Ok(Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&bytes)))
+}
+
+/// Checks if the message committed in the subnet by a bottom-up checkpoint is stored in its
+/// postbox, i.e. if it is sent to a subnet below it.
+pub(crate) fn is_postbox_msg(subnet: &SubnetID, msg: &CrossMsg) -> Result<bool> {
+    let destination = msg
+        .msg
+        .to
+        .subnet()
+        .map_err(|e| anyhow!("invalid destination of cross msg: {e:?}"))?;
+    let children = subnet.children_as_ref();
+    let destination_children = destination.children_as_ref();
+    Ok(destination.root_id() == subnet.root_id()
+        && destination_children.len() > children.len()
+        && destination_children.starts_with(children))
+}
+
+enum RelayOutcome {
+    Propagated,
+    /// The message was already relayed, or the relayer gave up on it.
+    Skipped,
+    /// The fee budget does not allow to propagate the message yet.
+    OverBudget,
+    /// The propagation failed, it is attempted again in the next run.
+    Failed,
+}
+
+/// Relays the messages in the postbox of a single subnet.
+pub(crate) struct Relayer<H> {
+    subnet: Subnet,
+    config: RelayerConfig,
+    handler: H,
+    journal: Arc<RelayerJournal>,
+    /// The failed propagations of the messages, by postbox cid.
+    attempts: HashMap<Cid, u32>,
+}
+
+impl<H: PostboxHandler> Relayer<H> {
+    pub(crate) fn new(subnet: Subnet, handler: H, journal: Arc<RelayerJournal>) -> Result<Self> {
+        let config = subnet
+            .relayer
+            .clone()
+            .ok_or_else(|| anyhow!("no relayer configured in subnet {:}", subnet.id))?;
+        Ok(Self {
+            subnet,
+            config,
+            handler,
+            journal,
+            attempts: HashMap::new(),
+        })
+    }
+
+    /// Propagates the pending messages of the managed accounts committed since the last run.
+    /// Returns the number of messages propagated.
+    pub(crate) async fn relay_pending(&mut self) -> Result<usize> {
+        let subnet_id = self.subnet.id.clone();
+        self.journal
+            .prune(&subnet_id, self.config.budget_period_secs)?;
+
+        let accounts = self.config.relaying_accounts(self.subnet.accounts());
+        if accounts.is_empty() {
+            log::debug!("no managed accounts to relay messages for in subnet: {subnet_id:}");
+            return Ok(0);
+        }
+
+        let mut relayed = 0;
+        for child in self.handler.child_subnets().await? {
+            // a failure only stops the scan of its child, the other children are still relayed.
+            match self.relay_child(&child, &accounts).await {
+                Ok(n) => relayed += n,
+                Err(e) => log::warn!(
+                    "cannot relay the messages of child: {child:} in subnet: {subnet_id:} due to {e:}"
+                ),
+            }
+        }
+        Ok(relayed)
+    }
+
+    /// Propagates the pending messages of the accounts in the checkpoints of the child committed
+    /// since the last run, moving its cursor past the checkpoints scanned. The scan stops at a
+    /// message that cannot be propagated yet, whose checkpoint is scanned again in the next run.
+    async fn relay_child(&mut self, child: &SubnetID, accounts: &[Address]) -> Result<usize> {
+        let subnet_id = self.subnet.id.clone();
+        let from_epoch = self
+            .journal
+            .cursor(&subnet_id, child)
+            .map_or(0, |epoch| epoch + 1);
+        let to_epoch = std::cmp::min(
+            self.handler.last_committed_epoch(child).await?,
+            from_epoch + MAX_SCAN_EPOCHS - 1,
+        );
+        if to_epoch < from_epoch {
+            return Ok(0);
+        }
+
+        let mut relayed = 0;
+        let checkpoints = self
+            .handler
+            .committed_checkpoints(child, from_epoch, to_epoch)
+            .await?;
+        for checkpoint in checkpoints {
+            let epoch = checkpoint.data.epoch;
+            let msgs = checkpoint.data.cross_msgs.cross_msgs.unwrap_or_default();
+            for msg in msgs {
+                if !is_postbox_msg(&subnet_id, &msg)? {
+                    continue;
+                }
+                let owner = msg
+                    .msg
+                    .from
+                    .raw_addr()
+                    .map_err(|e| anyhow!("invalid source of cross msg: {e:?}"))?;
+                if !accounts.contains(&owner) {
+                    continue;
+                }
+                match self.relay(&msg, &owner, epoch).await? {
+                    RelayOutcome::Propagated => relayed += 1,
+                    RelayOutcome::Skipped => {}
+                    RelayOutcome::OverBudget | RelayOutcome::Failed => return Ok(relayed),
+                }
+            }
+            self.journal.set_cursor(&subnet_id, child, epoch)?;
+        }
+        self.journal.set_cursor(&subnet_id, child, to_epoch)?;
+        Ok(relayed)
+    }
+
+    /// Propagates a single message, unless it was already relayed or the fee budget does not allow
+    /// it.
+    async fn relay(
+        &mut self,
+        msg: &CrossMsg,
+        owner: &Address,
+        epoch: ChainEpoch,
+    ) -> Result<RelayOutcome> {
+        let subnet_id = &self.subnet.id;
+        let cid = postbox_msg_cid(msg)?;
+        if self.journal.get(subnet_id, &cid).is_some()
+            || self.attempts.get(&cid) >= Some(&MAX_PROPAGATE_ATTEMPTS)
+        {
+            return Ok(RelayOutcome::Skipped);
+        }
+
+        let max_fee = self.config.max_fee_per_msg.clone();
+        let spent = self
+            .journal
+            .spent_since(subnet_id, self.config.budget_period_secs);
+        if spent + max_fee.clone() > self.config.fee_budget {
+            log::warn!(
+                "fee budget of the relayer in subnet: {subnet_id:} is exhausted, postbox message: {cid:} is left pending"
+            );
+            return Ok(RelayOutcome::OverBudget);
+        }
+
+        match self.handler.propagate(owner, cid, max_fee.clone()).await {
+            Ok(submission) => {
+                log::info!("propagated postbox message: {cid:} in subnet: {subnet_id:} with message: {submission:}");
+                self.journal
+                    .record_relayed(subnet_id, &cid, epoch, owner, submission, &max_fee)?;
+                self.attempts.remove(&cid);
+                metrics::inc_relayed_cross_msgs(subnet_id);
+                Ok(RelayOutcome::Propagated)
+            }
+            Err(e) => {
+                let attempts = self.attempts.entry(cid).or_default();
+                *attempts += 1;
+                if *attempts < MAX_PROPAGATE_ATTEMPTS {
+                    log::warn!("cannot propagate postbox message: {cid:} in subnet: {subnet_id:} due to {e:}");
+                    return Ok(RelayOutcome::Failed);
+                }
+                log::error!("giving up on postbox message: {cid:} in subnet: {subnet_id:} after {attempts:} attempts: {e:}");
+                Ok(RelayOutcome::Skipped)
+            }
+        }
+    }
+
+    /// Relays the pending messages periodically.
+    async fn run(mut self) {
+        let interval = Duration::from_secs(self.config.polling_interval_secs);
+        loop {
+            match timeout(RELAY_RUN_TIMEOUT, self.relay_pending()).await {
+                Ok(Ok(relayed)) if relayed > 0 => {
+                    log::info!("relayed {relayed:} messages in subnet: {:}", self.subnet.id)
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => log::warn!("relayer of subnet: {:} had error: {e:}", self.subnet.id),
+                Err(_) => log::warn!("relayer of subnet: {:} timed out", self.subnet.id),
+            }
+            sleep(interval).await;
+        }
+    }
+}
+
+pub struct CrossMsgRelayerSubsystem {
+    /// The subsystem uses a `ReloadableConfig` to relay the messages of the subnets in the latest
+    /// version of the config.
+    config: Arc<ReloadableConfig>,
+    fvm_wallet: Arc<RwLock<Wallet>>,
+    /// Keeps track of the relayed messages and the fees spent across restarts.
+    journal: Arc<RelayerJournal>,
+    /// Assigns the nonces of the propagations, shared with the other subsystems.
+    nonces: Arc<NonceManager>,
+}
+
+impl CrossMsgRelayerSubsystem {
+    /// Creates a new `CrossMsgRelayerSubsystem` with a configuration `config`.
+    pub fn new(
+        config: Arc<ReloadableConfig>,
+        fvm_wallet: Arc<RwLock<Wallet>>,
+        journal: Arc<RelayerJournal>,
+        nonces: Arc<NonceManager>,
+    ) -> Self {
+        Self {
+            config,
+            fvm_wallet,
+            journal,
+            nonces,
+        }
+    }
+
+    /// Spawns a relayer for every subnet with a relayer section. They are aborted when the returned
+    /// set is dropped.
+    fn spawn_relayers(&self, subnets: &HashMap<SubnetID, Subnet>) -> JoinSet<()> {
+        let mut relayers = JoinSet::new();
+        for subnet in subnets.values().filter(|s| s.relayer.is_some()) {
+            if subnet.network_type() != NetworkType::Fvm {
+                log::warn!(
+                    "relaying cross-net messages is not supported in fevm subnet: {:}",
+                    subnet.id
+                );
+                continue;
+            }
+
+            let client =
+                LotusJsonRPCClient::from_subnet_with_wallet_store(subnet, self.fvm_wallet.clone())
+                    .with_nonce_manager(self.nonces.clone());
+            let handler = LotusSubnetManager::new(client, subnet.gateway_addr());
+            match Relayer::new(subnet.clone(), handler, self.journal.clone()) {
+                Ok(relayer) => {
+                    log::info!("starting the relayer of subnet: {:}", subnet.id);
+                    relayers.spawn(relayer.run());
+                }
+                Err(e) => log::error!("cannot start the relayer of subnet: {:}: {e:}", subnet.id),
+            }
+        }
+        relayers
+    }
+}
+
+#[async_trait]
+impl IntoSubsystem<anyhow::Error> for CrossMsgRelayerSubsystem {
+    async fn run(self, subsys: SubsystemHandle) -> anyhow::Result<()> {
+        // Each event in this channel is notification of a new config.
+        let mut config_chan = self.config.new_subscriber();
+
+        loop {
+            let config = self.config.get_config();
+            // The relayers are aborted once dropped, i.e. when the config is reloaded.
+            let _relayers = self.spawn_relayers(&config.subnets);
+
+            select! {
+                r = config_chan.recv() => {
+                    log::info!("Config changed, reloading relayer subsystem");
+                    if r.is_err() {
+                        return Err(anyhow!("Config channel unexpectedly closed, shutting down relayer subsystem"));
+                    }
+                }
+                _ = subsys.on_shutdown_requested() => {
+                    log::info!("Shutting down relayer subsystem");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
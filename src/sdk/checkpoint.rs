@@ -2,11 +2,15 @@
 // SPDX-License-Identifier: MIT
 //! Checkpoint related sdk functions
 
+use crate::checkpoint::{AuditReport, ManagerStatus};
 use crate::config::json_rpc_methods;
 use crate::jsonrpc::JsonRpcClient;
 use crate::sdk::IpcAgentClient;
 use crate::server::list_checkpoints::ListBottomUpCheckpointsParams;
 use crate::server::topdown_executed::LastTopDownExecParams;
+use crate::server::{
+    AuditBottomUpCheckpointsParams, CheckpointStatusParams, PreviewBottomUpCheckpointParams,
+};
 use fvm_shared::clock::ChainEpoch;
 
 impl<T: JsonRpcClient> IpcAgentClient<T> {
@@ -15,12 +19,11 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
             subnet_id: subnet.to_string(),
         };
 
-        self.json_rpc_client
-            .request::<ChainEpoch>(
-                json_rpc_methods::LAST_TOPDOWN_EXECUTED,
-                serde_json::to_value(params)?,
-            )
-            .await
+        self.request::<ChainEpoch>(
+            json_rpc_methods::LAST_TOPDOWN_EXECUTED,
+            serde_json::to_value(params)?,
+        )
+        .await
     }
 
     pub async fn list_bottom_up_checkpoints(
@@ -35,11 +38,59 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
             to_epoch: end,
         };
 
-        self.json_rpc_client
-            .request::<Vec<serde_json::Value>>(
-                json_rpc_methods::LIST_BOTTOMUP_CHECKPOINTS,
-                serde_json::to_value(params)?,
-            )
-            .await
+        self.request::<Vec<serde_json::Value>>(
+            json_rpc_methods::LIST_BOTTOMUP_CHECKPOINTS,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
+    pub async fn checkpoint_status(
+        &self,
+        subnet: Option<String>,
+    ) -> anyhow::Result<Vec<ManagerStatus>> {
+        let params = CheckpointStatusParams { subnet_id: subnet };
+
+        self.request::<Vec<ManagerStatus>>(
+            json_rpc_methods::CHECKPOINT_STATUS,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
+    pub async fn preview_bottom_up_checkpoint(
+        &self,
+        subnet: &str,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<serde_json::Value> {
+        let params = PreviewBottomUpCheckpointParams {
+            subnet_id: subnet.to_string(),
+            epoch,
+        };
+
+        self.request::<serde_json::Value>(
+            json_rpc_methods::PREVIEW_BOTTOMUP_CHECKPOINT,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
+    pub async fn audit_bottom_up_checkpoints(
+        &self,
+        subnet: &str,
+        start: ChainEpoch,
+        end: ChainEpoch,
+    ) -> anyhow::Result<AuditReport> {
+        let params = AuditBottomUpCheckpointsParams {
+            subnet_id: subnet.to_string(),
+            from_epoch: start,
+            to_epoch: end,
+        };
+
+        self.request::<AuditReport>(
+            json_rpc_methods::AUDIT_BOTTOMUP_CHECKPOINTS,
+            serde_json::to_value(params)?,
+        )
+        .await
     }
 }
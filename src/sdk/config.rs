@@ -3,16 +3,22 @@
 use crate::config::json_rpc_methods;
 use crate::jsonrpc::JsonRpcClient;
 use crate::sdk::IpcAgentClient;
+use crate::server::openrpc::OpenRpcDocument;
 use crate::server::ReloadConfigParams;
+use serde_json::Value;
 
 impl<T: JsonRpcClient> IpcAgentClient<T> {
     pub async fn reload_config(&self, path: Option<String>) -> anyhow::Result<()> {
         let params = ReloadConfigParams { path };
-        self.json_rpc_client
-            .request::<()>(
-                json_rpc_methods::RELOAD_CONFIG,
-                serde_json::to_value(params)?,
-            )
+        self.request::<()>(
+            json_rpc_methods::RELOAD_CONFIG,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+    /// Fetches the OpenRPC document describing the json rpc methods of the agent.
+    pub async fn discover(&self) -> anyhow::Result<OpenRpcDocument> {
+        self.request::<OpenRpcDocument>(json_rpc_methods::RPC_DISCOVER, Value::Null)
             .await
     }
 }
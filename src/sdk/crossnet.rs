@@ -7,6 +7,7 @@ use crate::jsonrpc::JsonRpcClient;
 use crate::sdk::IpcAgentClient;
 use crate::server::fund::FundParams;
 use crate::server::release::ReleaseParams;
+use crate::tracker::TrackedTransaction;
 use fvm_shared::clock::ChainEpoch;
 
 impl<T: JsonRpcClient> IpcAgentClient<T> {
@@ -22,10 +23,30 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
             from,
             to,
             amount,
+            asynchronous: false,
         };
 
-        self.json_rpc_client
-            .request::<ChainEpoch>(json_rpc_methods::FUND, serde_json::to_value(params)?)
+        self.request::<ChainEpoch>(json_rpc_methods::FUND, serde_json::to_value(params)?)
+            .await
+    }
+
+    /// Funds the subnet without waiting for the message to be executed, returning its record.
+    pub async fn fund_async(
+        &self,
+        subnet: &str,
+        from: Option<String>,
+        to: Option<String>,
+        amount: f64,
+    ) -> anyhow::Result<TrackedTransaction> {
+        let params = FundParams {
+            subnet: subnet.to_string(),
+            from,
+            to,
+            amount,
+            asynchronous: true,
+        };
+
+        self.request::<TrackedTransaction>(json_rpc_methods::FUND, serde_json::to_value(params)?)
             .await
     }
 
@@ -41,9 +62,29 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
             from,
             to,
             amount,
+            asynchronous: false,
+        };
+        self.request::<ChainEpoch>(json_rpc_methods::RELEASE, serde_json::to_value(params)?)
+            .await
+    }
+
+    /// Releases funds from the subnet without waiting for the message to be executed, returning
+    /// its record.
+    pub async fn release_async(
+        &self,
+        subnet: &str,
+        from: Option<String>,
+        to: Option<String>,
+        amount: f64,
+    ) -> anyhow::Result<TrackedTransaction> {
+        let params = ReleaseParams {
+            subnet: subnet.to_string(),
+            from,
+            to,
+            amount,
+            asynchronous: true,
         };
-        self.json_rpc_client
-            .request::<ChainEpoch>(json_rpc_methods::RELEASE, serde_json::to_value(params)?)
+        self.request::<TrackedTransaction>(json_rpc_methods::RELEASE, serde_json::to_value(params)?)
             .await
     }
 }
@@ -7,11 +7,16 @@ mod checkpoint;
 mod config;
 mod crossnet;
 mod subnet;
+mod transaction;
 mod wallet;
 
-use crate::jsonrpc::JsonRpcClientImpl;
+use crate::config::{Server, UNIX_SOCKET_SCHEME};
+use crate::jsonrpc::{ClientTls, JsonRpcClient, JsonRpcClientImpl, JsonRpcError};
+use crate::server::error::AgentError;
 pub use wallet::LotusJsonKeyType;
 
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use url::Url;
 
 /// The ipc agent client
@@ -25,8 +30,50 @@ impl<T> IpcAgentClient<T> {
     }
 }
 
+impl<T: JsonRpcClient> IpcAgentClient<T> {
+    /// Sends the request to the agent. The errors returned by the agent are surfaced as
+    /// [`AgentError`], so that the callers can branch on them with `downcast_ref`.
+    async fn request<R: DeserializeOwned>(&self, method: &str, params: Value) -> anyhow::Result<R> {
+        self.json_rpc_client
+            .request::<R>(method, params)
+            .await
+            .map_err(|e| {
+                match e
+                    .downcast_ref::<JsonRpcError>()
+                    .and_then(AgentError::from_json_rpc_error)
+                {
+                    Some(agent_error) => agent_error.into(),
+                    None => e,
+                }
+            })
+    }
+}
+
 impl IpcAgentClient<JsonRpcClientImpl> {
     pub fn default_from_url(url: Url) -> Self {
         IpcAgentClient::new(JsonRpcClientImpl::new(url, None))
     }
+
+    /// Creates a client authenticating its requests with the token minted by the agent, see
+    /// [`crate::server::auth`].
+    pub fn from_url_with_token(url: Url, token: Option<&str>) -> Self {
+        IpcAgentClient::new(JsonRpcClientImpl::new(url, token))
+    }
+
+    /// Creates a client of the agent serving the json rpc api with the server config, through its
+    /// unix socket if configured, and otherwise over https if tls is configured.
+    pub fn from_server_config(
+        server: &Server,
+        token: Option<&str>,
+        tls: &ClientTls,
+    ) -> anyhow::Result<Self> {
+        let url = server.json_rpc_url()?;
+        let json_rpc_client =
+            if url.scheme() != UNIX_SOCKET_SCHEME && (server.tls.is_some() || !tls.is_empty()) {
+                JsonRpcClientImpl::new_with_tls(url, token, tls)?
+            } else {
+                JsonRpcClientImpl::new(url, token)
+            };
+        Ok(IpcAgentClient::new(json_rpc_client))
+    }
 }
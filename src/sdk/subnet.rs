@@ -7,11 +7,12 @@ use crate::server::create::{CreateSubnetParams, CreateSubnetResponse};
 use crate::server::join::JoinSubnetParams;
 use crate::server::kill::KillSubnetParams;
 use crate::server::leave::LeaveSubnetParams;
+use crate::tracker::TrackedTransaction;
 
 impl<T: JsonRpcClient> IpcAgentClient<T> {
-    pub async fn create_subnet(&self, params: CreateSubnetParams) -> anyhow::Result<String> {
+    pub async fn create_subnet(&self, mut params: CreateSubnetParams) -> anyhow::Result<String> {
+        params.asynchronous = false;
         Ok(self
-            .json_rpc_client
             .request::<CreateSubnetResponse>(
                 json_rpc_methods::CREATE_SUBNET,
                 serde_json::to_value(params)?,
@@ -20,24 +21,48 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
             .address)
     }
 
-    pub async fn join_subnet(&self, params: JoinSubnetParams) -> anyhow::Result<()> {
-        self.json_rpc_client
-            .request::<()>(json_rpc_methods::JOIN_SUBNET, serde_json::to_value(params)?)
+    /// Creates the subnet without waiting for the message to be executed, returning its record.
+    pub async fn create_subnet_async(
+        &self,
+        mut params: CreateSubnetParams,
+    ) -> anyhow::Result<TrackedTransaction> {
+        params.asynchronous = true;
+        self.request::<TrackedTransaction>(
+            json_rpc_methods::CREATE_SUBNET,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
+    pub async fn join_subnet(&self, mut params: JoinSubnetParams) -> anyhow::Result<()> {
+        params.asynchronous = false;
+        self.request::<()>(json_rpc_methods::JOIN_SUBNET, serde_json::to_value(params)?)
             .await
     }
 
+    /// Joins the subnet without waiting for the message to be executed, returning its record.
+    pub async fn join_subnet_async(
+        &self,
+        mut params: JoinSubnetParams,
+    ) -> anyhow::Result<TrackedTransaction> {
+        params.asynchronous = true;
+        self.request::<TrackedTransaction>(
+            json_rpc_methods::JOIN_SUBNET,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
     pub async fn leave_subnet(&self, params: LeaveSubnetParams) -> anyhow::Result<()> {
-        self.json_rpc_client
-            .request::<()>(
-                json_rpc_methods::LEAVE_SUBNET,
-                serde_json::to_value(params)?,
-            )
-            .await
+        self.request::<()>(
+            json_rpc_methods::LEAVE_SUBNET,
+            serde_json::to_value(params)?,
+        )
+        .await
     }
 
     pub async fn kill_subnet(&self, params: KillSubnetParams) -> anyhow::Result<()> {
-        self.json_rpc_client
-            .request::<()>(json_rpc_methods::KILL_SUBNET, serde_json::to_value(params)?)
+        self.request::<()>(json_rpc_methods::KILL_SUBNET, serde_json::to_value(params)?)
             .await
     }
 }
@@ -0,0 +1,40 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Tracked transactions related sdk functions
+
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::sdk::IpcAgentClient;
+use crate::server::{GetTransactionStatusParams, ListTransactionsParams};
+use crate::tracker::{TrackedTransaction, TransactionStatus};
+
+impl<T: JsonRpcClient> IpcAgentClient<T> {
+    pub async fn get_transaction_status(&self, id: u64) -> anyhow::Result<TrackedTransaction> {
+        let params = GetTransactionStatusParams { id };
+
+        self.request::<TrackedTransaction>(
+            json_rpc_methods::GET_TRANSACTION_STATUS,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+
+    pub async fn list_transactions(
+        &self,
+        subnet: Option<String>,
+        status: Option<TransactionStatus>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<TrackedTransaction>> {
+        let params = ListTransactionsParams {
+            subnet,
+            status,
+            limit,
+        };
+
+        self.request::<Vec<TrackedTransaction>>(
+            json_rpc_methods::LIST_TRANSACTIONS,
+            serde_json::to_value(params)?,
+        )
+        .await
+    }
+}
@@ -42,7 +42,6 @@ impl<T: JsonRpcClient> IpcAgentClient<T> {
 
     pub async fn import(&self, params: WalletImportParams) -> anyhow::Result<String> {
         Ok(self
-            .json_rpc_client
             .request::<WalletImportResponse>(
                 json_rpc_methods::WALLET_IMPORT,
                 serde_json::to_value(params)?,
@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: MIT
 //! Json serialization of checkpoints
 
+use crate::checkpoint::NativeBottomUpCheckpoint;
 use crate::serialization::SerializeToJson;
 use base64::Engine;
+use cid::Cid;
 use ipc_gateway::checkpoint::{BatchCrossMsgs, CheckData};
 use ipc_gateway::BottomUpCheckpoint;
 use num_traits::ToPrimitive;
@@ -27,6 +29,66 @@ impl Serialize for SerializeToJson<BottomUpCheckpoint> {
     }
 }
 
+impl Serialize for SerializeToJson<NativeBottomUpCheckpoint> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let NativeBottomUpCheckpoint {
+            source,
+            proof,
+            epoch,
+            prev_check,
+            children,
+            cross_msgs,
+            sig,
+        } = &self.0;
+
+        let source = source.to_string();
+        let proof = proof
+            .as_ref()
+            .map(|p| base64::engine::general_purpose::STANDARD.encode(p));
+        let prev_check = prev_check.as_deref().map(check_to_string);
+        let children = children
+            .iter()
+            .map(|c| {
+                let source = c.source.to_string();
+                let checks = c
+                    .checks
+                    .iter()
+                    .map(|check| check_to_string(check))
+                    .collect::<Vec<_>>();
+                serde_json::json!({
+                    "source": source,
+                    "checks": checks,
+                })
+            })
+            .collect::<Vec<_>>();
+        let cross_msgs = SerializeToJson(cross_msgs);
+        let sig = base64::engine::general_purpose::STANDARD.encode(sig);
+
+        let mut state = serializer.serialize_struct("NativeBottomUpCheckpoint", 7)?;
+        state.serialize_field("source", &source)?;
+        state.serialize_field("proof", &proof)?;
+        state.serialize_field("epoch", epoch)?;
+        state.serialize_field("prev_check", &prev_check)?;
+        state.serialize_field("children", &children)?;
+        state.serialize_field("cross_msgs", &cross_msgs)?;
+        state.serialize_field("sig", &sig)?;
+
+        state.end()
+    }
+}
+
+/// Checks are cids in fvm and 32 bytes hashes in evm, show the cid when the bytes are a valid one
+/// and fallback to hex otherwise.
+fn check_to_string(check: &[u8]) -> String {
+    match Cid::try_from(check) {
+        Ok(cid) => cid.to_string(),
+        Err(_) => format!("0x{}", hex::encode(check)),
+    }
+}
+
 impl<'a> Serialize for SerializeToJson<&'a CheckData> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -106,8 +168,12 @@ impl<'a> Serialize for SerializeToJson<&'a BatchCrossMsgs> {
 
 #[cfg(test)]
 mod tests {
+    use crate::checkpoint::{NativeBottomUpCheckpoint, NativeChildCheck};
     use crate::serialization::SerializeToJson;
+    use cid::Cid;
+    use ipc_gateway::checkpoint::BatchCrossMsgs;
     use ipc_gateway::{BottomUpCheckpoint, SubnetID};
+    use std::str::FromStr;
 
     #[test]
     fn test_serialization() {
@@ -116,4 +182,33 @@ mod tests {
         let v = serde_json::to_string(&SerializeToJson(cp)).unwrap();
         println!("{v:}");
     }
+
+    #[test]
+    fn test_native_checkpoint_serialization() {
+        let root = SubnetID::new_root(123);
+        let prev_check =
+            Cid::from_str("bafy2bzacecsatvda6lodrorh7y7foxjt3a2dexxx5jiyvtl7gimrrvywb7l5m")
+                .unwrap();
+        let cp = NativeBottomUpCheckpoint {
+            source: root.clone(),
+            proof: None,
+            epoch: 10,
+            prev_check: Some(prev_check.to_bytes()),
+            children: vec![NativeChildCheck {
+                source: root,
+                checks: vec![vec![1; 32]],
+            }],
+            cross_msgs: BatchCrossMsgs::default(),
+            sig: vec![],
+        };
+
+        let v = serde_json::to_value(SerializeToJson(cp)).unwrap();
+        assert_eq!(v["epoch"], 10);
+        assert_eq!(v["proof"], serde_json::Value::Null);
+        assert_eq!(v["prev_check"], prev_check.to_string());
+        assert_eq!(
+            v["children"][0]["checks"][0],
+            format!("0x{}", "01".repeat(32))
+        );
+    }
 }
@@ -6,6 +6,10 @@
 
 mod checkpoint;
 
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+
 /// A helper struct to serialize struct to json.
 ///
 /// Most of the types should have no need to use this struct. But some types that are shared between
@@ -13,3 +17,26 @@ mod checkpoint;
 /// to handle convert to json instead.
 #[derive(Debug)]
 pub struct SerializeToJson<T>(pub T);
+
+/// The json of the wrapped types is written by hand, so it is only described as an object.
+impl<T> JsonSchema for SerializeToJson<T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::from("SerializeToJson")
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(String::from("The json serialization of an actor type")),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Token based authentication of the json rpc requests.
+//!
+//! The tokens are JWTs signed with HMAC-SHA256 using a secret stored in the agent repo, and carry
+//! the permissions granted to their holder, in the same format as the Lotus tokens. Every method
+//! requires one of the `read`, `write`, `sign` and `admin` permissions.
+
+use crate::config::{json_rpc_methods, ReloadableConfig};
+use crate::server::error::AgentError;
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the file storing the secret of the tokens in the agent repo directory.
+pub const JWT_SECRET_FILE_NAME: &str = "jwt_secret";
+const JWT_SECRET_LEN: usize = 32;
+const JWT_ALGORITHM: &str = "HS256";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Query the state of the agent and the subnets.
+    Read,
+    /// Change the state of the agent without spending funds.
+    Write,
+    /// Send messages signed with the keys of the agent.
+    Sign,
+    /// Manage the agent and its keys.
+    Admin,
+}
+
+impl Permission {
+    /// The permissions granted along with this one: every permission includes the lower ones.
+    pub fn granted(&self) -> Vec<Permission> {
+        [
+            Permission::Read,
+            Permission::Write,
+            Permission::Sign,
+            Permission::Admin,
+        ]
+        .into_iter()
+        .filter(|p| p <= self)
+        .collect()
+    }
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let permission = match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Sign => "sign",
+            Self::Admin => "admin",
+        };
+        write!(f, "{permission:}")
+    }
+}
+
+impl FromStr for Permission {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "sign" => Ok(Self::Sign),
+            "admin" => Ok(Self::Admin),
+            _ => Err(anyhow!("unknown permission: {s:}")),
+        }
+    }
+}
+
+/// The permission required to call the json rpc method. The methods not listed here require the
+/// admin permission.
+pub fn method_permission(method: &str) -> Permission {
+    match method {
+        json_rpc_methods::LIST_CHILD_SUBNETS
+        | json_rpc_methods::QUERY_VALIDATOR_SET
+        | json_rpc_methods::RPC_SUBNET
+        | json_rpc_methods::WALLET_BALANCES
+        | json_rpc_methods::LIST_BOTTOMUP_CHECKPOINTS
+        | json_rpc_methods::LAST_TOPDOWN_EXECUTED
+        | json_rpc_methods::CHECKPOINT_STATUS
+        | json_rpc_methods::PREVIEW_BOTTOMUP_CHECKPOINT
+        | json_rpc_methods::AUDIT_BOTTOMUP_CHECKPOINTS
+        | json_rpc_methods::GET_TRANSACTION_STATUS
+        | json_rpc_methods::LIST_TRANSACTIONS
+        | json_rpc_methods::RPC_DISCOVER
+        | json_rpc_methods::SUBSCRIBE
+        | json_rpc_methods::UNSUBSCRIBE => Permission::Read,
+        json_rpc_methods::WALLET_NEW | json_rpc_methods::RELOAD_CONFIG => Permission::Write,
+        json_rpc_methods::CREATE_SUBNET
+        | json_rpc_methods::JOIN_SUBNET
+        | json_rpc_methods::LEAVE_SUBNET
+        | json_rpc_methods::KILL_SUBNET
+        | json_rpc_methods::FUND
+        | json_rpc_methods::RELEASE
+        | json_rpc_methods::PROPAGATE
+        | json_rpc_methods::WHITELIST_PROPAGATOR
+        | json_rpc_methods::SET_VALIDATOR_NET_ADDR
+        | json_rpc_methods::SEND_VALUE => Permission::Sign,
+        _ => Permission::Admin,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    typ: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JwtClaims {
+    #[serde(rename = "Allow")]
+    allow: Vec<Permission>,
+    /// Unix timestamp in seconds after which the token is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exp: Option<u64>,
+}
+
+/// Creates and verifies the tokens signed with the secret of the agent.
+pub struct JwtAuth {
+    secret: Vec<u8>,
+}
+
+impl JwtAuth {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+
+    /// Loads the secret stored in `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let secret = hex::decode(fs::read_to_string(path)?.trim())
+            .map_err(|e| anyhow!("cannot parse jwt secret {path:?}: {e:}"))?;
+        Ok(Self::new(secret))
+    }
+
+    /// Loads the secret stored in `path`, or creates a new random one if the file does not exist.
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        if path.exists() {
+            return Self::load(path);
+        }
+
+        let mut secret = vec![0u8; JWT_SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+
+        let mut file = fs::File::create(path)?;
+        #[cfg(unix)]
+        ipc_identity::set_user_perm(&file)?;
+        file.write_all(hex::encode(&secret).as_bytes())?;
+        file.sync_all()?;
+        log::info!("created jwt secret in {path:?}");
+
+        Ok(Self::new(secret))
+    }
+
+    /// Loads the secret from the repo directory of the agent config.
+    pub fn from_config(config: &ReloadableConfig) -> Result<Self> {
+        let repo = config
+            .get_config_repo()
+            .ok_or_else(|| anyhow!("No repo found in config to store the jwt secret"))?;
+        Self::load_or_create(Path::new(&repo).join(JWT_SECRET_FILE_NAME))
+    }
+
+    /// Creates a token granting the permission, and the lower ones, until `expires_at` if set.
+    pub fn create_token(&self, permission: Permission, expires_at: Option<u64>) -> Result<String> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+        let header = serde_json::to_vec(&JwtHeader {
+            alg: String::from(JWT_ALGORITHM),
+            typ: String::from("JWT"),
+        })?;
+        let claims = serde_json::to_vec(&JwtClaims {
+            allow: permission.granted(),
+            exp: expires_at,
+        })?;
+
+        let payload = format!("{}.{}", engine.encode(header), engine.encode(claims));
+        let signature = self.mac(payload.as_bytes())?.finalize().into_bytes();
+        Ok(format!("{payload:}.{}", engine.encode(signature)))
+    }
+
+    /// Returns the permissions granted by the token if it was signed with the secret of the agent
+    /// and has not expired.
+    pub fn verify(&self, token: &str) -> Result<Vec<Permission>> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+        let (payload, signature) = token
+            .rsplit_once('.')
+            .ok_or_else(|| anyhow!("malformed token"))?;
+        let (header, claims) = payload
+            .split_once('.')
+            .ok_or_else(|| anyhow!("malformed token"))?;
+
+        let signature = engine
+            .decode(signature)
+            .map_err(|_| anyhow!("malformed token signature"))?;
+        self.mac(payload.as_bytes())?
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("invalid token signature"))?;
+
+        let header: JwtHeader = serde_json::from_slice(&engine.decode(header)?)?;
+        if header.alg != JWT_ALGORITHM {
+            return Err(anyhow!("unsupported token algorithm: {:}", header.alg));
+        }
+
+        let claims: JwtClaims = serde_json::from_slice(&engine.decode(claims)?)?;
+        if let Some(exp) = claims.exp {
+            if exp <= now() {
+                return Err(anyhow!("token expired"));
+            }
+        }
+        Ok(claims.allow)
+    }
+
+    fn mac(&self, payload: &[u8]) -> Result<HmacSha256> {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).map_err(|_| anyhow!("invalid jwt secret"))?;
+        mac.update(payload);
+        Ok(mac)
+    }
+}
+
+/// Authorizes the json rpc requests according to the `authorization` header they carry.
+pub struct RequestAuth {
+    jwt: JwtAuth,
+    /// Whether the requests without a token are rejected.
+    require_auth: bool,
+}
+
+impl RequestAuth {
+    pub fn new(jwt: JwtAuth, require_auth: bool) -> Self {
+        Self { jwt, require_auth }
+    }
+
+    /// Checks the request can call the method. The requests without a token are rejected if the
+    /// server requires authentication, and otherwise only get the read permission.
+    pub fn authorize(&self, authorization: Option<&str>, method: &str) -> Result<(), AgentError> {
+        let required = method_permission(method);
+        if !self.granted(authorization)?.contains(&required) {
+            return Err(AgentError::Unauthorized {
+                reason: format!("method {method:} requires the {required:} permission"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the request has the permission, for the endpoints other than the json rpc methods.
+    pub fn authorize_permission(
+        &self,
+        authorization: Option<&str>,
+        required: Permission,
+    ) -> Result<(), AgentError> {
+        if !self.granted(authorization)?.contains(&required) {
+            return Err(AgentError::Unauthorized {
+                reason: format!("the {required:} permission is required"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the permissions granted to the request.
+    fn granted(&self, authorization: Option<&str>) -> Result<Vec<Permission>, AgentError> {
+        let unauthorized = |reason: String| AgentError::Unauthorized { reason };
+
+        match authorization {
+            Some(authorization) => {
+                let token = authorization
+                    .strip_prefix("Bearer ")
+                    .ok_or_else(|| unauthorized(String::from("expected a bearer token")))?;
+                self.jwt
+                    .verify(token.trim())
+                    .map_err(|e| unauthorized(e.to_string()))
+            }
+            None if self.require_auth => Err(unauthorized(String::from("missing token"))),
+            None => Ok(Permission::Read.granted()),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::json_rpc_methods;
+    use crate::server::auth::{
+        method_permission, JwtAuth, Permission, RequestAuth, JWT_SECRET_FILE_NAME,
+    };
+
+    #[test]
+    fn test_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JWT_SECRET_FILE_NAME);
+        let auth = JwtAuth::load_or_create(&path).unwrap();
+
+        let token = auth.create_token(Permission::Sign, None).unwrap();
+        assert_eq!(
+            auth.verify(&token).unwrap(),
+            vec![Permission::Read, Permission::Write, Permission::Sign]
+        );
+
+        // the secret is persisted
+        let auth = JwtAuth::load_or_create(&path).unwrap();
+        assert!(auth.verify(&token).is_ok());
+
+        // signed with another secret
+        let other = JwtAuth::new(vec![1; 32]);
+        assert!(other.verify(&token).is_err());
+
+        // tampered with
+        let mut parts = token.split('.').collect::<Vec<_>>();
+        let admin = other.create_token(Permission::Admin, None).unwrap();
+        parts[1] = admin.split('.').nth(1).unwrap();
+        assert!(auth.verify(&parts.join(".")).is_err());
+
+        // expired
+        let expired = auth.create_token(Permission::Read, Some(1)).unwrap();
+        assert!(auth.verify(&expired).is_err());
+        assert!(auth.verify("not a token").is_err());
+    }
+
+    #[test]
+    fn test_method_permissions() {
+        assert_eq!(
+            method_permission(json_rpc_methods::LIST_CHILD_SUBNETS),
+            Permission::Read
+        );
+        assert_eq!(method_permission(json_rpc_methods::FUND), Permission::Sign);
+        assert_eq!(
+            method_permission(json_rpc_methods::WALLET_EXPORT),
+            Permission::Admin
+        );
+        assert_eq!(
+            method_permission(json_rpc_methods::WALLET_IMPORT),
+            Permission::Admin
+        );
+        assert_eq!(
+            method_permission(json_rpc_methods::WALLET_REMOVE),
+            Permission::Admin
+        );
+        assert_eq!(method_permission("ipc_unknown"), Permission::Admin);
+    }
+
+    #[test]
+    fn test_authorize() {
+        let jwt = JwtAuth::new(vec![0; 32]);
+        let sign = format!(
+            "Bearer {:}",
+            jwt.create_token(Permission::Sign, None).unwrap()
+        );
+
+        // the requests without a token can only read
+        let auth = RequestAuth::new(JwtAuth::new(vec![0; 32]), false);
+        assert!(auth
+            .authorize(None, json_rpc_methods::LIST_CHILD_SUBNETS)
+            .is_ok());
+        assert!(auth.authorize(None, json_rpc_methods::WALLET_NEW).is_err());
+        assert!(auth.authorize(None, json_rpc_methods::FUND).is_err());
+        assert!(auth.authorize(Some(&sign), json_rpc_methods::FUND).is_ok());
+        assert!(auth
+            .authorize(Some(&sign), json_rpc_methods::WALLET_IMPORT)
+            .is_err());
+
+        let auth = RequestAuth::new(JwtAuth::new(vec![0; 32]), true);
+        assert!(auth
+            .authorize(None, json_rpc_methods::LIST_CHILD_SUBNETS)
+            .is_err());
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The errors returned by the json rpc methods of the agent.
+//!
+//! Every error has a stable code and carries its fields as the `data` of the json rpc error
+//! object, tagged with its `kind`, so that the clients can branch on them. The handlers and managers
+//! keep on returning `anyhow` errors, wrapping an [`AgentError`] where they know what failed. The
+//! other errors are classified by the type of their sources when the response is built, e.g. the
+//! errors of the nodes, and are internal errors otherwise.
+
+use crate::jsonrpc::JsonRpcError;
+use crate::server::response::{
+    JSONRPCError, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const UNAUTHORIZED_CODE: i32 = -32001;
+pub const SUBNET_NOT_CONFIGURED_CODE: i32 = -32010;
+pub const INSUFFICIENT_FUNDS_CODE: i32 = -32011;
+pub const KEY_NOT_FOUND_CODE: i32 = -32012;
+pub const UPSTREAM_NODE_ERROR_CODE: i32 = -32013;
+pub const REVERTED_CODE: i32 = -32014;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AgentError {
+    #[error("method not found: {method:}")]
+    MethodNotFound { method: String },
+    #[error("invalid params: {reason:}")]
+    InvalidParams { reason: String },
+    #[error("unauthorized: {reason:}")]
+    Unauthorized { reason: String },
+    /// The subnet is missing from the config of the agent.
+    #[error("subnet not configured: {subnet:}")]
+    SubnetNotConfigured { subnet: String },
+    #[error("insufficient funds: {reason:}")]
+    InsufficientFunds { reason: String },
+    /// The agent has no key for the address, if known.
+    #[error("key not found{}", address.as_ref().map(|a| format!(": {a:}")).unwrap_or_default())]
+    KeyNotFound { address: Option<String> },
+    /// The node of the subnet could not be reached or failed the call.
+    #[error("upstream node error: {reason:}")]
+    UpstreamNode {
+        reason: String,
+        /// The error returned by the node, if it replied.
+        code: Option<i64>,
+    },
+    /// The message or transaction was executed but reverted.
+    #[error("reverted: {reason:}")]
+    Reverted { reason: String },
+    #[error("{message:}")]
+    Internal { message: String },
+}
+
+impl AgentError {
+    pub fn subnet_not_configured(subnet: impl ToString) -> Self {
+        Self::SubnetNotConfigured {
+            subnet: subnet.to_string(),
+        }
+    }
+
+    pub fn key_not_found(address: impl ToString) -> Self {
+        Self::KeyNotFound {
+            address: Some(address.to_string()),
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::MethodNotFound { .. } => METHOD_NOT_FOUND_CODE,
+            Self::InvalidParams { .. } => INVALID_PARAMS_CODE,
+            Self::Unauthorized { .. } => UNAUTHORIZED_CODE,
+            Self::SubnetNotConfigured { .. } => SUBNET_NOT_CONFIGURED_CODE,
+            Self::InsufficientFunds { .. } => INSUFFICIENT_FUNDS_CODE,
+            Self::KeyNotFound { .. } => KEY_NOT_FOUND_CODE,
+            Self::UpstreamNode { .. } => UPSTREAM_NODE_ERROR_CODE,
+            Self::Reverted { .. } => REVERTED_CODE,
+            Self::Internal { .. } => INTERNAL_ERROR_CODE,
+        }
+    }
+
+    /// The json rpc error object of the response.
+    pub fn to_json_rpc_error(&self) -> JSONRPCError<Value> {
+        JSONRPCError {
+            code: self.code(),
+            message: self.to_string(),
+            data: serde_json::to_value(self).ok(),
+        }
+    }
+
+    /// Parses the error object of a response of the agent, `None` if it is not an agent error.
+    pub fn from_json_rpc_error(error: &JsonRpcError) -> Option<Self> {
+        let e = serde_json::from_value::<Self>(error.data.clone()?).ok()?;
+        (i64::from(e.code()) == error.code).then_some(e)
+    }
+}
+
+impl From<anyhow::Error> for AgentError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(e) = e.chain().find_map(|c| c.downcast_ref::<AgentError>()) {
+            return e.clone();
+        }
+
+        let reason = format!("{e:#}");
+        for cause in e.chain() {
+            if let Some(
+                ipc_identity::Error::KeyInfo
+                | ipc_identity::Error::KeyNotExists
+                | ipc_identity::Error::NoKey,
+            ) = cause.downcast_ref::<ipc_identity::Error>()
+            {
+                return Self::KeyNotFound { address: None };
+            }
+            if let Some(error) = cause.downcast_ref::<JsonRpcError>() {
+                return Self::UpstreamNode {
+                    reason,
+                    code: Some(error.code),
+                };
+            }
+            if cause.is::<reqwest::Error>()
+                || cause.is::<ethers::providers::ProviderError>()
+                || cause.is::<tokio_tungstenite::tungstenite::Error>()
+            {
+                return Self::UpstreamNode { reason, code: None };
+            }
+        }
+
+        Self::Internal { message: reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jsonrpc::JsonRpcError;
+    use crate::server::error::{AgentError, KEY_NOT_FOUND_CODE};
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_classify_errors() {
+        let e = anyhow::Error::new(AgentError::subnet_not_configured("/r123"))
+            .context("cannot fund the subnet");
+        assert_eq!(
+            AgentError::from(e),
+            AgentError::subnet_not_configured("/r123")
+        );
+
+        let e = anyhow::Error::new(AgentError::Reverted {
+            reason: String::from("not enough collateral"),
+        })
+        .context("cannot join the subnet");
+        assert!(matches!(AgentError::from(e), AgentError::Reverted { .. }));
+
+        let e = Err::<(), _>(JsonRpcError {
+            code: 1,
+            message: String::from("actor not found"),
+            data: None,
+        })
+        .context("cannot get the balance")
+        .unwrap_err();
+        assert!(matches!(
+            AgentError::from(e),
+            AgentError::UpstreamNode { code: Some(1), .. }
+        ));
+
+        let e = anyhow::Error::new(ipc_identity::Error::KeyNotExists);
+        assert_eq!(
+            AgentError::from(e),
+            AgentError::KeyNotFound { address: None }
+        );
+
+        // the messages of the errors are not inspected
+        let e = anyhow!("execution reverted").context("something else");
+        assert_eq!(
+            AgentError::from(e),
+            AgentError::Internal {
+                message: String::from("something else: execution reverted")
+            }
+        );
+    }
+
+    #[test]
+    fn test_json_rpc_error_round_trip() {
+        let e = AgentError::key_not_found("t1cp4q4lqsdhob23ysywffg2tvbmar5cshia4rweq");
+        let error = e.to_json_rpc_error();
+        assert_eq!(error.code, KEY_NOT_FOUND_CODE);
+
+        let error = JsonRpcError {
+            code: error.code as i64,
+            message: error.message,
+            data: error.data,
+        };
+        assert_eq!(AgentError::from_json_rpc_error(&error), Some(e));
+
+        // the errors of other servers are not agent errors
+        let error = JsonRpcError {
+            code: -32601,
+            message: String::from("method not found"),
+            data: None,
+        };
+        assert_eq!(AgentError::from_json_rpc_error(&error), None);
+    }
+}
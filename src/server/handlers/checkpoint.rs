@@ -0,0 +1,194 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Status of the checkpoint managers run by the agent, preview and audit of the checkpoints
+
+use crate::checkpoint::{
+    audit_chain, audit_proofs, setup_bottom_up_builder, AuditReport, CheckpointQuery,
+    CheckpointStatusRegistry, ManagerStatus, NativeBottomUpCheckpoint,
+};
+use crate::config::subnet::NetworkType;
+use crate::config::ReloadableConfig;
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::manager::{LotusSubnetManager, NonceManager, SubnetManager};
+use crate::serialization::SerializeToJson;
+use crate::server::error::AgentError;
+use crate::server::JsonRPCRequestHandler;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use fvm_shared::clock::ChainEpoch;
+use ipc_identity::{PersistentKeyStore, Wallet};
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CheckpointStatusParams {
+    /// Only return the managers with this subnet as parent or child.
+    pub subnet_id: Option<String>,
+}
+
+/// The checkpoint status json rpc method handler.
+pub(crate) struct CheckpointStatusHandler {
+    status: Arc<CheckpointStatusRegistry>,
+}
+
+impl CheckpointStatusHandler {
+    pub(crate) fn new(status: Arc<CheckpointStatusRegistry>) -> Self {
+        Self { status }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for CheckpointStatusHandler {
+    type Request = CheckpointStatusParams;
+    type Response = Vec<ManagerStatus>;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let mut managers = self.status.list();
+        if let Some(subnet_id) = request.subnet_id {
+            managers.retain(|m| m.parent == subnet_id || m.child == subnet_id);
+        }
+        Ok(managers)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewBottomUpCheckpointParams {
+    pub subnet_id: String,
+    pub epoch: ChainEpoch,
+}
+
+/// The preview bottom-up checkpoint json rpc method handler. It builds the checkpoint the agent
+/// would submit for the subnet in the epoch, without submitting it.
+pub(crate) struct PreviewBottomUpCheckpointHandler {
+    config: Arc<ReloadableConfig>,
+    fvm_wallet: Arc<RwLock<Wallet>>,
+    evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    nonces: Arc<NonceManager>,
+}
+
+impl PreviewBottomUpCheckpointHandler {
+    pub(crate) fn new(
+        config: Arc<ReloadableConfig>,
+        fvm_wallet: Arc<RwLock<Wallet>>,
+        evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        nonces: Arc<NonceManager>,
+    ) -> Self {
+        Self {
+            config,
+            fvm_wallet,
+            evm_keystore,
+            nonces,
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for PreviewBottomUpCheckpointHandler {
+    type Request = PreviewBottomUpCheckpointParams;
+    type Response = SerializeToJson<NativeBottomUpCheckpoint>;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let subnet_id = SubnetID::from_str(&request.subnet_id)?;
+
+        let config = self.config.get_config();
+        let subnet = config
+            .subnets
+            .get(&subnet_id)
+            .ok_or_else(|| AgentError::subnet_not_configured(&subnet_id))?;
+
+        let builder = setup_bottom_up_builder(
+            &config.subnets,
+            subnet,
+            self.fvm_wallet.clone(),
+            self.evm_keystore.clone(),
+            &self.nonces,
+        )
+        .await?;
+        let checkpoint = builder.build_checkpoint(request.epoch).await?;
+
+        Ok(SerializeToJson(checkpoint))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AuditBottomUpCheckpointsParams {
+    pub subnet_id: String,
+    pub from_epoch: ChainEpoch,
+    pub to_epoch: ChainEpoch,
+}
+
+/// The audit bottom-up checkpoints json rpc method handler. It walks the checkpoints committed by
+/// the subnet in its parent and reports the gaps and breaks in their chain.
+pub(crate) struct AuditBottomUpCheckpointsHandler {
+    config: Arc<ReloadableConfig>,
+}
+
+impl AuditBottomUpCheckpointsHandler {
+    pub(crate) fn new(config: Arc<ReloadableConfig>) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for AuditBottomUpCheckpointsHandler {
+    type Request = AuditBottomUpCheckpointsParams;
+    type Response = AuditReport;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let child_subnet_id = SubnetID::from_str(&request.subnet_id)?;
+        let parent_subnet_id = child_subnet_id
+            .parent()
+            .ok_or_else(|| anyhow!("subnet id does not have a parent"))?;
+
+        let config = self.config.get_config();
+        let parent = config
+            .subnets
+            .get(&parent_subnet_id)
+            .ok_or_else(|| AgentError::subnet_not_configured(&parent_subnet_id))?;
+        // listing the committed checkpoints is only supported in fvm parents
+        if parent.network_type() != NetworkType::Fvm {
+            return Err(anyhow!(
+                "cannot audit checkpoints committed in a non fvm parent: {parent_subnet_id:}"
+            ));
+        }
+
+        let manager = LotusSubnetManager::from_subnet(parent);
+        let period = CheckpointQuery::<NativeBottomUpCheckpoint>::checkpoint_period(
+            &manager,
+            &child_subnet_id,
+        )
+        .await?;
+        let checkpoints = manager
+            .list_checkpoints(
+                child_subnet_id.clone(),
+                request.from_epoch,
+                request.to_epoch,
+            )
+            .await?;
+
+        let mut issues = audit_chain(&checkpoints, period);
+
+        // the proofs can only be verified if we can reach the child subnet
+        let proofs_verified = match config.subnets.get(&child_subnet_id) {
+            Some(child) => {
+                let client = LotusJsonRPCClient::from_subnet(child);
+                issues.extend(audit_proofs(&client, &checkpoints).await);
+                true
+            }
+            None => false,
+        };
+
+        Ok(AuditReport {
+            subnet_id: child_subnet_id.to_string(),
+            from_epoch: request.from_epoch,
+            to_epoch: request.to_epoch,
+            period,
+            checkpoints: checkpoints.len(),
+            proofs_verified,
+            issues,
+        })
+    }
+}
@@ -8,10 +8,11 @@ use anyhow::anyhow;
 use async_trait::async_trait;
 use ipc_identity::{KeyStore, KeyStoreConfig, KEYSTORE_NAME};
 use ipc_identity::{PersistentKeyStore, DEFAULT_KEYSTORE_NAME};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{path::Path, sync::Arc};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ReloadConfigParams {
     pub path: Option<String>,
 }
@@ -38,7 +39,8 @@ impl JsonRPCRequestHandler for ReloadConfigHandler {
         if request.path.is_some() {
             self.config.set_path(request.path.unwrap());
         }
-        self.config.reload().await
+        self.config.reload().await?;
+        Ok(())
     }
 }
 
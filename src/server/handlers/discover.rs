@@ -0,0 +1,31 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Serves the OpenRPC document of the json rpc api
+
+use crate::server::openrpc::OpenRpcDocument;
+use crate::server::JsonRPCRequestHandler;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// The rpc discover json rpc method handler.
+pub(crate) struct DiscoverHandler {
+    document: Value,
+}
+
+impl DiscoverHandler {
+    pub(crate) fn new(document: OpenRpcDocument) -> anyhow::Result<Self> {
+        Ok(Self {
+            document: serde_json::to_value(document)?,
+        })
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for DiscoverHandler {
+    type Request = ();
+    type Response = Value;
+
+    async fn handle(&self, _request: Self::Request) -> anyhow::Result<Self::Response> {
+        Ok(self.document.clone())
+    }
+}
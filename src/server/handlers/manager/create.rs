@@ -2,19 +2,25 @@
 // SPDX-License-Identifier: MIT
 //! Create subnet handler and parameters
 
+use crate::config::json_rpc_methods;
+use crate::events::{EventBus, EventTopic};
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::handlers::manager::{check_subnet, parse_from};
+use crate::server::handlers::transaction::{submit_tracked, Tracked};
 use crate::server::{handlers, JsonRPCRequestHandler};
-use anyhow::anyhow;
+use crate::tracker::TransactionTracker;
 use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
 use ipc_sdk::subnet_id::SubnetID;
 use ipc_subnet_actor::{ConsensusType, ConstructParams};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSubnetParams {
     pub from: Option<String>,
     pub parent: String,
@@ -24,9 +30,13 @@ pub struct CreateSubnetParams {
     pub min_validators: u64,
     pub bottomup_check_period: ChainEpoch,
     pub topdown_check_period: ChainEpoch,
+    /// Return the record of the operation as soon as its message is submitted instead of waiting
+    /// for its result.
+    #[serde(default)]
+    pub asynchronous: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSubnetResponse {
     /// The address of the created subnet
     pub address: String,
@@ -35,31 +45,48 @@ pub struct CreateSubnetResponse {
 /// The create subnet json rpc method handler.
 pub(crate) struct CreateSubnetHandler {
     pool: Arc<SubnetManagerPool>,
+    tracker: Arc<TransactionTracker>,
+    events: Arc<EventBus>,
 }
 
 impl CreateSubnetHandler {
-    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
-        Self { pool }
+    pub(crate) fn new(
+        pool: Arc<SubnetManagerPool>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            pool,
+            tracker,
+            events,
+        }
     }
 }
 
 #[async_trait]
 impl JsonRPCRequestHandler for CreateSubnetHandler {
     type Request = CreateSubnetParams;
-    type Response = CreateSubnetResponse;
+    type Response = Tracked<CreateSubnetResponse>;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let parent = SubnetID::from_str(&request.parent)?;
-        let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target parent subnet not found")),
+        let (submissions, submitted) = mpsc::unbounded_channel();
+        let conn = if request.asynchronous {
+            self.pool.get_tracked(&parent, submissions)
+        } else {
+            self.pool.get(&parent)
+        };
+        let conn = match conn {
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
         let subnet_config = conn.subnet();
         check_subnet(subnet_config)?;
 
+        let tracked_parent = parent.clone();
         let constructor_params = ConstructParams {
-            parent,
+            parent: parent.clone(),
             name: request.name,
             ipc_gateway_addr: subnet_config.gateway_addr(),
             consensus: ConsensusType::Mir,
@@ -73,13 +100,32 @@ impl JsonRPCRequestHandler for CreateSubnetHandler {
         let from = parse_from(subnet_config, request.from)?;
         log::debug!("conn: {:?}, from: {from:?}", conn.subnet());
 
-        let created_subnet_addr = conn
-            .manager()
-            .create_subnet(from, constructor_params)
-            .await?;
+        let events = self.events.clone();
+        let create = async move {
+            let created_subnet_addr = conn
+                .manager()
+                .create_subnet(from, constructor_params)
+                .await?;
+
+            let response = CreateSubnetResponse {
+                address: created_subnet_addr.to_string(),
+            };
+            events.publish(EventTopic::SubnetRegistered, Some(&parent), &response);
+            Ok::<_, anyhow::Error>(response)
+        };
+        if !request.asynchronous {
+            return Ok(Tracked::Completed(create.await?));
+        }
 
-        Ok(CreateSubnetResponse {
-            address: created_subnet_addr.to_string(),
-        })
+        let transaction = submit_tracked(
+            self.tracker.clone(),
+            json_rpc_methods::CREATE_SUBNET,
+            &tracked_parent,
+            &from,
+            submitted,
+            create,
+        )
+        .await?;
+        Ok(Tracked::Submitted(transaction))
     }
 }
@@ -2,47 +2,74 @@
 // SPDX-License-Identifier: MIT
 //! Fund operation in the gateway actor
 
+use crate::config::json_rpc_methods;
+use crate::events::{EventBus, EventTopic};
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
-use crate::server::{check_subnet, handlers, parse_from, JsonRPCRequestHandler};
+use crate::server::handlers::transaction::{submit_tracked, Tracked};
+use crate::server::{check_balance, check_subnet, handlers, parse_from, JsonRPCRequestHandler};
+use crate::tracker::TransactionTracker;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct FundParams {
     pub subnet: String,
     pub from: Option<String>,
     pub to: Option<String>,
     /// In whole FIL
     pub amount: f64,
+    /// Return the record of the operation as soon as its message is submitted instead of waiting
+    /// for its result.
+    #[serde(default)]
+    pub asynchronous: bool,
 }
 
 /// The fund json rpc method handler.
 pub(crate) struct FundHandler {
     pool: Arc<SubnetManagerPool>,
+    tracker: Arc<TransactionTracker>,
+    events: Arc<EventBus>,
 }
 
 impl FundHandler {
-    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
-        Self { pool }
+    pub(crate) fn new(
+        pool: Arc<SubnetManagerPool>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            pool,
+            tracker,
+            events,
+        }
     }
 }
 
 #[async_trait]
 impl JsonRPCRequestHandler for FundHandler {
     type Request = FundParams;
-    type Response = ChainEpoch;
+    type Response = Tracked<ChainEpoch>;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
-        let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target parent subnet not found")),
+        let (submissions, submitted) = mpsc::unbounded_channel();
+        let conn = if request.asynchronous {
+            self.pool.get_tracked(&parent, submissions)
+        } else {
+            self.pool.get(&parent)
+        };
+        let conn = match conn {
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
@@ -56,9 +83,40 @@ impl JsonRPCRequestHandler for FundHandler {
             .transpose()?
             .unwrap_or(from);
         let amount = handlers::f64_to_token_amount(request.amount)?;
+        check_balance(conn.manager(), &from, &amount).await?;
+
+        let gateway_addr = subnet_config.gateway_addr();
+        let events = self.events.clone();
+        let fund = async move {
+            let epoch = conn
+                .manager()
+                .fund(subnet.clone(), gateway_addr, from, to, amount.clone())
+                .await?;
+            events.publish(
+                EventTopic::FundExecuted,
+                Some(&subnet),
+                serde_json::json!({
+                    "from": from.to_string(),
+                    "to": to.to_string(),
+                    "amount": amount.to_string(),
+                    "epoch": epoch,
+                }),
+            );
+            Ok::<_, anyhow::Error>(epoch)
+        };
+        if !request.asynchronous {
+            return Ok(Tracked::Completed(fund.await?));
+        }
 
-        conn.manager()
-            .fund(subnet, subnet_config.gateway_addr(), from, to, amount)
-            .await
+        let transaction = submit_tracked(
+            self.tracker.clone(),
+            json_rpc_methods::FUND,
+            &parent,
+            &from,
+            submitted,
+            fund,
+        )
+        .await?;
+        Ok(Tracked::Submitted(transaction))
     }
 }
@@ -2,18 +2,25 @@
 // SPDX-License-Identifier: MIT
 //! Join subnet handler and parameters
 
+use crate::config::json_rpc_methods;
+use crate::events::{EventBus, EventTopic};
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
-use crate::server::handlers::manager::{check_subnet, parse_from};
+use crate::server::handlers::manager::{check_balance, check_subnet, parse_from};
+use crate::server::handlers::transaction::{submit_tracked, Tracked};
 use crate::server::{handlers, JsonRPCRequestHandler};
+use crate::tracker::TransactionTracker;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JoinSubnetParams {
     pub subnet: String,
     pub from: Option<String>,
@@ -21,29 +28,49 @@ pub struct JoinSubnetParams {
     pub collateral: f64,
     pub validator_net_addr: String,
     pub worker_addr: Option<String>,
+    /// Return the record of the operation as soon as its message is submitted instead of waiting
+    /// for its result.
+    #[serde(default)]
+    pub asynchronous: bool,
 }
 
 /// The create subnet json rpc method handler.
 pub(crate) struct JoinSubnetHandler {
     pool: Arc<SubnetManagerPool>,
+    tracker: Arc<TransactionTracker>,
+    events: Arc<EventBus>,
 }
 
 impl JoinSubnetHandler {
-    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
-        Self { pool }
+    pub(crate) fn new(
+        pool: Arc<SubnetManagerPool>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            pool,
+            tracker,
+            events,
+        }
     }
 }
 
 #[async_trait]
 impl JsonRPCRequestHandler for JoinSubnetHandler {
     type Request = JoinSubnetParams;
-    type Response = ();
+    type Response = Tracked<()>;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
-        let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target parent subnet not found")),
+        let (submissions, submitted) = mpsc::unbounded_channel();
+        let conn = if request.asynchronous {
+            self.pool.get_tracked(&parent, submissions)
+        } else {
+            self.pool.get(&parent)
+        };
+        let conn = match conn {
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
@@ -53,12 +80,37 @@ impl JsonRPCRequestHandler for JoinSubnetHandler {
         check_subnet(subnet_config)?;
 
         let from = parse_from(subnet_config, request.from)?;
+        check_balance(conn.manager(), &from, &collateral).await?;
         let worker = match request.worker_addr {
             None => from,
             Some(addr) => Address::from_str(&addr)?,
         };
-        conn.manager()
-            .join_subnet(subnet, from, collateral, request.validator_net_addr, worker)
-            .await
+        let validator_net_addr = request.validator_net_addr;
+        let events = self.events.clone();
+        let join = async move {
+            conn.manager()
+                .join_subnet(subnet.clone(), from, collateral, validator_net_addr, worker)
+                .await?;
+            events.publish(
+                EventTopic::ValidatorSetChanged,
+                Some(&subnet),
+                serde_json::json!({ "joined": [from.to_string()] }),
+            );
+            Ok::<_, anyhow::Error>(())
+        };
+        if !request.asynchronous {
+            return Ok(Tracked::Completed(join.await?));
+        }
+
+        let transaction = submit_tracked(
+            self.tracker.clone(),
+            json_rpc_methods::JOIN_SUBNET,
+            &parent,
+            &from,
+            submitted,
+            join,
+        )
+        .await?;
+        Ok(Tracked::Submitted(transaction))
     }
 }
@@ -2,17 +2,20 @@
 // SPDX-License-Identifier: MIT
 //! Kill subnet handler and parameters
 
+use crate::events::{EventBus, EventTopic};
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::handlers::manager::{check_subnet, parse_from};
 use crate::server::JsonRPCRequestHandler;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct KillSubnetParams {
     pub subnet: String,
     pub from: Option<String>,
@@ -21,11 +24,12 @@ pub struct KillSubnetParams {
 /// The create subnet json rpc method handler.
 pub(crate) struct KillSubnetHandler {
     pool: Arc<SubnetManagerPool>,
+    events: Arc<EventBus>,
 }
 
 impl KillSubnetHandler {
-    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
-        Self { pool }
+    pub(crate) fn new(pool: Arc<SubnetManagerPool>, events: Arc<EventBus>) -> Self {
+        Self { pool, events }
     }
 }
 
@@ -38,7 +42,7 @@ impl JsonRPCRequestHandler for KillSubnetHandler {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
         let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target parent subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
@@ -47,6 +51,12 @@ impl JsonRPCRequestHandler for KillSubnetHandler {
 
         let from = parse_from(subnet_config, request.from)?;
 
-        conn.manager().kill_subnet(subnet, from).await
+        conn.manager().kill_subnet(subnet.clone(), from).await?;
+        self.events.publish(
+            EventTopic::SubnetKilled,
+            Some(&subnet),
+            serde_json::json!({ "from": from.to_string() }),
+        );
+        Ok(())
     }
 }
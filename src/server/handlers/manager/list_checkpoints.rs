@@ -10,14 +10,16 @@ use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
 use ipc_gateway::BottomUpCheckpoint;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::serialization::SerializeToJson;
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::check_subnet;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ListBottomUpCheckpointsParams {
     pub subnet_id: String,
     pub from_epoch: ChainEpoch,
@@ -47,7 +49,7 @@ impl JsonRPCRequestHandler for ListBottomUpCheckpointsHandler {
             .ok_or_else(|| anyhow!("subnet id does not have a parent"))?;
 
         let conn = match self.pool.get(&parent_subnet_id) {
-            None => return Err(anyhow!("target parent subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&parent_subnet_id).into()),
             Some(conn) => conn,
         };
 
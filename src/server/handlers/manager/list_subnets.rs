@@ -4,19 +4,20 @@
 
 use crate::lotus::message::ipc::SubnetInfo;
 
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::check_subnet;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ListSubnetsParams {
     pub gateway_address: String,
     pub subnet_id: String,
@@ -41,7 +42,7 @@ impl JsonRPCRequestHandler for ListSubnetsHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet_id)?;
         let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target parent subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
 
@@ -5,9 +5,12 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, Result};
 use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
 
 use crate::config::subnet::SubnetConfig;
 use crate::config::Subnet;
+use crate::manager::SubnetManager;
+use crate::server::error::AgentError;
 
 pub mod create;
 pub mod fund;
@@ -54,3 +57,20 @@ pub(crate) fn parse_from(subnet: &Subnet, from: Option<String>) -> Result<Addres
     };
     Ok(addr)
 }
+
+/// Fails with an insufficient funds error if the balance of `from` cannot cover `amount`, before
+/// any message is sent.
+pub(crate) async fn check_balance(
+    manager: &dyn SubnetManager,
+    from: &Address,
+    amount: &TokenAmount,
+) -> Result<()> {
+    let balance = manager.wallet_balance(from).await?;
+    if balance < *amount {
+        return Err(AgentError::InsufficientFunds {
+            reason: format!("balance of {from:} is {balance:}, required {amount:}"),
+        }
+        .into());
+    }
+    Ok(())
+}
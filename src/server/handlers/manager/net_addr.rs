@@ -2,16 +2,18 @@
 // SPDX-License-Identifier: MIT
 //! Set the subnet actor validator net addr
 
+use crate::server::error::AgentError;
 use crate::server::subnet::SubnetManagerPool;
 use crate::server::{check_subnet, parse_from, JsonRPCRequestHandler};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SetValidatorNetAddrParams {
     pub subnet: String,
     pub from: Option<String>,
@@ -38,7 +40,7 @@ impl JsonRPCRequestHandler for SetValidatorNetAddrHandler {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let parent = subnet.parent().ok_or_else(|| anyhow!("no parent found"))?;
         let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target parent subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
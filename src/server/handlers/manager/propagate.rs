@@ -2,20 +2,22 @@
 // SPDX-License-Identifier: MIT
 //! Propagate operation in the gateway actor
 
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::{check_subnet, parse_from, JsonRPCRequestHandler};
-use anyhow::anyhow;
 use async_trait::async_trait;
 use cid::Cid;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PropagateParams {
     pub subnet: String,
     pub from: Option<String>,
+    #[schemars(with = "serde_json::Value")]
     pub postbox_msg_cid: Cid,
 }
 
@@ -38,7 +40,7 @@ impl JsonRPCRequestHandler for PropagateHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
 
@@ -2,46 +2,72 @@
 // SPDX-License-Identifier: MIT
 //! Release operation in the gateway actor
 
+use crate::config::json_rpc_methods;
+use crate::events::{EventBus, EventTopic};
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
-use crate::server::{check_subnet, handlers, parse_from, JsonRPCRequestHandler};
-use anyhow::anyhow;
+use crate::server::handlers::transaction::{submit_tracked, Tracked};
+use crate::server::{check_balance, check_subnet, handlers, parse_from, JsonRPCRequestHandler};
+use crate::tracker::TransactionTracker;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ReleaseParams {
     pub subnet: String,
     pub from: Option<String>,
     pub to: Option<String>,
     /// In whole FIL
     pub amount: f64,
+    /// Return the record of the operation as soon as its message is submitted instead of waiting
+    /// for its result.
+    #[serde(default)]
+    pub asynchronous: bool,
 }
 
 /// The Release json rpc method handler.
 pub(crate) struct ReleaseHandler {
     pool: Arc<SubnetManagerPool>,
+    tracker: Arc<TransactionTracker>,
+    events: Arc<EventBus>,
 }
 
 impl ReleaseHandler {
-    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
-        Self { pool }
+    pub(crate) fn new(
+        pool: Arc<SubnetManagerPool>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            pool,
+            tracker,
+            events,
+        }
     }
 }
 
 #[async_trait]
 impl JsonRPCRequestHandler for ReleaseHandler {
     type Request = ReleaseParams;
-    type Response = ChainEpoch;
+    type Response = Tracked<ChainEpoch>;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
-        let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target subnet not found")),
+        let (submissions, submitted) = mpsc::unbounded_channel();
+        let conn = if request.asynchronous {
+            self.pool.get_tracked(&subnet, submissions)
+        } else {
+            self.pool.get(&subnet)
+        };
+        let conn = match conn {
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
 
@@ -50,14 +76,46 @@ impl JsonRPCRequestHandler for ReleaseHandler {
 
         let amount = handlers::f64_to_token_amount(request.amount)?;
         let from = parse_from(subnet_config, request.from)?;
+        check_balance(conn.manager(), &from, &amount).await?;
         let to = request
             .to
             .map(|r| Address::from_str(&r))
             .transpose()?
             .unwrap_or(from);
 
-        conn.manager()
-            .release(subnet, subnet_config.gateway_addr(), from, to, amount)
-            .await
+        let gateway_addr = subnet_config.gateway_addr();
+        let tracked_subnet = subnet.clone();
+        let events = self.events.clone();
+        let release = async move {
+            let epoch = conn
+                .manager()
+                .release(subnet.clone(), gateway_addr, from, to, amount.clone())
+                .await?;
+            events.publish(
+                EventTopic::ReleaseExecuted,
+                Some(&subnet),
+                serde_json::json!({
+                    "from": from.to_string(),
+                    "to": to.to_string(),
+                    "amount": amount.to_string(),
+                    "epoch": epoch,
+                }),
+            );
+            Ok::<_, anyhow::Error>(epoch)
+        };
+        if !request.asynchronous {
+            return Ok(Tracked::Completed(release.await?));
+        }
+
+        let transaction = submit_tracked(
+            self.tracker.clone(),
+            json_rpc_methods::RELEASE,
+            &tracked_subnet,
+            &from,
+            submitted,
+            release,
+        )
+        .await?;
+        Ok(Tracked::Submitted(transaction))
     }
 }
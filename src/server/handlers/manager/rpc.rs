@@ -2,16 +2,17 @@
 // SPDX-License-Identifier: MIT
 //! RPC subnet handler and parameters
 
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RPCSubnetParams {
     pub subnet: String,
 }
@@ -35,7 +36,7 @@ impl JsonRPCRequestHandler for RPCSubnetHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
 
@@ -3,18 +3,19 @@
 //! SendValue subnet handler and parameters
 
 use crate::manager::evm::ethers_address_to_fil_address;
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
-use crate::server::handlers::manager::{check_subnet, parse_from};
+use crate::server::handlers::manager::{check_balance, check_subnet, parse_from};
 use crate::server::{handlers, JsonRPCRequestHandler};
-use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SendValueParams {
     pub subnet: String,
     pub from: Option<String>,
@@ -42,7 +43,7 @@ impl JsonRPCRequestHandler for SendValueHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target parent subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
 
@@ -51,6 +52,7 @@ impl JsonRPCRequestHandler for SendValueHandler {
         check_subnet(subnet_config)?;
 
         let from = parse_from(subnet_config, request.from)?;
+        check_balance(conn.manager(), &from, &amount).await?;
         let to = match Address::from_str(&request.to) {
             Ok(addr) => addr,
             Err(_) => {
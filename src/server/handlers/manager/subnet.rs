@@ -4,7 +4,9 @@
 
 use crate::config::subnet::SubnetConfig;
 use crate::config::{ReloadableConfig, Subnet};
-use crate::manager::{EthSubnetManager, LotusSubnetManager, SubnetManager};
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::manager::{EthSubnetManager, LotusSubnetManager, NonceManager, SubnetManager};
+use crate::tracker::SubmissionSink;
 use ipc_identity::PersistentKeyStore;
 use ipc_identity::Wallet;
 use ipc_sdk::subnet_id::SubnetID;
@@ -35,6 +37,8 @@ pub struct SubnetManagerPool {
     config: Arc<ReloadableConfig>,
     fvm_wallet: Arc<RwLock<Wallet>>,
     evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    /// Assigns the nonces of the messages sent by the managers, shared with the checkpoint managers.
+    nonces: Arc<NonceManager>,
 }
 
 impl SubnetManagerPool {
@@ -42,40 +46,67 @@ impl SubnetManagerPool {
         reload_config: Arc<ReloadableConfig>,
         fvm_wallet: Arc<RwLock<Wallet>>,
         evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        nonces: Arc<NonceManager>,
     ) -> Self {
         Self {
             config: reload_config,
             fvm_wallet,
             evm_keystore,
+            nonces,
         }
     }
 
     /// Get the connection instance for the subnet.
     pub fn get(&self, subnet: &SubnetID) -> Option<Connection> {
+        self.connect(subnet, None)
+    }
+
+    /// Get a connection instance for the subnet whose manager reports the messages it submits to
+    /// `submissions`.
+    pub fn get_tracked(
+        &self,
+        subnet: &SubnetID,
+        submissions: SubmissionSink,
+    ) -> Option<Connection> {
+        self.connect(subnet, Some(submissions))
+    }
+
+    fn connect(
+        &self,
+        subnet: &SubnetID,
+        submissions: Option<SubmissionSink>,
+    ) -> Option<Connection> {
         let config = self.config.get_config();
         let subnets = &config.subnets;
         match subnets.get(subnet) {
             Some(subnet) => match &subnet.config {
                 SubnetConfig::Fvm(_) => {
-                    let manager = Box::new(LotusSubnetManager::from_subnet_with_wallet_store(
+                    let mut client = LotusJsonRPCClient::from_subnet_with_wallet_store(
                         subnet,
                         self.fvm_wallet.clone(),
-                    ));
+                    )
+                    .with_nonce_manager(self.nonces.clone());
+                    if let Some(submissions) = submissions {
+                        client = client.with_submission_sink(submissions);
+                    }
+                    let manager = Box::new(LotusSubnetManager::new(client, subnet.gateway_addr()));
                     Some(Connection {
                         manager,
                         subnet: subnet.clone(),
                     })
                 }
                 SubnetConfig::Fevm(_) => {
-                    let manager = Box::new(
-                        EthSubnetManager::from_subnet_with_wallet_store(
-                            subnet,
-                            self.evm_keystore.clone(),
-                        )
-                        .ok()?,
-                    );
+                    let mut manager = EthSubnetManager::from_subnet_with_wallet_store(
+                        subnet,
+                        self.evm_keystore.clone(),
+                    )
+                    .ok()?
+                    .with_nonce_manager(subnet.id.clone(), self.nonces.clone());
+                    if let Some(submissions) = submissions {
+                        manager = manager.with_submission_sink(submissions);
+                    }
                     Some(Connection {
-                        manager,
+                        manager: Box::new(manager),
                         subnet: subnet.clone(),
                     })
                 }
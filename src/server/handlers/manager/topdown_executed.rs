@@ -5,17 +5,18 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::clock::ChainEpoch;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::check_subnet;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LastTopDownExecParams {
     pub subnet_id: String,
 }
@@ -39,7 +40,7 @@ impl JsonRPCRequestHandler for LastTopDownExecHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let child_subnet_id = SubnetID::from_str(request.subnet_id.as_str())?;
         let conn = match self.pool.get(&child_subnet_id) {
-            None => return Err(anyhow!("target subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&child_subnet_id).into()),
             Some(conn) => conn,
         };
 
@@ -6,10 +6,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use async_trait::async_trait;
+use schemars::gen::SchemaGenerator;
 use serde_json::Value;
 
+pub use checkpoint::{
+    AuditBottomUpCheckpointsParams, CheckpointStatusParams, PreviewBottomUpCheckpointParams,
+};
 pub use config::ReloadConfigParams;
 use fvm_shared::econ::TokenAmount;
 use ipc_identity::PersistentKeyStore;
@@ -19,22 +23,35 @@ use manager::kill::KillSubnetHandler;
 use manager::leave::LeaveSubnetHandler;
 use manager::subnet::SubnetManagerPool;
 pub use manager::*;
+pub use transaction::{GetTransactionStatusParams, ListTransactionsParams, Tracked};
 
+use crate::checkpoint::CheckpointStatusRegistry;
 use crate::config::json_rpc_methods;
 use crate::config::ReloadableConfig;
+use crate::events::EventBus;
+use crate::manager::NonceManager;
+use crate::server::error::AgentError;
+use crate::server::handlers::checkpoint::{
+    AuditBottomUpCheckpointsHandler, CheckpointStatusHandler, PreviewBottomUpCheckpointHandler,
+};
 use crate::server::handlers::config::ReloadConfigHandler;
+use crate::server::handlers::discover::DiscoverHandler;
 use crate::server::handlers::manager::fund::FundHandler;
 use crate::server::handlers::manager::list_subnets::ListSubnetsHandler;
 use crate::server::handlers::manager::propagate::PropagateHandler;
 use crate::server::handlers::manager::release::ReleaseHandler;
 use crate::server::handlers::manager::whitelist::WhitelistPropagatorHandler;
 use crate::server::handlers::send_value::SendValueHandler;
+use crate::server::handlers::transaction::{GetTransactionStatusHandler, ListTransactionsHandler};
 use crate::server::handlers::validator::QueryValidatorSetHandler;
 use crate::server::handlers::wallet::balances::WalletBalancesHandler;
 use crate::server::handlers::wallet::new::WalletNewHandler;
 use crate::server::list_checkpoints::ListBottomUpCheckpointsHandler;
 use crate::server::net_addr::SetValidatorNetAddrHandler;
+use crate::server::openrpc::{self, MethodObject, OpenRpcDocument};
+use crate::server::ws;
 use crate::server::JsonRPCRequestHandler;
+use crate::tracker::TransactionTracker;
 use ipc_identity::Wallet;
 
 pub use self::config::{new_evm_keystore_from_config, new_evm_keystore_from_path};
@@ -45,8 +62,11 @@ use self::wallet::export::WalletExportHandler;
 use self::wallet::import::WalletImportHandler;
 use self::wallet::remove::WalletRemoveHandler;
 
+mod checkpoint;
 mod config;
+mod discover;
 mod manager;
+mod transaction;
 mod validator;
 pub mod wallet;
 
@@ -61,15 +81,26 @@ pub struct Handlers {
 /// A util trait to avoid Box<dyn> and associated type mess in Handlers struct
 #[async_trait]
 trait HandlerWrapper: Send + Sync {
-    async fn handle(&self, params: Value) -> Result<Value>;
+    async fn handle(&self, params: Value) -> Result<Value, AgentError>;
+
+    /// Describes the method of the handler in the OpenRPC document.
+    fn describe(&self, method: &str, gen: &mut SchemaGenerator) -> MethodObject;
 }
 
 #[async_trait]
 impl<H: JsonRPCRequestHandler + Send + Sync> HandlerWrapper for H {
-    async fn handle(&self, params: Value) -> Result<Value> {
-        let p = serde_json::from_value(params)?;
+    async fn handle(&self, params: Value) -> Result<Value, AgentError> {
+        let p = serde_json::from_value(params).map_err(|e| AgentError::InvalidParams {
+            reason: e.to_string(),
+        })?;
         let r = self.handle(p).await?;
-        Ok(serde_json::to_value(r)?)
+        serde_json::to_value(r).map_err(|e| AgentError::Internal {
+            message: e.to_string(),
+        })
+    }
+
+    fn describe(&self, method: &str, gen: &mut SchemaGenerator) -> MethodObject {
+        openrpc::method::<H::Request, H::Response>(method, gen)
     }
 }
 
@@ -87,37 +118,75 @@ impl Handlers {
         config: Arc<ReloadableConfig>,
         fvm_wallet: Arc<RwLock<Wallet>>,
         evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        checkpoint_status: Arc<CheckpointStatusRegistry>,
+        nonces: Arc<NonceManager>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
     ) -> Result<Self> {
         let mut handlers = HashMap::new();
 
         let h: Box<dyn HandlerWrapper> = Box::new(ReloadConfigHandler::new(config.clone()));
         handlers.insert(String::from(json_rpc_methods::RELOAD_CONFIG), h);
 
+        let h: Box<dyn HandlerWrapper> = Box::new(PreviewBottomUpCheckpointHandler::new(
+            config.clone(),
+            fvm_wallet.clone(),
+            evm_keystore.clone(),
+            nonces.clone(),
+        ));
+        handlers.insert(
+            String::from(json_rpc_methods::PREVIEW_BOTTOMUP_CHECKPOINT),
+            h,
+        );
+
+        let h: Box<dyn HandlerWrapper> =
+            Box::new(AuditBottomUpCheckpointsHandler::new(config.clone()));
+        handlers.insert(
+            String::from(json_rpc_methods::AUDIT_BOTTOMUP_CHECKPOINTS),
+            h,
+        );
+
         // subnet manager methods
         let pool = Arc::new(SubnetManagerPool::new(
             config,
             fvm_wallet.clone(),
             evm_keystore.clone(),
+            nonces,
+        ));
+        let h: Box<dyn HandlerWrapper> = Box::new(CreateSubnetHandler::new(
+            pool.clone(),
+            tracker.clone(),
+            events.clone(),
         ));
-        let h: Box<dyn HandlerWrapper> = Box::new(CreateSubnetHandler::new(pool.clone()));
         handlers.insert(String::from(json_rpc_methods::CREATE_SUBNET), h);
 
-        let h: Box<dyn HandlerWrapper> = Box::new(LeaveSubnetHandler::new(pool.clone()));
+        let h: Box<dyn HandlerWrapper> =
+            Box::new(LeaveSubnetHandler::new(pool.clone(), events.clone()));
         handlers.insert(String::from(json_rpc_methods::LEAVE_SUBNET), h);
 
-        let h: Box<dyn HandlerWrapper> = Box::new(KillSubnetHandler::new(pool.clone()));
+        let h: Box<dyn HandlerWrapper> =
+            Box::new(KillSubnetHandler::new(pool.clone(), events.clone()));
         handlers.insert(String::from(json_rpc_methods::KILL_SUBNET), h);
 
-        let h: Box<dyn HandlerWrapper> = Box::new(JoinSubnetHandler::new(pool.clone()));
+        let h: Box<dyn HandlerWrapper> = Box::new(JoinSubnetHandler::new(
+            pool.clone(),
+            tracker.clone(),
+            events.clone(),
+        ));
         handlers.insert(String::from(json_rpc_methods::JOIN_SUBNET), h);
 
         let h: Box<dyn HandlerWrapper> = Box::new(RPCSubnetHandler::new(pool.clone()));
         handlers.insert(String::from(json_rpc_methods::RPC_SUBNET), h);
 
-        let h: Box<dyn HandlerWrapper> = Box::new(FundHandler::new(pool.clone()));
+        let h: Box<dyn HandlerWrapper> = Box::new(FundHandler::new(
+            pool.clone(),
+            tracker.clone(),
+            events.clone(),
+        ));
         handlers.insert(String::from(json_rpc_methods::FUND), h);
 
-        let h: Box<dyn HandlerWrapper> = Box::new(ReleaseHandler::new(pool.clone()));
+        let h: Box<dyn HandlerWrapper> =
+            Box::new(ReleaseHandler::new(pool.clone(), tracker.clone(), events));
         handlers.insert(String::from(json_rpc_methods::RELEASE), h);
 
         let h: Box<dyn HandlerWrapper> = Box::new(PropagateHandler::new(pool.clone()));
@@ -147,14 +216,12 @@ impl Handlers {
         ));
         handlers.insert(String::from(json_rpc_methods::WALLET_IMPORT), h);
 
-        let _h: Box<dyn HandlerWrapper> = Box::new(WalletExportHandler::new(
+        // only callable with an admin token.
+        let h: Box<dyn HandlerWrapper> = Box::new(WalletExportHandler::new(
             fvm_wallet.clone(),
             evm_keystore.clone(),
         ));
-        // FIXME: For security reasons currently not exposing the ability to export wallet
-        // remotely through the RPC API, only directly through the CLI.
-        // We can consider re-enabling once we have RPC authentication in the agent.
-        // handlers.insert(String::from(json_rpc_methods::WALLET_EXPORT), h);
+        handlers.insert(String::from(json_rpc_methods::WALLET_EXPORT), h);
 
         let h: Box<dyn HandlerWrapper> = Box::new(WalletBalancesHandler::new(
             pool.clone(),
@@ -176,18 +243,58 @@ impl Handlers {
         let h: Box<dyn HandlerWrapper> = Box::new(LastTopDownExecHandler::new(pool.clone()));
         handlers.insert(String::from(json_rpc_methods::LAST_TOPDOWN_EXECUTED), h);
 
+        let h: Box<dyn HandlerWrapper> = Box::new(CheckpointStatusHandler::new(checkpoint_status));
+        handlers.insert(String::from(json_rpc_methods::CHECKPOINT_STATUS), h);
+
+        let h: Box<dyn HandlerWrapper> =
+            Box::new(GetTransactionStatusHandler::new(tracker.clone()));
+        handlers.insert(String::from(json_rpc_methods::GET_TRANSACTION_STATUS), h);
+
+        let h: Box<dyn HandlerWrapper> = Box::new(ListTransactionsHandler::new(tracker));
+        handlers.insert(String::from(json_rpc_methods::LIST_TRANSACTIONS), h);
+
         // query validator
         let h: Box<dyn HandlerWrapper> = Box::new(QueryValidatorSetHandler::new(pool));
         handlers.insert(String::from(json_rpc_methods::QUERY_VALIDATOR_SET), h);
 
-        Ok(Self { handlers })
+        // the document describes all the methods above, it has to be registered last.
+        let mut handlers = Self { handlers };
+        let h: Box<dyn HandlerWrapper> = Box::new(DiscoverHandler::new(handlers.document())?);
+        handlers
+            .handlers
+            .insert(String::from(json_rpc_methods::RPC_DISCOVER), h);
+
+        Ok(handlers)
     }
 
-    pub async fn handle(&self, method: Method, params: Value) -> Result<Value> {
+    /// The OpenRPC document of the methods of the handlers, along with `rpc.discover` and the
+    /// methods of the websocket endpoint.
+    pub fn document(&self) -> OpenRpcDocument {
+        let mut gen = openrpc::schema_generator();
+        let mut methods = self
+            .handlers
+            .iter()
+            .filter(|(method, _)| method.as_str() != json_rpc_methods::RPC_DISCOVER)
+            .map(|(method, h)| h.describe(method, &mut gen))
+            .collect::<Vec<_>>();
+        methods.push(openrpc::method::<(), Value>(
+            json_rpc_methods::RPC_DISCOVER,
+            &mut gen,
+        ));
+        methods.extend(ws::describe_methods(&mut gen));
+        OpenRpcDocument::new(methods, gen)
+    }
+
+    /// Returns true if there is a handler for the method.
+    pub fn has_method(&self, method: &str) -> bool {
+        self.handlers.contains_key(method)
+    }
+
+    pub async fn handle(&self, method: Method, params: Value) -> Result<Value, AgentError> {
         if let Some(wrapper) = self.handlers.get(&method) {
             wrapper.handle(params).await
         } else {
-            Err(anyhow!("method not supported"))
+            Err(AgentError::MethodNotFound { method })
         }
     }
 }
@@ -200,7 +307,8 @@ pub(crate) fn f64_to_token_amount(f: f64) -> anyhow::Result<TokenAmount> {
 
 #[cfg(test)]
 mod tests {
-    use crate::server::handlers::f64_to_token_amount;
+    use crate::config::json_rpc_methods;
+    use crate::server::handlers::{f64_to_token_amount, Handlers};
     use fvm_shared::econ::TokenAmount;
 
     #[test]
@@ -208,4 +316,15 @@ mod tests {
         let amount = f64_to_token_amount(1000000.1f64).unwrap();
         assert_eq!(amount, TokenAmount::from_nano(1000000100000000u128));
     }
+
+    #[test]
+    fn test_document_methods() {
+        let document = Handlers::empty_handlers().document();
+        let method = |name: &str| document.methods.iter().find(|m| m.name == name).unwrap();
+        assert!(method(json_rpc_methods::RPC_DISCOVER).description.is_none());
+        // the subscriptions are only served over websocket
+        assert!(method(json_rpc_methods::SUBSCRIBE).description.is_some());
+        assert!(method(json_rpc_methods::UNSUBSCRIBE).description.is_some());
+        assert!(document.components.schemas.contains_key("EventTopic"));
+    }
 }
@@ -0,0 +1,226 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Asynchronous submission of the subnet operations and query of their records
+
+use crate::server::JsonRPCRequestHandler;
+use crate::tracker::{TrackedTransaction, TransactionStatus, TransactionTracker};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use fvm_shared::address::Address;
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+
+/// The number of records listed if no limit is set.
+const DEFAULT_LIST_TRANSACTIONS_LIMIT: usize = 100;
+
+/// The response of a method that can be called asynchronously: the record of the operation if
+/// called asynchronously, the usual response of the method otherwise.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Tracked<T> {
+    Submitted(TrackedTransaction),
+    Completed(T),
+}
+
+/// Runs the operation in the background, recording the messages it submits as they are reported
+/// to `submissions` and its outcome once it completes. Returns the record of the operation as soon
+/// as its first message is submitted. An operation failing before submitting any message returns
+/// its error instead.
+pub(crate) async fn submit_tracked<T, Fut>(
+    tracker: Arc<TransactionTracker>,
+    method: &str,
+    subnet: &SubnetID,
+    from: &Address,
+    mut submissions: UnboundedReceiver<String>,
+    op: Fut,
+) -> anyhow::Result<TrackedTransaction>
+where
+    T: Serialize + Send + 'static,
+    Fut: Future<Output = anyhow::Result<T>> + Send + 'static,
+{
+    let id = tracker.track(method, subnet, from)?;
+
+    let (submitted, first_submission) = oneshot::channel();
+    let background = tracker.clone();
+    tokio::spawn(async move {
+        let mut submitted = Some(submitted);
+        tokio::pin!(op);
+
+        let outcome = loop {
+            tokio::select! {
+                biased;
+                Some(submission) = submissions.recv() => {
+                    log::info!("transaction {id:} submitted {submission:}");
+                    if let Err(e) = background.add_submission(id, submission) {
+                        log::error!("cannot record the submission of transaction {id:}: {e:}");
+                    }
+                    if let Some(submitted) = submitted.take() {
+                        let _ = submitted.send(());
+                    }
+                }
+                outcome = &mut op => break outcome,
+            }
+        };
+        // the messages submitted right before the operation completed.
+        while let Ok(submission) = submissions.try_recv() {
+            if let Err(e) = background.add_submission(id, submission) {
+                log::error!("cannot record the submission of transaction {id:}: {e:}");
+            }
+        }
+
+        let outcome = outcome.and_then(|r| Ok(serde_json::to_value(r)?));
+        if let Err(e) = &outcome {
+            log::error!("transaction {id:} failed: {e:}");
+        }
+        if let Err(e) = background.finish(id, outcome) {
+            log::error!("cannot record the outcome of transaction {id:}: {e:}");
+        }
+    });
+
+    // the sender is dropped without sending once the operation completed without submitting.
+    let _ = first_submission.await;
+
+    let transaction = tracker
+        .get(id)
+        .ok_or_else(|| anyhow!("transaction {id:} not tracked"))?;
+    if transaction.status == TransactionStatus::Failed && transaction.submissions.is_empty() {
+        return Err(anyhow!(transaction.error.unwrap_or_default()));
+    }
+    Ok(transaction)
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GetTransactionStatusParams {
+    /// The tracking id returned by the asynchronous call.
+    pub id: u64,
+}
+
+/// The get transaction status json rpc method handler.
+pub(crate) struct GetTransactionStatusHandler {
+    tracker: Arc<TransactionTracker>,
+}
+
+impl GetTransactionStatusHandler {
+    pub(crate) fn new(tracker: Arc<TransactionTracker>) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for GetTransactionStatusHandler {
+    type Request = GetTransactionStatusParams;
+    type Response = TrackedTransaction;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        self.tracker
+            .get(request.id)
+            .ok_or_else(|| anyhow!("transaction {:} not found", request.id))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ListTransactionsParams {
+    /// Only return the transactions submitted to this subnet.
+    pub subnet: Option<String>,
+    /// Only return the transactions with this status.
+    pub status: Option<TransactionStatus>,
+    /// The maximum number of transactions returned, the most recent first.
+    pub limit: Option<usize>,
+}
+
+/// The list transactions json rpc method handler.
+pub(crate) struct ListTransactionsHandler {
+    tracker: Arc<TransactionTracker>,
+}
+
+impl ListTransactionsHandler {
+    pub(crate) fn new(tracker: Arc<TransactionTracker>) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for ListTransactionsHandler {
+    type Request = ListTransactionsParams;
+    type Response = Vec<TrackedTransaction>;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let subnet = request.subnet.map(|s| SubnetID::from_str(&s)).transpose()?;
+        Ok(self.tracker.list(
+            subnet.as_ref(),
+            request.status,
+            request.limit.unwrap_or(DEFAULT_LIST_TRANSACTIONS_LIMIT),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::handlers::transaction::{submit_tracked, Tracked};
+    use crate::tracker::{TransactionStatus, TransactionTracker, TRANSACTIONS_FILE_NAME};
+    use anyhow::anyhow;
+    use fvm_shared::address::Address;
+    use fvm_shared::clock::ChainEpoch;
+    use ipc_sdk::subnet_id::SubnetID;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    #[tokio::test]
+    async fn test_submit_tracked() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker =
+            Arc::new(TransactionTracker::load(dir.path().join(TRANSACTIONS_FILE_NAME)).unwrap());
+        let subnet = SubnetID::new_root(123);
+        let from = Address::new_id(100);
+
+        // returns once the message is submitted, while the operation waits for its inclusion
+        let (sink, submissions) = mpsc::unbounded_channel();
+        let (included, inclusion) = oneshot::channel::<ChainEpoch>();
+        let op = async move {
+            sink.send(String::from("cid")).unwrap();
+            Ok::<_, anyhow::Error>(inclusion.await?)
+        };
+        let t = submit_tracked(tracker.clone(), "ipc_fund", &subnet, &from, submissions, op)
+            .await
+            .unwrap();
+        assert_eq!(t.status, TransactionStatus::Pending);
+        assert_eq!(t.submissions, vec![String::from("cid")]);
+
+        included.send(10).unwrap();
+        for _ in 0..100 {
+            if tracker.get(t.id).unwrap().status != TransactionStatus::Pending {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let t = tracker.get(t.id).unwrap();
+        assert_eq!(t.status, TransactionStatus::Completed);
+        assert_eq!(t.result, Some(serde_json::json!(10)));
+
+        // fails before submitting any message
+        let (_sink, submissions) = mpsc::unbounded_channel();
+        let op = async move { Err::<(), _>(anyhow!("no key for the account")) };
+        assert!(
+            submit_tracked(tracker.clone(), "ipc_fund", &subnet, &from, submissions, op)
+                .await
+                .is_err()
+        );
+        let listed = tracker.list(None, Some(TransactionStatus::Failed), 10);
+        assert_eq!(listed.len(), 1);
+    }
+
+    #[test]
+    fn test_tracked_response() {
+        let r: Tracked<ChainEpoch> = serde_json::from_value(serde_json::json!(10)).unwrap();
+        assert!(matches!(r, Tracked::Completed(10)));
+        let r: Tracked<()> = serde_json::from_value(serde_json::Value::Null).unwrap();
+        assert!(matches!(r, Tracked::Completed(())));
+    }
+}
@@ -3,16 +3,18 @@
 //! Expose the subnet actor validator set
 
 use crate::lotus::message::ipc::QueryValidatorSetResponse;
+use crate::server::error::AgentError;
 use crate::server::subnet::SubnetManagerPool;
 use crate::server::{check_subnet, JsonRPCRequestHandler};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct QueryValidatorSetParams {
     pub subnet: String,
 }
@@ -40,7 +42,7 @@ impl JsonRPCRequestHandler for QueryValidatorSetHandler {
             .ok_or_else(|| anyhow!("cannot get for root"))?;
 
         let conn = match self.pool.get(&parent) {
-            None => return Err(anyhow!("target subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&parent).into()),
             Some(conn) => conn,
         };
 
@@ -4,9 +4,9 @@
 use crate::config::subnet::SubnetConfig;
 use crate::manager::evm::ethers_address_to_fil_address;
 use crate::manager::SubnetManager;
+use crate::server::error::AgentError;
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use futures_util::future::join_all;
 use fvm_shared::address::Address;
@@ -14,12 +14,13 @@ use fvm_shared::econ::TokenAmount;
 use ipc_identity::EvmKeyStore;
 use ipc_identity::{PersistentKeyStore, Wallet};
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBalancesParams {
     pub subnet: String,
 }
@@ -114,7 +115,7 @@ impl JsonRPCRequestHandler for WalletBalancesHandler {
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
         let conn = match self.pool.get(&subnet) {
-            None => return Err(anyhow!("target subnet not found")),
+            None => return Err(AgentError::subnet_not_configured(&subnet).into()),
             Some(conn) => conn,
         };
         let manager = conn.manager();
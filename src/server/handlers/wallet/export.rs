@@ -2,17 +2,18 @@
 // SPDX-License-Identifier: MIT
 //! wallet handlers and parameters
 
+use crate::server::error::AgentError;
 use crate::server::JsonRPCRequestHandler;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use ipc_identity::json::KeyInfoJson;
 use ipc_identity::{EvmKeyStore, PersistentKeyStore, Wallet};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "network_type")]
 pub enum WalletExportParams {
     #[serde(rename = "fvm")]
@@ -31,11 +32,11 @@ pub struct EvmExportParams {
     pub address: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "network_type")]
 pub enum WalletExportResponse {
     #[serde(rename = "fvm")]
-    Fvm(KeyInfoJson),
+    Fvm(#[schemars(with = "serde_json::Value")] KeyInfoJson),
     #[serde(rename = "evm")]
     Evm { private_key: String },
 }
@@ -71,7 +72,7 @@ impl WalletExportHandler {
 
         let key_info = keystore
             .get(&address)?
-            .ok_or_else(|| anyhow!("key does not exists"))?;
+            .ok_or_else(|| AgentError::key_not_found(format!("{address:?}")))?;
         Ok(WalletExportResponse::Evm {
             private_key: hex::encode(key_info.private_key()),
         })
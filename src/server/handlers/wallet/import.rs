@@ -8,11 +8,12 @@ use base64::Engine;
 use fvm_shared::crypto::signature::SignatureType;
 use ipc_identity::json::KeyInfoJson;
 use ipc_identity::{EvmKeyInfo, EvmKeyStore, KeyInfo, PersistentKeyStore, Wallet};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
 use zeroize::Zeroize;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 #[serde(tag = "network_type")]
 pub enum WalletImportParams {
     #[serde(rename = "fvm")]
@@ -21,14 +22,14 @@ pub enum WalletImportParams {
     Evm(EvmImportParams),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct FvmImportParams {
     pub key_type: u8,
     /// Base64 encoded private key string
     pub private_key: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct EvmImportParams {
     /// Hex encoded private key string
     pub private_key: String,
@@ -47,7 +48,7 @@ impl Drop for WalletImportParams {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct WalletImportResponse {
     pub address: String,
 }
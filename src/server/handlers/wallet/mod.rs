@@ -3,6 +3,7 @@
 use std::str::FromStr;
 
 use anyhow::anyhow;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 // Copyright 2022-2023 Protocol Labs
@@ -14,7 +15,7 @@ pub mod new;
 pub mod remove;
 
 /// The wallet type, i.e. for which network
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "network_type")]
 pub enum WalletType {
     Evm,
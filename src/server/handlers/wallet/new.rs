@@ -9,11 +9,12 @@ use async_trait::async_trait;
 use ethers::types::Address;
 use fvm_shared::crypto::signature::SignatureType;
 use ipc_identity::{random_key_info, EvmKeyStore, PersistentKeyStore, Wallet};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "network_type")]
 pub enum WalletNewParams {
     #[serde(rename = "fvm")]
@@ -22,12 +23,12 @@ pub enum WalletNewParams {
     Evm,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct NewFvmWallet {
     pub key_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct WalletNewResponse {
     pub address: String,
 }
@@ -6,6 +6,7 @@ use crate::server::JsonRPCRequestHandler;
 use async_trait::async_trait;
 use ethers::types::Address;
 use ipc_identity::{EvmKeyStore, PersistentKeyStore, Wallet};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
     str::FromStr,
@@ -14,7 +15,7 @@ use std::{
 
 use super::WalletType;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct WalletRemoveParams {
     pub wallet_type: WalletType,
     pub address: String,
@@ -2,30 +2,46 @@
 // SPDX-License-Identifier: MIT
 use std::sync::{Arc, RwLock};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::future::join_all;
 use ipc_identity::PersistentKeyStore;
 use ipc_identity::Wallet;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
 use tokio::sync::Notify;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 use warp::http::StatusCode;
-use warp::reject::Reject;
 use warp::reply::with_status;
 use warp::{Filter, Rejection, Reply};
 
+use crate::checkpoint::CheckpointStatusRegistry;
 use crate::config::JSON_RPC_VERSION;
-use crate::config::{ReloadableConfig, JSON_RPC_ENDPOINT};
-use crate::server::request::JSONRPCRequest;
-use crate::server::response::{JSONRPCError, JSONRPCErrorResponse, JSONRPCResultResponse};
+use crate::config::{ReloadableConfig, JSON_RPC_ENDPOINT, METRICS_ENDPOINT};
+use crate::events::EventBus;
+use crate::manager::NonceManager;
+use crate::metrics;
+use crate::server::auth::{JwtAuth, Permission, RequestAuth};
+use crate::server::error::AgentError;
+use crate::server::request::{JSONRPCId, JSONRPCRequest};
+use crate::server::response::{JSONRPCErrorResponse, JSONRPCResultResponse};
+use crate::server::ws::ws_filter;
 use crate::server::Handlers;
+use crate::tracker::TransactionTracker;
 
-type ArcHandlers = Arc<Handlers>;
+pub(crate) type ArcHandlers = Arc<Handlers>;
+pub(crate) type ArcAuth = Arc<RequestAuth>;
 
 /// The IPC JSON RPC node that contains all the methods and handlers. The underlying implementation
 /// is using `warp`.
 ///
-/// Note that currently only http json rpc is supported.
+/// The json rpc api is served over http and websocket, over tls if the `[server.tls]` section of
+/// the config is set, and on the unix socket of the `[server.unix_socket]` section if any. The
+/// websocket clients can also subscribe to the events published to the event bus, see
+/// [`crate::server::ws`]. The requests are authorized with the tokens signed with the jwt secret in
+/// the agent repo, see [`crate::server::auth`].
 ///
 /// # Examples
 /// ```no_run
@@ -34,9 +50,13 @@ type ArcHandlers = Arc<Handlers>;
 ///
 /// use tokio_graceful_shutdown::{IntoSubsystem, Toplevel};
 ///
+/// use ipc_agent::checkpoint::CheckpointStatusRegistry;
 /// use ipc_agent::config::ReloadableConfig;
+/// use ipc_agent::events::EventBus;
+/// use ipc_agent::manager::NonceManager;
 /// use ipc_agent::server::jsonrpc::JsonRPCServer;
 /// use ipc_agent::server::{new_evm_keystore_from_config, new_fvm_wallet_from_config};
+/// use ipc_agent::tracker::TransactionTracker;
 /// use ipc_identity::Wallet;
 ///
 /// #[tokio::main]
@@ -45,7 +65,11 @@ type ArcHandlers = Arc<Handlers>;
 ///     let config = Arc::new(ReloadableConfig::new(path.to_string()).unwrap());
 ///     let fvm_wallet = Arc::new(RwLock::new(Wallet::new(new_fvm_wallet_from_config(config.clone()).unwrap())));
 ///     let evm_keystore = Arc::new(RwLock::new((new_evm_keystore_from_config(config.clone()).unwrap()));
-///     let server = JsonRPCServer::new(config, fvm_wallet, evm_keystore);
+///     let checkpoint_status = Arc::new(CheckpointStatusRegistry::new());
+///     let nonces = Arc::new(NonceManager::new());
+///     let tracker = Arc::new(TransactionTracker::from_config(&config).unwrap());
+///     let events = Arc::new(EventBus::new());
+///     let server = JsonRPCServer::new(config, fvm_wallet, evm_keystore, checkpoint_status, nonces, tracker, events);
 ///     Toplevel::new()
 ///         .start("JSON-RPC server subsystem", server.into_subsystem())
 ///         .catch_signals()
@@ -58,6 +82,10 @@ pub struct JsonRPCServer {
     config: Arc<ReloadableConfig>,
     fvm_wallet: Arc<RwLock<Wallet>>,
     evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+    checkpoint_status: Arc<CheckpointStatusRegistry>,
+    nonces: Arc<NonceManager>,
+    tracker: Arc<TransactionTracker>,
+    events: Arc<EventBus>,
 }
 
 impl JsonRPCServer {
@@ -65,11 +93,19 @@ impl JsonRPCServer {
         config: Arc<ReloadableConfig>,
         fvm_wallet: Arc<RwLock<Wallet>>,
         evm_keystore: Arc<RwLock<PersistentKeyStore<ethers::types::Address>>>,
+        checkpoint_status: Arc<CheckpointStatusRegistry>,
+        nonces: Arc<NonceManager>,
+        tracker: Arc<TransactionTracker>,
+        events: Arc<EventBus>,
     ) -> Self {
         Self {
             config,
             fvm_wallet,
             evm_keystore,
+            checkpoint_status,
+            nonces,
+            tracker,
+            events,
         }
     }
 }
@@ -78,10 +114,12 @@ impl JsonRPCServer {
 impl IntoSubsystem<anyhow::Error> for JsonRPCServer {
     /// Runs the JSON-RPC server as a subsystem.
     async fn run(self, subsys: SubsystemHandle) -> Result<()> {
-        log::info!(
-            "IPC agent rpc node listening at {:?}",
-            self.config.get_config().server.json_rpc_address
-        );
+        let server_config = self.config.get_config().server.clone();
+        if server_config.json_rpc_address.is_none() && server_config.unix_socket.is_none() {
+            return Err(anyhow!(
+                "no json rpc address or unix socket in the server config"
+            ));
+        }
 
         // For notifying the server to gracefully shutdown.
         let notify_send = Arc::new(Notify::new());
@@ -92,18 +130,98 @@ impl IntoSubsystem<anyhow::Error> for JsonRPCServer {
             self.config.clone(),
             self.fvm_wallet.clone(),
             self.evm_keystore.clone(),
+            self.checkpoint_status.clone(),
+            self.nonces.clone(),
+            self.tracker.clone(),
+            self.events.clone(),
         )?);
-        let (_, server) = warp::serve(json_rpc_filter(handlers)).bind_with_graceful_shutdown(
-            self.config.get_config().server.json_rpc_address,
-            async move { notify_recv.notified().await },
-        );
-        let server_handle = tokio::spawn(server);
+        let auth = Arc::new(RequestAuth::new(
+            JwtAuth::from_config(&self.config)?,
+            server_config.require_auth,
+        ));
+        if !server_config.require_auth {
+            log::warn!(
+                "the json rpc requests without a token are accepted with the read permission, \
+                set `require_auth` in the server config to reject them"
+            );
+        }
+        // the json rpc filter replies to all the requests it rejects, it has to come last.
+        let routes = metrics_filter(auth.clone())
+            .or(ws_filter(
+                handlers.clone(),
+                auth.clone(),
+                self.events.clone(),
+            ))
+            .or(json_rpc_filter(handlers, auth));
+        let shutdown = || {
+            let notify_recv = notify_recv.clone();
+            async move { notify_recv.notified().await }
+        };
+        let mut server_handles = vec![];
+        if let Some(address) = server_config.json_rpc_address {
+            log::info!("IPC agent rpc node listening at {address:}");
+            let server_handle = match &server_config.tls {
+                Some(tls) => {
+                    // warp panics on unreadable files, report them as errors instead.
+                    for path in [
+                        Some(&tls.cert_path),
+                        Some(&tls.key_path),
+                        tls.client_ca_path.as_ref(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    {
+                        std::fs::metadata(path)
+                            .map_err(|e| anyhow!("cannot read tls file {path:}: {e:}"))?;
+                    }
+
+                    let mut server = warp::serve(routes)
+                        .tls()
+                        .cert_path(&tls.cert_path)
+                        .key_path(&tls.key_path);
+                    if let Some(client_ca_path) = &tls.client_ca_path {
+                        server = server.client_auth_required_path(client_ca_path);
+                    }
+                    let (_, server) = server.bind_with_graceful_shutdown(address, shutdown());
+                    tokio::spawn(server)
+                }
+                None => {
+                    let (_, server) =
+                        warp::serve(routes).bind_with_graceful_shutdown(address, shutdown());
+                    tokio::spawn(server)
+                }
+            };
+            server_handles.push(server_handle);
+        }
+        if let Some(unix_socket) = &server_config.unix_socket {
+            #[cfg(unix)]
+            {
+                // the clients cannot reach relative paths, reject them before binding.
+                let url = unix_socket.url()?;
+                let incoming = bind_unix_socket(&unix_socket.path, unix_socket.permissions)?;
+                log::info!("IPC agent rpc node listening at {url:}");
+                let server = warp::serve(routes.clone())
+                    .serve_incoming_with_graceful_shutdown(incoming, shutdown());
+                server_handles.push(tokio::spawn(server));
+            }
+            #[cfg(not(unix))]
+            return Err(anyhow!(
+                "unix sockets are not supported on this platform: {:}",
+                unix_socket.path
+            ));
+        }
 
         // Wait for the shutdown signal and gracefully shutdown.
         subsys.on_shutdown_requested().await;
         log::info!("Shutting down IPC agent rpc node");
         notify_send.notify_waiters();
-        server_handle.await?;
+        for server_handle in server_handles {
+            server_handle.await?;
+        }
+        #[cfg(unix)]
+        if let Some(unix_socket) = &server_config.unix_socket {
+            let _ = std::fs::remove_file(&unix_socket.path);
+        }
 
         Ok(())
     }
@@ -113,40 +231,199 @@ impl IntoSubsystem<anyhow::Error> for JsonRPCServer {
 
 /// Create the json_rpc filter. The filter does the following:
 /// - Listen to POST requests on the DEFAULT_JSON_RPC_ENDPOINT
-/// - Extract the body of the request.
-/// - Pass it to to the json_rpc_filter to deserialize into a jsonrpc request.
+/// - Extract the body of the request, a single jsonrpc request or a batch of them.
+/// - Extract the `authorization` header of the request to authorize it.
+/// - Pass it to `handle_body` to handle the requests and reply with their responses.
 fn json_rpc_filter(
     handlers: ArcHandlers,
+    auth: ArcAuth,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     warp::post()
         .and(warp::path(JSON_RPC_ENDPOINT))
         .and(warp::body::bytes())
-        .and_then(to_json_rpc_request)
+        .and(warp::header::optional::<String>("authorization"))
         .and(with_handlers(handlers))
-        .and_then(handle_request)
+        .and(with_auth(auth))
+        .and_then(handle_body)
         .recover(handle_rejection)
 }
 
-fn with_handlers(
+/// Binds the unix socket of the server with the permissions. The socket is bound in a private
+/// directory next to `path` and only moved to `path` once its permissions are set, so that it is
+/// never reachable with the permissions of the umask. A stale socket left by a previous run of the
+/// agent is replaced, but not a socket another process is still listening on.
+#[cfg(unix)]
+fn bind_unix_socket(
+    path: &str,
+    permissions: u32,
+) -> Result<tokio_stream::wrappers::UnixListenerStream> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::path::Path;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("cannot bind unix socket {path:}: not a socket"));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(anyhow!("cannot bind unix socket {path:}: already in use"));
+        }
+    }
+
+    let target = Path::new(path);
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| anyhow!("cannot bind unix socket {path:}: no file name"))?;
+    let private_dir = target.with_file_name(format!(
+        ".{:}.{:}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    if std::fs::symlink_metadata(&private_dir).is_ok() {
+        std::fs::remove_dir_all(&private_dir)?;
+    }
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)
+        .map_err(|e| anyhow!("cannot create the directory of unix socket {path:}: {e:}"))?;
+
+    let bind = || -> Result<tokio::net::UnixListener> {
+        let private_path = private_dir.join(file_name);
+        let listener = tokio::net::UnixListener::bind(&private_path)
+            .map_err(|e| anyhow!("cannot bind unix socket {path:}: {e:}"))?;
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(permissions))?;
+        // the rename replaces the stale socket, if any.
+        std::fs::rename(&private_path, target)?;
+        Ok(listener)
+    };
+    let listener = bind();
+    std::fs::remove_dir_all(&private_dir)?;
+    Ok(tokio_stream::wrappers::UnixListenerStream::new(listener?))
+}
+
+/// Create the metrics filter, serving the prometheus metrics of the agent on GET requests to
+/// `/metrics`. The requests need the read permission, like the json rpc methods.
+fn metrics_filter(
+    auth: ArcAuth,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path(METRICS_ENDPOINT))
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_auth(auth))
+        .and_then(handle_metrics)
+}
+
+async fn handle_metrics(
+    authorization: Option<String>,
+    auth: ArcAuth,
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = auth.authorize_permission(authorization.as_deref(), Permission::Read) {
+        log::debug!("unauthorized metrics request: {e:}");
+        return Ok(with_status(e.to_string(), StatusCode::UNAUTHORIZED));
+    }
+
+    match metrics::encode() {
+        Ok(text) => Ok(with_status(text, StatusCode::OK)),
+        Err(e) => {
+            log::error!("cannot encode metrics: {e:}");
+            Ok(with_status(
+                String::from("INTERNAL_SERVER_ERROR"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+pub(crate) fn with_handlers(
     handlers: ArcHandlers,
 ) -> impl Filter<Extract = (ArcHandlers,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || handlers.clone())
 }
 
-// Filter that deserializes the body of the request into a jsonrpc request.
-async fn to_json_rpc_request(bytes: Bytes) -> Result<JSONRPCRequest, warp::Rejection> {
-    serde_json::from_slice::<JSONRPCRequest>(bytes.as_ref()).map_err(|e| {
-        log::debug!("cannot deserialize {bytes:?} due to {e:?}");
-        warp::reject::custom(InvalidParameter)
-    })
+pub(crate) fn with_auth(
+    auth: ArcAuth,
+) -> impl Filter<Extract = (ArcAuth,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || auth.clone())
 }
 
-/// Main function responsible for handling and routing jsonrpc requests to the right underlying handler according to the method
-async fn handle_request(
-    json_rpc_request: JSONRPCRequest,
+/// Handles the body of a json rpc call and replies with its responses.
+async fn handle_body(
+    bytes: Bytes,
+    authorization: Option<String>,
     handlers: ArcHandlers,
-) -> Result<impl Reply, warp::Rejection> {
-    log::debug!("received json rpc request = {:?}", json_rpc_request);
+    auth: ArcAuth,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let authorization = authorization.as_deref();
+    let reply = handle_payload(bytes.as_ref(), |method, params| {
+        handle_method(method, params, authorization, &handlers, &auth)
+    })
+    .await;
+    Ok(json_reply(reply))
+}
+
+/// Handles a json rpc payload: a single request, or a batch of requests handled concurrently.
+/// The methods are called with `handle_method`. Following the spec, the payload gets no response
+/// if it only carries notifications.
+pub(crate) async fn handle_payload<F, Fut>(bytes: &[u8], handle_method: F) -> Option<Value>
+where
+    F: Fn(String, Value) -> Fut,
+    Fut: Future<Output = Result<Value, AgentError>>,
+{
+    let body = match serde_json::from_slice::<Value>(bytes) {
+        Ok(body) => body,
+        Err(e) => {
+            log::debug!("cannot deserialize {bytes:?} due to {e:?}");
+            return Some(to_value(JSONRPCErrorResponse::parse_error()));
+        }
+    };
+
+    match body {
+        Value::Array(requests) if requests.is_empty() => Some(to_value(
+            JSONRPCErrorResponse::invalid_request(JSONRPCId::Null),
+        )),
+        Value::Array(requests) => {
+            let responses = join_all(
+                requests
+                    .into_iter()
+                    .map(|r| handle_request(r, &handle_method)),
+            )
+            .await
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+            if responses.is_empty() {
+                None
+            } else {
+                Some(Value::Array(responses))
+            }
+        }
+        request => handle_request(request, &handle_method).await,
+    }
+}
+
+/// Main function responsible for handling and routing jsonrpc requests to the right underlying
+/// handler according to the method. Returns the response to the request, `None` for a
+/// notification.
+async fn handle_request<F, Fut>(request: Value, handle_method: &F) -> Option<Value>
+where
+    F: Fn(String, Value) -> Fut,
+    Fut: Future<Output = Result<Value, AgentError>>,
+{
+    log::debug!("received json rpc request = {:?}", request);
+
+    let json_rpc_request = match serde_json::from_value::<JSONRPCRequest>(request.clone()) {
+        Ok(r) => r,
+        Err(e) => {
+            log::debug!("invalid json rpc request {request:?} due to {e:?}");
+            // echo the id if it is valid, null otherwise
+            let id = request
+                .get("id")
+                .and_then(|id| serde_json::from_value::<JSONRPCId>(id.clone()).ok())
+                .unwrap_or(JSONRPCId::Null);
+            return Some(to_value(JSONRPCErrorResponse::invalid_request(id)));
+        }
+    };
 
     let JSONRPCRequest {
         id,
@@ -156,36 +433,73 @@ async fn handle_request(
     } = json_rpc_request;
 
     if jsonrpc != JSON_RPC_VERSION {
-        return Ok(warp::reply::json(&JSONRPCErrorResponse::invalid_request(
-            id,
+        return Some(to_value(JSONRPCErrorResponse::invalid_request(
+            id.unwrap_or(JSONRPCId::Null),
         )));
     }
 
     log::debug!("received method = {method:?} and params = {params:?}");
-    match handlers.handle(method, params).await {
-        Ok(response) => Ok(warp::reply::json(&JSONRPCResultResponse::new(id, response))),
-        Err(e) => {
-            let error: JSONRPCError<()> = JSONRPCError {
-                code: -1,
-                data: None,
-                message: e.to_string(),
-            };
-            Ok(warp::reply::json(&JSONRPCErrorResponse::new(id, error)))
-        }
+
+    let result = handle_method(method, params).await;
+    // the notifications are handled all the same, but get no response.
+    let id = id?;
+    let response = match result {
+        Ok(response) => to_value(JSONRPCResultResponse::new(id, response)),
+        Err(error) => to_value(JSONRPCErrorResponse::new(id, error.to_json_rpc_error())),
+    };
+    Some(response)
+}
+
+/// Authorizes the call of the method and routes it to its handler.
+pub(crate) async fn handle_method(
+    method: String,
+    params: Value,
+    authorization: Option<&str>,
+    handlers: &Handlers,
+    auth: &RequestAuth,
+) -> Result<Value, AgentError> {
+    if let Err(e) = auth.authorize(authorization, &method) {
+        log::debug!("rejected method = {method:?}: {e:}");
+        return Err(e);
+    }
+
+    // unsupported methods share a label to keep the number of series bounded
+    let label = if handlers.has_method(&method) {
+        method.clone()
+    } else {
+        String::from("unsupported")
+    };
+    metrics::JSON_RPC_REQUESTS
+        .with_label_values(&[&label])
+        .inc();
+    let timer = metrics::JSON_RPC_LATENCY
+        .with_label_values(&[&label])
+        .start_timer();
+    let result = handlers.handle(method, params).await;
+    timer.observe_duration();
+
+    if result.is_err() {
+        metrics::JSON_RPC_ERRORS.with_label_values(&[&label]).inc();
     }
+    result
 }
 
-/// The invalid parameter warp rejection error handling
-#[derive(Debug)]
-struct InvalidParameter;
+/// Replies with the json response, or with no content if there is none.
+fn json_reply(response: Option<Value>) -> warp::reply::Response {
+    match response {
+        Some(response) => warp::reply::json(&response).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
 
-impl Reject for InvalidParameter {}
+pub(crate) fn to_value<T: Serialize>(response: T) -> Value {
+    // the responses only hold json values, strings and numbers.
+    serde_json::to_value(response).expect("json rpc responses are serializable")
+}
 
 async fn handle_rejection(err: Rejection) -> Result<impl Reply, warp::Rejection> {
     if err.is_not_found() {
         Ok(with_status("NOT_FOUND", StatusCode::NOT_FOUND))
-    } else if err.find::<InvalidParameter>().is_some() {
-        Ok(with_status("BAD_REQUEST", StatusCode::BAD_REQUEST))
     } else {
         log::error!("unhandled rejection: {:?}", err);
         Ok(with_status(
@@ -199,28 +513,40 @@ async fn handle_rejection(err: Rejection) -> Result<impl Reply, warp::Rejection>
 mod tests {
     use std::sync::Arc;
 
+    use serde_json::Value;
     use warp::http::StatusCode;
 
     use crate::config::{JSON_RPC_ENDPOINT, JSON_RPC_VERSION};
-    use crate::server::jsonrpc::{json_rpc_filter, ArcHandlers, JSONRPCResultResponse};
-    use crate::server::request::JSONRPCRequest;
+    use crate::server::auth::{JwtAuth, Permission, RequestAuth};
+    use crate::server::error::UNAUTHORIZED_CODE;
+    use crate::server::jsonrpc::{
+        json_rpc_filter, metrics_filter, ArcAuth, ArcHandlers, JSONRPCResultResponse,
+    };
+    use crate::server::request::{JSONRPCId, JSONRPCRequest};
+    use crate::server::response::{
+        JSONRPCErrorResponse, INVALID_REQUEST_CODE, METHOD_NOT_FOUND_CODE, PARSE_ERROR_CODE,
+    };
     use crate::server::Handlers;
 
     fn get_empty_handlers() -> ArcHandlers {
         Arc::new(Handlers::empty_handlers())
     }
 
+    fn get_auth(require_auth: bool) -> ArcAuth {
+        Arc::new(RequestAuth::new(JwtAuth::new(vec![0; 32]), require_auth))
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_json_rpc_filter_works() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(false));
 
         let foo = "foo".to_string();
         let jsonrpc = String::from(JSON_RPC_VERSION);
-        let id = 0;
+        let id = JSONRPCId::from(0);
 
         let req = JSONRPCRequest {
-            id,
+            id: Some(id.clone()),
             jsonrpc: jsonrpc.clone(),
             method: foo.clone(),
             params: Default::default(),
@@ -242,7 +568,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_json_rpc_filter_cannot_parse_param() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(false));
 
         let value = warp::test::request()
             .method("POST")
@@ -251,12 +577,215 @@ mod tests {
             .reply(&filter)
             .await;
 
-        assert_eq!(StatusCode::BAD_REQUEST, value.status());
+        assert_eq!(StatusCode::OK, value.status());
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert_eq!(v.id, JSONRPCId::Null);
+        assert_eq!(v.error.code, INVALID_REQUEST_CODE);
+
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .body("{\"jsonrpc\": \"2.0\", \"method\"")
+            .reply(&filter)
+            .await;
+
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert_eq!(v.id, JSONRPCId::Null);
+        assert_eq!(v.error.code, PARSE_ERROR_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_filter_batch() {
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(false));
+
+        let batch = serde_json::json!([
+            {"jsonrpc": JSON_RPC_VERSION, "method": "foo", "id": "a"},
+            {"jsonrpc": JSON_RPC_VERSION, "method": "foo", "id": null},
+            {"jsonrpc": JSON_RPC_VERSION, "method": "foo"},
+            {"jsonrpc": "1.0", "method": "foo", "id": 3},
+            1,
+        ]);
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .json(&batch)
+            .reply(&filter)
+            .await;
+
+        // every request but the notification gets a response
+        let v = serde_json::from_slice::<Vec<JSONRPCErrorResponse<Value>>>(value.body()).unwrap();
+        assert_eq!(
+            v.iter().map(|r| r.id.clone()).collect::<Vec<_>>(),
+            vec![
+                JSONRPCId::String(String::from("a")),
+                JSONRPCId::Null,
+                JSONRPCId::from(3),
+                JSONRPCId::Null
+            ]
+        );
+        assert_eq!(v[2].error.code, INVALID_REQUEST_CODE);
+        assert_eq!(v[3].error.code, INVALID_REQUEST_CODE);
+
+        // only notifications
+        let batch = serde_json::json!([{"jsonrpc": JSON_RPC_VERSION, "method": "foo"}]);
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .json(&batch)
+            .reply(&filter)
+            .await;
+        assert_eq!(StatusCode::NO_CONTENT, value.status());
+        assert!(value.body().is_empty());
+
+        // empty batch
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .json(&serde_json::json!([]))
+            .reply(&filter)
+            .await;
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert_eq!(v.error.code, INVALID_REQUEST_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_filter_unauthorized() {
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(true));
+
+        let req = JSONRPCRequest {
+            id: Some(JSONRPCId::from(0)),
+            jsonrpc: String::from(JSON_RPC_VERSION),
+            method: String::from("foo"),
+            params: Default::default(),
+        };
+
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .json(&req)
+            .reply(&filter)
+            .await;
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert!(v.error.message.starts_with("unauthorized"));
+
+        // unknown methods require the admin permission
+        let token = JwtAuth::new(vec![0; 32])
+            .create_token(Permission::Sign, None)
+            .unwrap();
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .header("authorization", format!("Bearer {token:}"))
+            .json(&req)
+            .reply(&filter)
+            .await;
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert!(v.error.message.contains("admin"));
+        assert_eq!(v.error.code, UNAUTHORIZED_CODE);
+
+        let token = JwtAuth::new(vec![0; 32])
+            .create_token(Permission::Admin, None)
+            .unwrap();
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .header("authorization", format!("Bearer {token:}"))
+            .json(&req)
+            .reply(&filter)
+            .await;
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<Value>>(value.body()).unwrap();
+        assert_eq!(v.error.code, METHOD_NOT_FOUND_CODE);
+        assert_eq!(v.error.data.unwrap()["method"], "foo");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket() {
+        use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl, JsonRpcError};
+        use crate::server::jsonrpc::bind_unix_socket;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.sock");
+        let path = path.to_str().unwrap();
+        // a socket still listened on is not replaced, a stale one is
+        let listener = std::os::unix::net::UnixListener::bind(path).unwrap();
+        assert!(bind_unix_socket(path, 0o600).is_err());
+        drop(listener);
+
+        let incoming = bind_unix_socket(path, 0o600).unwrap();
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // only the socket is left in the directory
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(false));
+        tokio::spawn(warp::serve(filter).run_incoming(incoming));
+
+        let url = format!("unix://{path:}").parse().unwrap();
+        let client = JsonRpcClientImpl::new(url, None);
+        let e = client
+            .request::<Value>("foo", Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<JsonRpcError>().unwrap().code,
+            UNAUTHORIZED_CODE as i64
+        );
+
+        // the token is sent over the socket too
+        let token = JwtAuth::new(vec![0; 32])
+            .create_token(Permission::Admin, None)
+            .unwrap();
+        let url = format!("unix://{path:}").parse().unwrap();
+        let client = JsonRpcClientImpl::new(url, Some(&token));
+        let e = client
+            .request::<Value>("foo", Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<JsonRpcError>().unwrap().code,
+            METHOD_NOT_FOUND_CODE as i64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_filter() {
+        let filter = metrics_filter(get_auth(false));
+
+        let value = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(StatusCode::OK, value.status());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_filter_requires_auth() {
+        let jwt = JwtAuth::new(vec![0; 32]);
+        let token = jwt.create_token(Permission::Read, None).unwrap();
+        let filter = metrics_filter(get_auth(true));
+
+        let value = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&filter)
+            .await;
+        assert_eq!(StatusCode::UNAUTHORIZED, value.status());
+
+        let value = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .header("authorization", format!("Bearer {token:}"))
+            .reply(&filter)
+            .await;
+        assert_eq!(StatusCode::OK, value.status());
     }
 
     #[tokio::test]
     async fn test_json_rpc_filter_not_found() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), get_auth(false));
 
         let value = warp::test::request()
             .method("POST")
@@ -4,22 +4,28 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
 
+pub mod auth;
+pub mod error;
 mod handlers;
 pub mod jsonrpc;
+pub mod openrpc;
 pub mod request;
 pub mod response;
+pub mod ws;
 
 pub use handlers::*;
 
-/// The JSON RPC server request handler trait.
+/// The JSON RPC server request handler trait. The schemas of the request and response describe
+/// the method in the OpenRPC document of the server, see [`openrpc`].
 #[async_trait]
 pub trait JsonRPCRequestHandler {
-    type Request: Debug + DeserializeOwned + Send;
-    type Response: Debug + Serialize;
+    type Request: Debug + DeserializeOwned + Send + JsonSchema;
+    type Response: Debug + Serialize + JsonSchema;
 
     /// Handles the request sent to the json rpc server. Returns a response back.
     async fn handle(&self, request: Self::Request) -> Result<Self::Response>;
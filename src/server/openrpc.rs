@@ -0,0 +1,196 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The OpenRPC document of the json rpc api, served by `rpc.discover`.
+//!
+//! The document is generated from the request and response types of the handlers, so that it
+//! follows the methods the agent actually serves. The params of the methods are passed by name:
+//! every field of the request of a method is one of its params. The few methods whose request is
+//! not a plain object, i.e. the creation and import of wallets, take it as a whole and are
+//! described with a single `params` param. The subscription methods are only served by the
+//! websocket endpoint, which their description states.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{InstanceType, Schema, SingleOrVec};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the OpenRPC specification the document follows.
+pub const OPENRPC_VERSION: &str = "1.2.6";
+/// The name of the param describing the whole request of the methods not taking an object.
+pub const WHOLE_PARAMS_NAME: &str = "params";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRpcDocument {
+    pub openrpc: String,
+    pub info: Info,
+    /// The methods of the agent, sorted by name.
+    pub methods: Vec<MethodObject>,
+    pub components: Components,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodObject {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub param_structure: String,
+    pub params: Vec<ContentDescriptor>,
+    pub result: ContentDescriptor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentDescriptor {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub schema: Schema,
+}
+
+/// The schemas of the types referenced by the methods.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Components {
+    pub schemas: BTreeMap<String, Schema>,
+}
+
+impl OpenRpcDocument {
+    /// Creates the document of the methods, described with the schemas of `gen`.
+    pub fn new(mut methods: Vec<MethodObject>, mut gen: SchemaGenerator) -> Self {
+        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            openrpc: String::from(OPENRPC_VERSION),
+            info: Info {
+                title: String::from("IPC agent"),
+                version: String::from(env!("CARGO_PKG_VERSION")),
+            },
+            methods,
+            components: Components {
+                schemas: gen.take_definitions().into_iter().collect(),
+            },
+        }
+    }
+}
+
+/// The generator of the schemas of a document, referencing the types from its components.
+pub fn schema_generator() -> SchemaGenerator {
+    SchemaSettings::draft07()
+        .with(|s| s.definitions_path = String::from("#/components/schemas/"))
+        .into_generator()
+}
+
+/// Describes the method taking `Req` as params and returning `Res`.
+pub fn method<Req: JsonSchema, Res: JsonSchema>(
+    name: &str,
+    gen: &mut SchemaGenerator,
+) -> MethodObject {
+    MethodObject {
+        name: name.to_string(),
+        description: None,
+        param_structure: String::from("by-name"),
+        params: params(Req::json_schema(gen)),
+        result: ContentDescriptor {
+            name: String::from("result"),
+            description: None,
+            required: true,
+            schema: gen.subschema_for::<Res>(),
+        },
+    }
+}
+
+/// Splits the schema of a request into the params of its method.
+fn params(schema: Schema) -> Vec<ContentDescriptor> {
+    let object = match &schema {
+        Schema::Object(object) => object,
+        Schema::Bool(_) => return vec![whole_params(schema)],
+    };
+
+    // the methods without params take `()`.
+    if object.instance_type == Some(SingleOrVec::Single(Box::new(InstanceType::Null))) {
+        return vec![];
+    }
+
+    match &object.object {
+        Some(validation) if object.subschemas.is_none() => validation
+            .properties
+            .iter()
+            .map(|(name, schema)| ContentDescriptor {
+                name: name.clone(),
+                description: description(schema),
+                required: validation.required.contains(name),
+                schema: schema.clone(),
+            })
+            .collect(),
+        _ => vec![whole_params(schema)],
+    }
+}
+
+fn whole_params(schema: Schema) -> ContentDescriptor {
+    ContentDescriptor {
+        name: String::from(WHOLE_PARAMS_NAME),
+        description: description(&schema),
+        required: true,
+        schema,
+    }
+}
+
+fn description(schema: &Schema) -> Option<String> {
+    match schema {
+        Schema::Object(object) => object.metadata.as_ref()?.description.clone(),
+        Schema::Bool(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::fund::FundParams;
+    use crate::server::openrpc::{method, schema_generator, OpenRpcDocument, WHOLE_PARAMS_NAME};
+    use crate::server::wallet::new::{WalletNewParams, WalletNewResponse};
+    use crate::server::Tracked;
+    use fvm_shared::clock::ChainEpoch;
+
+    #[test]
+    fn test_openrpc_document() {
+        let mut gen = schema_generator();
+        let fund = method::<FundParams, Tracked<ChainEpoch>>("ipc_fund", &mut gen);
+        let wallet_new = method::<WalletNewParams, WalletNewResponse>("ipc_walletNew", &mut gen);
+        let discover = method::<(), serde_json::Value>("rpc.discover", &mut gen);
+        let document = OpenRpcDocument::new(vec![wallet_new, fund, discover], gen);
+
+        let names = document.methods.iter().map(|m| m.name.as_str());
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            vec!["ipc_fund", "ipc_walletNew", "rpc.discover"]
+        );
+
+        let fund = &document.methods[0];
+        let param = |name: &str| fund.params.iter().find(|p| p.name == name).unwrap();
+        assert!(param("subnet").required);
+        assert!(param("amount").required);
+        assert_eq!(param("amount").description.as_deref(), Some("In whole FIL"));
+        assert!(!param("from").required);
+        assert!(!param("asynchronous").required);
+        // the record of the asynchronous calls is shared between the methods
+        assert!(document
+            .components
+            .schemas
+            .contains_key("TrackedTransaction"));
+
+        let wallet_new = &document.methods[1];
+        assert_eq!(wallet_new.params.len(), 1);
+        assert_eq!(wallet_new.params[0].name, WHOLE_PARAMS_NAME);
+        assert!(document.methods[2].params.is_empty());
+
+        let value = serde_json::to_value(&document).unwrap();
+        assert_eq!(value["openrpc"], "1.2.6");
+        assert_eq!(value["methods"][0]["paramStructure"], "by-name");
+    }
+}
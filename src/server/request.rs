@@ -1,14 +1,77 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+/// The id of a json rpc request, echoed in its response. Following the spec, it is either a number,
+/// a string or null: https://www.jsonrpc.org/specification#request_object
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum JSONRPCId {
+    Number(serde_json::Number),
+    String(String),
+    Null,
+}
+
+impl From<u64> for JSONRPCId {
+    fn from(id: u64) -> Self {
+        Self::Number(id.into())
+    }
+}
+
 /// The json rpc request param. It is the standard form our json-rpc and follows a structure similar
 /// to the one of the Ethereum RPC: https://ethereum.org/en/developers/docs/apis/json-rpc/#curl-examples
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JSONRPCRequest {
-    pub id: u64,
+    /// The id of the request, `None` for a notification, which gets no response. A `null` id is
+    /// not a notification.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_id"
+    )]
+    pub id: Option<JSONRPCId>,
     pub jsonrpc: String,
     pub method: String,
+    #[serde(default)]
     pub params: Value,
 }
+
+impl JSONRPCRequest {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// Tells an explicit `null` id apart from a missing one, which `Option` would merge.
+fn deserialize_id<'de, D>(deserializer: D) -> Result<Option<JSONRPCId>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    JSONRPCId::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::request::{JSONRPCId, JSONRPCRequest};
+
+    #[test]
+    fn test_request_ids() {
+        let parse = |s: &str| serde_json::from_str::<JSONRPCRequest>(s).unwrap();
+
+        let r = parse(r#"{"jsonrpc": "2.0", "method": "foo", "params": [], "id": 1}"#);
+        assert_eq!(r.id, Some(JSONRPCId::from(1)));
+        let r = parse(r#"{"jsonrpc": "2.0", "method": "foo", "id": "abc"}"#);
+        assert_eq!(r.id, Some(JSONRPCId::String(String::from("abc"))));
+        assert!(r.params.is_null());
+        let r = parse(r#"{"jsonrpc": "2.0", "method": "foo", "id": null}"#);
+        assert_eq!(r.id, Some(JSONRPCId::Null));
+        let r = parse(r#"{"jsonrpc": "2.0", "method": "foo"}"#);
+        assert!(r.is_notification());
+
+        assert!(serde_json::from_str::<JSONRPCRequest>(
+            r#"{"jsonrpc": "2.0", "method": "foo", "id": {}}"#
+        )
+        .is_err());
+    }
+}
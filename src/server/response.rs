@@ -1,22 +1,27 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use crate::config::JSON_RPC_VERSION;
+use crate::server::request::JSONRPCId;
 use serde::{Deserialize, Serialize};
 
 /// List of error codes for json rpc, see more: https://www.jsonrpc.org/specification#error_object
-const INVALID_REQUEST_CODE: i32 = -32600;
+pub const PARSE_ERROR_CODE: i32 = -32700;
+pub const INVALID_REQUEST_CODE: i32 = -32600;
+pub const METHOD_NOT_FOUND_CODE: i32 = -32601;
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+pub const INTERNAL_ERROR_CODE: i32 = -32603;
 
 /// The json rpc result response. It is the standard form our json-rpc and follows
 /// the spec: https://www.jsonrpc.org/specification#response_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JSONRPCResultResponse<T> {
-    pub id: u64,
+    pub id: JSONRPCId,
     pub jsonrpc: String,
     pub result: T,
 }
 
 impl<T: Serialize> JSONRPCResultResponse<T> {
-    pub fn new(id: u64, result: T) -> Self {
+    pub fn new(id: JSONRPCId, result: T) -> Self {
         Self {
             id,
             jsonrpc: String::from(JSON_RPC_VERSION),
@@ -36,26 +41,35 @@ pub struct JSONRPCError<T> {
 /// The json rpc error response. It is the standard form our json-rpc and follows the spec: https://www.jsonrpc.org/specification#response_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JSONRPCErrorResponse<T> {
-    pub id: u64,
+    pub id: JSONRPCId,
     pub jsonrpc: String,
     pub error: JSONRPCError<T>,
 }
 
 impl JSONRPCErrorResponse<()> {
-    pub fn invalid_request(id: u64) -> Self {
-        Self {
+    /// The body is not valid json. The id of the request is unknown.
+    pub fn parse_error() -> Self {
+        Self::with_code(JSONRPCId::Null, PARSE_ERROR_CODE, "Parse error")
+    }
+
+    pub fn invalid_request(id: JSONRPCId) -> Self {
+        Self::with_code(id, INVALID_REQUEST_CODE, "Invalid Request")
+    }
+
+    fn with_code(id: JSONRPCId, code: i32, message: &str) -> Self {
+        Self::new(
             id,
-            jsonrpc: String::from(JSON_RPC_VERSION),
-            error: JSONRPCError {
-                code: INVALID_REQUEST_CODE,
-                message: String::from("Invalid Request"),
+            JSONRPCError {
+                code,
+                message: String::from(message),
                 data: None,
             },
-        }
+        )
     }
 }
+
 impl<T: Serialize> JSONRPCErrorResponse<T> {
-    pub fn new(id: u64, error: JSONRPCError<T>) -> Self {
+    pub fn new(id: JSONRPCId, error: JSONRPCError<T>) -> Self {
         Self {
             id,
             jsonrpc: String::from(JSON_RPC_VERSION),
@@ -0,0 +1,392 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The websocket endpoint of the json rpc api.
+//!
+//! A websocket connection accepts the same requests as the http endpoint, authorized with the
+//! `authorization` header of its upgrade request. On top of them, the clients can subscribe to the
+//! events of the agent with `ipc_subscribe`, which returns the id of the subscription. The events
+//! are then pushed as `ipc_subscription` notifications until the client calls `ipc_unsubscribe`
+//! or closes the connection. A client too slow to read the notifications misses some of them.
+
+use crate::config::{json_rpc_methods, JSON_RPC_VERSION, WS_ENDPOINT};
+use crate::events::{Event, EventBus, EventTopic};
+use crate::server::auth::RequestAuth;
+use crate::server::error::AgentError;
+use crate::server::jsonrpc::{
+    handle_method, handle_payload, with_auth, with_handlers, ArcAuth, ArcHandlers,
+};
+use crate::server::openrpc::{self, MethodObject};
+use futures_util::{SinkExt, StreamExt};
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::gen::SchemaGenerator;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Reply};
+
+/// The number of responses and notifications queued for the writer of a connection. The events of
+/// the subscriptions are dropped while the queue is full.
+const OUTGOING_CAPACITY: usize = 256;
+/// The number of requests of a connection handled at the same time.
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SubscribeParams {
+    /// The topics of the events sent, all of them if empty.
+    #[serde(default)]
+    pub topics: Vec<EventTopic>,
+    /// Only send the events of this subnet.
+    pub subnet: Option<String>,
+}
+
+impl SubscribeParams {
+    fn matches(&self, event: &Event) -> bool {
+        (self.topics.is_empty() || self.topics.contains(&event.topic))
+            && self
+                .subnet
+                .as_ref()
+                .map_or(true, |s| Some(s) == event.subnet.as_ref())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UnsubscribeParams {
+    /// The id returned by `ipc_subscribe`.
+    pub subscription: u64,
+}
+
+/// The params of the `ipc_subscription` notifications.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscriptionNotification {
+    pub subscription: u64,
+    pub result: Event,
+}
+
+/// Describes the methods only served by the websocket endpoint in the OpenRPC document.
+pub(crate) fn describe_methods(gen: &mut SchemaGenerator) -> Vec<MethodObject> {
+    let description = format!("Only served by the websocket endpoint: /{WS_ENDPOINT:}");
+    let mut methods = vec![
+        openrpc::method::<SubscribeParams, u64>(json_rpc_methods::SUBSCRIBE, gen),
+        openrpc::method::<UnsubscribeParams, bool>(json_rpc_methods::UNSUBSCRIBE, gen),
+    ];
+    for method in methods.iter_mut() {
+        method.description = Some(description.clone());
+    }
+    methods
+}
+
+/// Create the websocket filter, upgrading the GET requests to the `WS_ENDPOINT` to websocket
+/// connections.
+pub(crate) fn ws_filter(
+    handlers: ArcHandlers,
+    auth: ArcAuth,
+    events: Arc<EventBus>,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::path(WS_ENDPOINT)
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_handlers(handlers))
+        .and(with_auth(auth))
+        .and(warp::any().map(move || events.clone()))
+        .map(
+            |ws: Ws,
+             authorization: Option<String>,
+             handlers: ArcHandlers,
+             auth: ArcAuth,
+             events: Arc<EventBus>| {
+                ws.on_upgrade(move |socket| {
+                    handle_connection(socket, authorization, handlers, auth, events)
+                })
+            },
+        )
+}
+
+/// Handles the messages of the connection until it is closed. Each request is handled in its own
+/// task, so that a slow one, e.g. a `fund`, does not hold up the others. The responses and
+/// notifications are written to the socket by a separate task.
+async fn handle_connection(
+    socket: WebSocket,
+    authorization: Option<String>,
+    handlers: ArcHandlers,
+    auth: ArcAuth,
+    events: Arc<EventBus>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let (sender, mut outgoing) = mpsc::channel::<Value>(OUTGOING_CAPACITY);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            if let Err(e) = sink.send(Message::text(message.to_string())).await {
+                log::debug!("cannot write to websocket: {e:}");
+                return;
+            }
+        }
+    });
+
+    let connection = Arc::new(Connection {
+        authorization,
+        handlers,
+        auth,
+        events,
+        sender,
+        next_subscription: AtomicU64::new(0),
+        subscriptions: Mutex::new(HashMap::new()),
+    });
+    let requests = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    while let Some(message) = stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                log::debug!("cannot read from websocket: {e:}");
+                break;
+            }
+        };
+        // the writer stops if the socket cannot be written to anymore.
+        if message.is_close() || connection.sender.is_closed() {
+            break;
+        }
+        // the pings are answered by warp.
+        if !message.is_text() && !message.is_binary() {
+            continue;
+        }
+
+        let permit = match requests.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => break,
+        };
+        let connection = connection.clone();
+        tokio::spawn(async move {
+            let reply = handle_payload(message.as_bytes(), |method, params| {
+                connection.handle_method(method, params)
+            })
+            .await;
+            // the reply is dropped if the connection was closed in the meantime.
+            if let Some(reply) = reply {
+                let _ = connection.sender.send(reply).await;
+            }
+            drop(permit);
+        });
+    }
+
+    connection.close();
+    writer.abort();
+}
+
+/// The state of a websocket connection.
+struct Connection {
+    authorization: Option<String>,
+    handlers: ArcHandlers,
+    auth: ArcAuth,
+    events: Arc<EventBus>,
+    /// Sends the responses and notifications to the writer of the socket.
+    sender: mpsc::Sender<Value>,
+    next_subscription: AtomicU64,
+    /// The tasks forwarding the events of each subscription.
+    subscriptions: Mutex<HashMap<u64, JoinHandle<()>>>,
+}
+
+impl Connection {
+    async fn handle_method(&self, method: String, params: Value) -> Result<Value, AgentError> {
+        let authorization = self.authorization.as_deref();
+        match method.as_str() {
+            json_rpc_methods::SUBSCRIBE => {
+                authorize(&self.auth, authorization, &method)?;
+                // subscribes to all the events if there are no params.
+                let params = if params.is_null() {
+                    SubscribeParams::default()
+                } else {
+                    parse_params::<SubscribeParams>(params)?
+                };
+                Ok(Value::from(self.subscribe(params)?))
+            }
+            json_rpc_methods::UNSUBSCRIBE => {
+                authorize(&self.auth, authorization, &method)?;
+                let params = parse_params::<UnsubscribeParams>(params)?;
+                Ok(Value::from(self.unsubscribe(params.subscription)))
+            }
+            _ => handle_method(method, params, authorization, &self.handlers, &self.auth).await,
+        }
+    }
+
+    /// Starts forwarding the events matching the params and returns the id of the subscription.
+    fn subscribe(&self, mut params: SubscribeParams) -> Result<u64, AgentError> {
+        // the subnets of the events are formatted from their id.
+        params.subnet = params
+            .subnet
+            .map(|s| SubnetID::from_str(&s).map(|s| s.to_string()))
+            .transpose()
+            .map_err(|e| AgentError::InvalidParams {
+                reason: format!("invalid subnet: {e:}"),
+            })?;
+
+        let id = self.next_subscription.fetch_add(1, Ordering::Relaxed);
+        let mut receiver = self.events.subscribe();
+        let sender = self.sender.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("subscription {id:} too slow, skipped {skipped:} events");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                if !params.matches(&event) {
+                    continue;
+                }
+
+                let notification = serde_json::json!({
+                    "jsonrpc": JSON_RPC_VERSION,
+                    "method": json_rpc_methods::SUBSCRIPTION,
+                    "params": SubscriptionNotification {
+                        subscription: id,
+                        result: event,
+                    },
+                });
+                match sender.try_send(notification) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        log::warn!("subscription {id:} too slow, dropped an event");
+                    }
+                    Err(TrySendError::Closed(_)) => return,
+                }
+            }
+        });
+
+        self.subscriptions.lock().unwrap().insert(id, task);
+        Ok(id)
+    }
+
+    /// Stops the subscription. Returns false if there is no such subscription.
+    fn unsubscribe(&self, id: u64) -> bool {
+        match self.subscriptions.lock().unwrap().remove(&id) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops all the subscriptions of the connection.
+    fn close(&self) {
+        for (_, task) in self.subscriptions.lock().unwrap().drain() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn authorize(
+    auth: &RequestAuth,
+    authorization: Option<&str>,
+    method: &str,
+) -> Result<(), AgentError> {
+    auth.authorize(authorization, method).map_err(|e| {
+        log::debug!("rejected method = {method:?}: {e:}");
+        e
+    })
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, AgentError> {
+    serde_json::from_value(params).map_err(|e| AgentError::InvalidParams {
+        reason: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ipc_sdk::subnet_id::SubnetID;
+    use serde_json::{json, Value};
+    use warp::test::WsClient;
+
+    use crate::config::{json_rpc_methods, JSON_RPC_VERSION, WS_ENDPOINT};
+    use crate::events::{EventBus, EventTopic};
+    use crate::server::auth::{JwtAuth, RequestAuth};
+    use crate::server::error::UNAUTHORIZED_CODE;
+    use crate::server::response::{JSONRPCErrorResponse, JSONRPCResultResponse};
+    use crate::server::ws::{ws_filter, SubscriptionNotification};
+    use crate::server::Handlers;
+
+    async fn call(client: &mut WsClient, method: &str, params: Value, id: u64) -> Value {
+        let request =
+            json!({"jsonrpc": JSON_RPC_VERSION, "method": method, "params": params, "id": id});
+        client.send_text(request.to_string()).await;
+        recv(client).await
+    }
+
+    async fn recv(client: &mut WsClient) -> Value {
+        let message = client.recv().await.unwrap();
+        serde_json::from_str(message.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ws_subscriptions() {
+        let events = Arc::new(EventBus::new());
+        let filter = ws_filter(
+            Arc::new(Handlers::empty_handlers()),
+            Arc::new(RequestAuth::new(JwtAuth::new(vec![0; 32]), false)),
+            events.clone(),
+        );
+        let mut client = warp::test::ws()
+            .path(&format!("/{WS_ENDPOINT:}"))
+            .handshake(filter)
+            .await
+            .unwrap();
+
+        let subnet = SubnetID::new_root(123);
+        let params = json!({"topics": ["fund_executed"], "subnet": subnet.to_string()});
+        let response = call(&mut client, json_rpc_methods::SUBSCRIBE, params, 1).await;
+        let subscription = serde_json::from_value::<JSONRPCResultResponse<u64>>(response)
+            .unwrap()
+            .result;
+
+        // only the events matching the subscription are sent
+        events.publish(EventTopic::ConfigReloaded, None, ());
+        events.publish(EventTopic::FundExecuted, Some(&SubnetID::new_root(1)), 1);
+        events.publish(EventTopic::FundExecuted, Some(&subnet), 10);
+        let notification = recv(&mut client).await;
+        assert_eq!(notification["method"], json_rpc_methods::SUBSCRIPTION);
+        let notification =
+            serde_json::from_value::<SubscriptionNotification>(notification["params"].clone())
+                .unwrap();
+        assert_eq!(notification.subscription, subscription);
+        assert_eq!(notification.result.topic, EventTopic::FundExecuted);
+        assert_eq!(notification.result.data, json!(10));
+
+        // the other methods are served as over http, unknown ones require the admin permission
+        let response = call(&mut client, "foo", Value::Null, 2).await;
+        let response = serde_json::from_value::<JSONRPCErrorResponse<Value>>(response).unwrap();
+        assert_eq!(response.error.code, UNAUTHORIZED_CODE);
+
+        let params = json!({ "subscription": subscription });
+        let response = call(
+            &mut client,
+            json_rpc_methods::UNSUBSCRIBE,
+            params.clone(),
+            3,
+        )
+        .await;
+        assert_eq!(response["result"], true);
+        let response = call(&mut client, json_rpc_methods::UNSUBSCRIBE, params, 4).await;
+        assert_eq!(response["result"], false);
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The state of a simulated chain and the api to drive it from the tests.
+
+use crate::relayer::{is_postbox_msg, postbox_msg_cid};
+use anyhow::{anyhow, Result};
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fil_actors_runtime::cbor;
+use fvm_ipld_encoding::{RawBytes, DAG_CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use ipc_gateway::checkpoint::{BatchCrossMsgs, ChildCheck};
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, TopDownCheckpoint};
+use ipc_sdk::subnet_id::SubnetID;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The checkpoint period of the simulated gateways and subnet actors, unless changed.
+pub const DEFAULT_SIM_PERIOD: ChainEpoch = 10;
+
+/// An in-memory chain of a subnet. It holds the gateway of the subnet and the subnet actors of its
+/// children. Clones share the same chain, so the test can keep a handle to drive it while the code
+/// under test uses another one.
+#[derive(Clone)]
+pub struct SimChain {
+    state: Arc<Mutex<SimState>>,
+}
+
+pub(super) struct SimState {
+    pub(super) subnet_id: SubnetID,
+    pub(super) epoch: ChainEpoch,
+    pub(super) gateway: SimGateway,
+    pub(super) subnet_actors: HashMap<SubnetID, SimSubnetActor>,
+    /// The epoch each submission was included in, keyed by the message cid or transaction hash.
+    pub(super) submissions: HashMap<String, ChainEpoch>,
+    next_submission: u64,
+}
+
+pub(super) struct SimGateway {
+    pub(super) initialized: bool,
+    pub(super) bottom_up_period: ChainEpoch,
+    pub(super) top_down_period: ChainEpoch,
+    /// The validators of the subnet, voting the top-down checkpoints.
+    pub(super) validators: Vec<Address>,
+    pub(super) top_down_voting: SimVoting,
+    pub(super) applied_top_down_nonce: u64,
+    /// The nonces of the messages of the top-down checkpoints submitted and not executed yet, by
+    /// epoch.
+    top_down_checkpoints: HashMap<ChainEpoch, Vec<u64>>,
+    /// The top-down messages to the child subnets, along with the epoch they were sent in.
+    pub(super) top_down_msgs: HashMap<SubnetID, Vec<(ChainEpoch, CrossMsg)>>,
+    /// The bottom-up messages to the parent, along with the epoch they were sent in.
+    pub(super) bottom_up_msgs: Vec<(ChainEpoch, CrossMsg)>,
+    /// The checks of the checkpoints committed by the children, along with the epoch they were
+    /// committed in.
+    pub(super) children: Vec<(ChainEpoch, ChildCheck)>,
+    /// The messages committed by the bottom-up checkpoints of the children that are waiting to be
+    /// propagated, by cid.
+    pub(super) postbox: HashMap<Cid, CrossMsg>,
+}
+
+pub(super) struct SimSubnetActor {
+    pub(super) bottom_up_period: ChainEpoch,
+    pub(super) validators: Vec<Address>,
+    pub(super) configuration_number: u64,
+    pub(super) voting: SimVoting,
+    /// The bottom-up checkpoints submitted and not executed yet, by epoch.
+    pending: HashMap<ChainEpoch, Vec<u8>>,
+    /// The executed bottom-up checkpoints, by epoch.
+    committed: BTreeMap<ChainEpoch, Vec<u8>>,
+}
+
+/// The votes of the validators on the checkpoints.
+#[derive(Default)]
+pub(super) struct SimVoting {
+    pub(super) last_executed_epoch: ChainEpoch,
+    votes: HashMap<ChainEpoch, HashSet<Address>>,
+}
+
+impl SimVoting {
+    pub(super) fn has_voted(&self, epoch: ChainEpoch, validator: &Address) -> bool {
+        self.votes
+            .get(&epoch)
+            .map_or(false, |votes| votes.contains(validator))
+    }
+
+    /// Records the vote of the validator and executes the checkpoints that reached a two-thirds
+    /// quorum, in order. Returns the executed epochs.
+    fn vote(
+        &mut self,
+        epoch: ChainEpoch,
+        validator: &Address,
+        validators: &[Address],
+        period: ChainEpoch,
+    ) -> Result<Vec<ChainEpoch>> {
+        if !validators.contains(validator) {
+            return Err(anyhow!("{validator:} is not a validator"));
+        }
+        if epoch <= self.last_executed_epoch || epoch % period != 0 {
+            return Err(anyhow!("cannot vote on checkpoint at epoch {epoch:}"));
+        }
+        if !self.votes.entry(epoch).or_default().insert(*validator) {
+            return Err(anyhow!("{validator:} already voted in epoch {epoch:}"));
+        }
+
+        let mut executed = vec![];
+        loop {
+            let next = self.last_executed_epoch + period;
+            let votes = self.votes.get(&next).map_or(0, |v| v.len());
+            if votes == 0 || 3 * votes < 2 * validators.len() {
+                return Ok(executed);
+            }
+            self.votes.remove(&next);
+            self.last_executed_epoch = next;
+            executed.push(next);
+        }
+    }
+}
+
+impl SimChain {
+    /// Creates the chain of the subnet at epoch 0, with an initialized gateway and no children.
+    pub fn new(subnet_id: SubnetID) -> Self {
+        let state = SimState {
+            subnet_id,
+            epoch: 0,
+            gateway: SimGateway {
+                initialized: true,
+                bottom_up_period: DEFAULT_SIM_PERIOD,
+                top_down_period: DEFAULT_SIM_PERIOD,
+                validators: vec![],
+                top_down_voting: SimVoting::default(),
+                applied_top_down_nonce: 0,
+                top_down_checkpoints: HashMap::new(),
+                top_down_msgs: HashMap::new(),
+                bottom_up_msgs: vec![],
+                children: vec![],
+                postbox: HashMap::new(),
+            },
+            subnet_actors: HashMap::new(),
+            submissions: HashMap::new(),
+            next_submission: 0,
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub(super) fn state(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn subnet_id(&self) -> SubnetID {
+        self.state().subnet_id.clone()
+    }
+
+    pub fn epoch(&self) -> ChainEpoch {
+        self.state().epoch
+    }
+
+    /// Mines `epochs` new empty blocks.
+    pub fn advance(&self, epochs: ChainEpoch) {
+        self.state().epoch += epochs;
+    }
+
+    pub fn set_gateway_initialized(&self, initialized: bool) {
+        self.state().gateway.initialized = initialized;
+    }
+
+    /// Sets the validators of this subnet, i.e. the validators voting the top-down checkpoints.
+    pub fn set_gateway_validators(&self, validators: Vec<Address>) {
+        self.state().gateway.validators = validators;
+    }
+
+    pub fn set_top_down_period(&self, period: ChainEpoch) {
+        self.state().gateway.top_down_period = period;
+    }
+
+    /// Registers the subnet actor of a child subnet with its initial validators.
+    pub fn register_subnet(&self, child: SubnetID, validators: Vec<Address>) {
+        self.state().subnet_actors.insert(
+            child,
+            SimSubnetActor {
+                bottom_up_period: DEFAULT_SIM_PERIOD,
+                validators,
+                configuration_number: 0,
+                voting: SimVoting::default(),
+                pending: HashMap::new(),
+                committed: BTreeMap::new(),
+            },
+        );
+    }
+
+    /// Replaces the validators of a child subnet, bumping the configuration number of its
+    /// validator set.
+    pub fn set_validators(&self, child: &SubnetID, validators: Vec<Address>) -> Result<()> {
+        let mut state = self.state();
+        let actor = state.subnet_actor_mut(child)?;
+        actor.validators = validators;
+        actor.configuration_number += 1;
+        Ok(())
+    }
+
+    /// Sends a top-down message to a child subnet in the current epoch. The message is assigned
+    /// the next nonce of the child.
+    pub fn push_top_down_msg(&self, child: &SubnetID, mut msg: CrossMsg) {
+        let mut state = self.state();
+        let epoch = state.epoch;
+        let msgs = state
+            .gateway
+            .top_down_msgs
+            .entry(child.clone())
+            .or_default();
+        msg.msg.nonce = msgs.len() as u64;
+        msgs.push((epoch, msg));
+    }
+
+    /// Sends a bottom-up message to the parent in the current epoch.
+    pub fn push_bottom_up_msg(&self, msg: CrossMsg) {
+        let mut state = self.state();
+        let epoch = state.epoch;
+        state.gateway.bottom_up_msgs.push((epoch, msg));
+    }
+
+    /// Records the check of a checkpoint committed by a child in the current epoch, it is carried
+    /// by the next bottom-up checkpoint of this subnet.
+    pub fn push_child_check(&self, check: ChildCheck) {
+        let mut state = self.state();
+        let epoch = state.epoch;
+        state.gateway.children.push((epoch, check));
+    }
+
+    /// The epoch of the last top-down checkpoint executed in this subnet.
+    pub fn last_executed_top_down(&self) -> ChainEpoch {
+        self.state().gateway.top_down_voting.last_executed_epoch
+    }
+
+    pub fn applied_top_down_nonce(&self) -> u64 {
+        self.state().gateway.applied_top_down_nonce
+    }
+
+    /// The nonces of the messages of the top-down checkpoint submitted at the epoch and not
+    /// executed yet.
+    pub fn pending_top_down_nonces(&self, epoch: ChainEpoch) -> Option<Vec<u64>> {
+        self.state()
+            .gateway
+            .top_down_checkpoints
+            .get(&epoch)
+            .cloned()
+    }
+
+    /// The epoch of the last bottom-up checkpoint of the child executed in this subnet.
+    pub fn last_executed_bottom_up(&self, child: &SubnetID) -> Result<ChainEpoch> {
+        Ok(self.state().subnet_actor(child)?.voting.last_executed_epoch)
+    }
+
+    /// The number of messages waiting in the postbox.
+    pub fn postbox_len(&self) -> usize {
+        self.state().gateway.postbox.len()
+    }
+
+    /// The bottom-up checkpoints of the child executed in this subnet in the range of epochs.
+    pub fn committed_checkpoints(
+        &self,
+        child: &SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        let state = self.state();
+        state
+            .subnet_actor(child)?
+            .committed
+            .range(from_epoch..=to_epoch)
+            .map(|(_, bytes)| decode(bytes))
+            .collect()
+    }
+}
+
+impl SimState {
+    pub(super) fn subnet_actor(&self, child: &SubnetID) -> Result<&SimSubnetActor> {
+        self.subnet_actors
+            .get(child)
+            .ok_or_else(|| anyhow!("subnet {child:} not registered"))
+    }
+
+    fn subnet_actor_mut(&mut self, child: &SubnetID) -> Result<&mut SimSubnetActor> {
+        self.subnet_actors
+            .get_mut(child)
+            .ok_or_else(|| anyhow!("subnet {child:} not registered"))
+    }
+
+    /// The cid of the tipset at the epoch. Every tipset of the simulated chain has a single block.
+    pub(super) fn tip_set(&self, epoch: ChainEpoch) -> Cid {
+        sim_cid(&format!("tipset {:} {epoch:}", self.subnet_id))
+    }
+
+    /// The parent state root of the block at the epoch.
+    pub(super) fn state_root(&self, epoch: ChainEpoch) -> Cid {
+        sim_cid(&format!("state {:} {epoch:}", self.subnet_id))
+    }
+
+    /// The bottom-up checkpoint template of the subnet at the epoch, with the bottom-up messages
+    /// sent and the child checks committed in the period.
+    pub(super) fn checkpoint_template(&self, epoch: ChainEpoch) -> BottomUpCheckpoint {
+        let period = self.gateway.bottom_up_period;
+        let cross_msgs = self
+            .gateway
+            .bottom_up_msgs
+            .iter()
+            .filter(|(e, _)| *e > epoch - period && *e <= epoch)
+            .map(|(_, msg)| msg.clone())
+            .collect::<Vec<_>>();
+
+        let mut checkpoint = BottomUpCheckpoint::new(self.subnet_id.clone(), epoch);
+        checkpoint.data.children = self
+            .gateway
+            .children
+            .iter()
+            .filter(|(e, _)| *e > epoch - period && *e <= epoch)
+            .map(|(_, check)| check.clone())
+            .collect();
+        if !cross_msgs.is_empty() {
+            checkpoint.data.cross_msgs = BatchCrossMsgs {
+                cross_msgs: Some(cross_msgs),
+                fee: Default::default(),
+            };
+        }
+        checkpoint
+    }
+
+    /// The cid of the last bottom-up checkpoint of the child executed in this subnet.
+    pub(super) fn prev_checkpoint(&self, child: &SubnetID) -> Result<Option<Cid>> {
+        match self.subnet_actor(child)?.committed.values().last() {
+            Some(bytes) => Ok(Some(decode(bytes)?.cid())),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn checkpoint(
+        &self,
+        child: &SubnetID,
+        epoch: ChainEpoch,
+    ) -> Result<BottomUpCheckpoint> {
+        let bytes = self
+            .subnet_actor(child)?
+            .committed
+            .get(&epoch)
+            .ok_or_else(|| anyhow!("no checkpoint committed in epoch {epoch:}"))?;
+        decode(bytes)
+    }
+
+    /// The top-down messages to the child from `nonce`, sent up to the epoch.
+    pub(super) fn top_down_msgs(
+        &self,
+        child: &SubnetID,
+        nonce: u64,
+        epoch: ChainEpoch,
+    ) -> Vec<CrossMsg> {
+        self.gateway
+            .top_down_msgs
+            .get(child)
+            .map(|msgs| {
+                msgs.iter()
+                    .filter(|(e, msg)| *e <= epoch && msg.msg.nonce >= nonce)
+                    .map(|(_, msg)| msg.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Votes the bottom-up checkpoint in the subnet actor of its source subnet. The first
+    /// checkpoint submitted for an epoch is the one committed once the epoch is executed.
+    pub(super) fn submit_bottom_up(
+        &mut self,
+        validator: &Address,
+        checkpoint: BottomUpCheckpoint,
+    ) -> Result<String> {
+        let epoch = checkpoint.data.epoch;
+        let actor = self.subnet_actor_mut(&checkpoint.data.source)?;
+        let executed =
+            actor
+                .voting
+                .vote(epoch, validator, &actor.validators, actor.bottom_up_period)?;
+
+        if let Entry::Vacant(e) = actor.pending.entry(epoch) {
+            e.insert(encode(&checkpoint)?);
+        }
+        let mut committed = vec![];
+        for e in executed {
+            if let Some(bytes) = actor.pending.remove(&e) {
+                committed.push(decode(&bytes)?);
+                actor.committed.insert(e, bytes);
+            }
+        }
+        for checkpoint in committed {
+            self.commit_cross_msgs(checkpoint)?;
+        }
+
+        Ok(self.record_submission())
+    }
+
+    /// Votes the top-down checkpoint in the gateway. Executing a checkpoint applies its top-down
+    /// messages.
+    pub(super) fn submit_top_down(
+        &mut self,
+        validator: &Address,
+        checkpoint: TopDownCheckpoint,
+    ) -> Result<String> {
+        let epoch = checkpoint.epoch;
+        let gateway = &mut self.gateway;
+        let nonces = checkpoint
+            .top_down_msgs
+            .iter()
+            .map(|msg| msg.msg.nonce)
+            .collect::<Vec<_>>();
+        // the votes on a different checkpoint never reach the quorum of the voted one.
+        if let Some(voted) = gateway.top_down_checkpoints.get(&epoch) && *voted != nonces {
+            return Err(anyhow!(
+                "top-down checkpoint at epoch {epoch:} does not match the one voted"
+            ));
+        }
+
+        let executed = gateway.top_down_voting.vote(
+            epoch,
+            validator,
+            &gateway.validators,
+            gateway.top_down_period,
+        )?;
+        gateway.top_down_checkpoints.entry(epoch).or_insert(nonces);
+        for e in executed {
+            let nonces = gateway.top_down_checkpoints.remove(&e).unwrap_or_default();
+            if let Some(nonce) = nonces.iter().max() {
+                gateway.applied_top_down_nonce =
+                    std::cmp::max(gateway.applied_top_down_nonce, nonce + 1);
+            }
+        }
+
+        Ok(self.record_submission())
+    }
+
+    /// Stores the messages of a committed bottom-up checkpoint sent below this subnet in the
+    /// postbox.
+    fn commit_cross_msgs(&mut self, checkpoint: BottomUpCheckpoint) -> Result<()> {
+        let msgs = checkpoint.data.cross_msgs.cross_msgs.unwrap_or_default();
+        for msg in msgs {
+            if is_postbox_msg(&self.subnet_id, &msg)? {
+                self.gateway.postbox.insert(postbox_msg_cid(&msg)?, msg);
+            }
+        }
+        Ok(())
+    }
+
+    /// Propagates a message out of the postbox.
+    pub(super) fn propagate(&mut self, postbox_msg_cid: &Cid) -> Result<String> {
+        self.gateway
+            .postbox
+            .remove(postbox_msg_cid)
+            .ok_or_else(|| anyhow!("no message {postbox_msg_cid:} in the postbox"))?;
+        Ok(self.record_submission())
+    }
+
+    /// Records a submission included in the current epoch and returns its identifier, the cid of
+    /// the message.
+    fn record_submission(&mut self) -> String {
+        self.next_submission += 1;
+        let submission = sim_cid(&format!(
+            "submission {:} {:}",
+            self.subnet_id, self.next_submission
+        ))
+        .to_string();
+        self.submissions.insert(submission.clone(), self.epoch);
+        submission
+    }
+
+    /// Returns the epoch the submission was included in.
+    pub(super) fn submission_epoch(&self, submission: &str) -> Result<ChainEpoch> {
+        self.submissions
+            .get(submission)
+            .copied()
+            .ok_or_else(|| anyhow!("unknown submission: {submission:}"))
+    }
+}
+
+/// Derives a deterministic cid from the seed.
+fn sim_cid(seed: &str) -> Cid {
+    Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(seed.as_bytes()))
+}
+
+// The checkpoints are stored encoded, so that they can be handed out without cloning them.
+fn encode(checkpoint: &BottomUpCheckpoint) -> Result<Vec<u8>> {
+    Ok(cbor::serialize(checkpoint, "bottom up checkpoint")?.to_vec())
+}
+
+fn decode(bytes: &[u8]) -> Result<BottomUpCheckpoint> {
+    Ok(cbor::deserialize(
+        &RawBytes::new(bytes.to_vec()),
+        "bottom up checkpoint",
+    )?)
+}
@@ -0,0 +1,285 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The evm api of the simulated chain. Transactions are identified by the digest of the cid of the
+//! equivalent lotus message. The subnet lifecycle methods are not simulated and return an error.
+
+use crate::lotus::message::ipc::QueryValidatorSetResponse;
+use crate::manager::evm::{cid_to_evm_hash, gateway, subnet_contract};
+use crate::manager::{EthManager, SubnetInfo, SubnetManager};
+use crate::sim::chain::SimState;
+use crate::sim::lotus::{not_simulated, subnet_info, validator_set};
+use crate::sim::SimChain;
+use anyhow::Result;
+use async_trait::async_trait;
+use cid::Cid;
+use ethers::types::TxHash;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, TopDownCheckpoint};
+use ipc_sdk::subnet_id::SubnetID;
+use ipc_subnet_actor::ConstructParams;
+use std::collections::HashMap;
+
+#[async_trait]
+impl SubnetManager for SimChain {
+    async fn create_subnet(&self, _from: Address, _params: ConstructParams) -> Result<Address> {
+        Err(not_simulated("create_subnet"))
+    }
+
+    async fn join_subnet(
+        &self,
+        _subnet: SubnetID,
+        _from: Address,
+        _collateral: TokenAmount,
+        _validator_net_addr: String,
+        _worker_addr: Address,
+    ) -> Result<()> {
+        Err(not_simulated("join_subnet"))
+    }
+
+    async fn leave_subnet(&self, _subnet: SubnetID, _from: Address) -> Result<()> {
+        Err(not_simulated("leave_subnet"))
+    }
+
+    async fn kill_subnet(&self, _subnet: SubnetID, _from: Address) -> Result<()> {
+        Err(not_simulated("kill_subnet"))
+    }
+
+    async fn list_child_subnets(
+        &self,
+        _gateway_addr: Address,
+    ) -> Result<HashMap<SubnetID, SubnetInfo>> {
+        let state = self.state();
+        Ok(state
+            .subnet_actors
+            .keys()
+            .map(|id| (id.clone(), subnet_info(id)))
+            .collect())
+    }
+
+    async fn fund(
+        &self,
+        _subnet: SubnetID,
+        _gateway_addr: Address,
+        _from: Address,
+        _to: Address,
+        _amount: TokenAmount,
+    ) -> Result<ChainEpoch> {
+        Err(not_simulated("fund"))
+    }
+
+    async fn release(
+        &self,
+        _subnet: SubnetID,
+        _gateway_addr: Address,
+        _from: Address,
+        _to: Address,
+        _amount: TokenAmount,
+    ) -> Result<ChainEpoch> {
+        Err(not_simulated("release"))
+    }
+
+    async fn propagate(
+        &self,
+        _subnet: SubnetID,
+        _gateway_addr: Address,
+        _from: Address,
+        _postbox_msg_cid: Cid,
+    ) -> Result<()> {
+        Err(not_simulated("propagate"))
+    }
+
+    async fn set_validator_net_addr(
+        &self,
+        _subnet: SubnetID,
+        _from: Address,
+        _validator_net_addr: String,
+    ) -> Result<()> {
+        Err(not_simulated("set_validator_net_addr"))
+    }
+
+    async fn whitelist_propagator(
+        &self,
+        _subnet: SubnetID,
+        _gateway_addr: Address,
+        _postbox_msg_cid: Cid,
+        _from: Address,
+        _to_add: Vec<Address>,
+    ) -> Result<()> {
+        Err(not_simulated("whitelist_propagator"))
+    }
+
+    async fn send_value(&self, _from: Address, _to: Address, _amount: TokenAmount) -> Result<()> {
+        Err(not_simulated("send_value"))
+    }
+
+    async fn wallet_balance(&self, _address: &Address) -> Result<TokenAmount> {
+        Err(not_simulated("wallet_balance"))
+    }
+
+    async fn last_topdown_executed(&self, _gateway_addr: &Address) -> Result<ChainEpoch> {
+        Ok(self.last_executed_top_down())
+    }
+
+    async fn list_checkpoints(
+        &self,
+        subnet_id: SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        self.committed_checkpoints(&subnet_id, from_epoch, to_epoch)
+    }
+
+    async fn get_validator_set(
+        &self,
+        subnet_id: &SubnetID,
+        _gateway: Option<Address>,
+    ) -> Result<QueryValidatorSetResponse> {
+        let state = self.state();
+        Ok(QueryValidatorSetResponse {
+            validator_set: validator_set(state.subnet_actor(subnet_id)?),
+            min_validators: 0,
+            genesis_epoch: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl EthManager for SimChain {
+    async fn gateway_last_voting_executed_epoch(&self) -> Result<ChainEpoch> {
+        Ok(self.last_executed_top_down())
+    }
+
+    async fn subnet_last_voting_executed_epoch(&self, subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        self.last_executed_bottom_up(subnet_id)
+    }
+
+    async fn current_epoch(&self) -> Result<ChainEpoch> {
+        Ok(self.epoch())
+    }
+
+    async fn submit_top_down_checkpoint(
+        &self,
+        from: &Address,
+        checkpoint: gateway::TopDownCheckpoint,
+    ) -> Result<TxHash> {
+        let top_down_msgs = checkpoint
+            .top_down_msgs
+            .into_iter()
+            .map(CrossMsg::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let checkpoint = TopDownCheckpoint {
+            epoch: checkpoint.epoch as ChainEpoch,
+            top_down_msgs,
+        };
+
+        let mut state = self.state();
+        let submission = state.submit_top_down(from, checkpoint)?;
+        to_tx_hash(&mut state, submission)
+    }
+
+    async fn submit_bottom_up_checkpoint(
+        &self,
+        from: &Address,
+        checkpoint: subnet_contract::BottomUpCheckpoint,
+    ) -> Result<TxHash> {
+        let checkpoint = BottomUpCheckpoint::try_from(checkpoint)?;
+
+        let mut state = self.state();
+        let submission = state.submit_bottom_up(from, checkpoint)?;
+        to_tx_hash(&mut state, submission)
+    }
+
+    async fn wait_transaction(&self, tx_hash: TxHash) -> Result<ChainEpoch> {
+        self.state().submission_epoch(&format!("{tx_hash:?}"))
+    }
+
+    async fn has_voted_in_subnet(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let state = self.state();
+        Ok(state
+            .subnet_actor(subnet_id)?
+            .voting
+            .has_voted(epoch, validator))
+    }
+
+    async fn has_voted_in_gateway(&self, epoch: ChainEpoch, validator: &Address) -> Result<bool> {
+        let state = self.state();
+        Ok(state.gateway.top_down_voting.has_voted(epoch, validator))
+    }
+
+    async fn bottom_up_checkpoint(
+        &self,
+        epoch: ChainEpoch,
+    ) -> Result<subnet_contract::BottomUpCheckpoint> {
+        let template = self.state().checkpoint_template(epoch);
+        subnet_contract::BottomUpCheckpoint::try_from(template)
+    }
+
+    async fn get_applied_top_down_nonce(&self, _subnet_id: &SubnetID) -> Result<u64> {
+        Ok(self.applied_top_down_nonce())
+    }
+
+    async fn gateway_applied_top_down_nonce(&self) -> Result<u64> {
+        Ok(self.applied_top_down_nonce())
+    }
+
+    async fn top_down_msgs(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        nonce: u64,
+    ) -> Result<Vec<CrossMsg>> {
+        Ok(self.state().top_down_msgs(subnet_id, nonce, epoch))
+    }
+
+    async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>> {
+        Ok(self.state().subnet_actor(subnet_id)?.validators.clone())
+    }
+
+    async fn validator_set_configuration_number(&self, subnet_id: &SubnetID) -> Result<u64> {
+        Ok(self.state().subnet_actor(subnet_id)?.configuration_number)
+    }
+
+    async fn gateway_initialized(&self) -> Result<bool> {
+        Ok(self.state().gateway.initialized)
+    }
+
+    async fn subnet_bottom_up_checkpoint_period(&self, subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        Ok(self.state().subnet_actor(subnet_id)?.bottom_up_period)
+    }
+
+    async fn gateway_top_down_check_period(&self) -> Result<ChainEpoch> {
+        Ok(self.state().gateway.top_down_period)
+    }
+
+    async fn prev_bottom_up_checkpoint_hash(
+        &self,
+        subnet_id: &SubnetID,
+        _epoch: ChainEpoch,
+    ) -> Result<[u8; 32]> {
+        match self.state().prev_checkpoint(subnet_id)? {
+            Some(prev) => cid_to_evm_hash(&prev),
+            None => Ok([0; 32]),
+        }
+    }
+
+    async fn min_validators(&self, subnet_id: &SubnetID) -> Result<u64> {
+        self.state().subnet_actor(subnet_id)?;
+        Ok(0)
+    }
+}
+
+/// Derives the transaction hash of a submission and records it alongside the message cid.
+fn to_tx_hash(state: &mut SimState, submission: String) -> Result<TxHash> {
+    let cid = Cid::try_from(submission.as_str())?;
+    let tx_hash = TxHash::from_slice(cid.hash().digest());
+    let epoch = state.submission_epoch(&submission)?;
+    state.submissions.insert(format!("{tx_hash:?}"), epoch);
+    Ok(tx_hash)
+}
@@ -0,0 +1,194 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The checkpoint handlers of the simulated chain, so that it can back the bottom-up and top-down
+//! checkpoint managers both as parent and as child, and the postbox handler of the relayer.
+
+use crate::checkpoint::{
+    create_proof, BottomUpHandler, CheckpointQuery, NativeBottomUpCheckpoint, SubmissionQuery,
+    TopDownHandler, VoteQuery,
+};
+use crate::relayer::PostboxHandler;
+use crate::sim::SimChain;
+use anyhow::Result;
+use async_trait::async_trait;
+use cid::Cid;
+use fil_actors_runtime::cbor;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, TopDownCheckpoint};
+use ipc_sdk::subnet_id::SubnetID;
+use primitives::TCid;
+
+#[async_trait]
+impl VoteQuery<NativeBottomUpCheckpoint> for SimChain {
+    async fn last_executed_epoch(&self, subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        self.last_executed_bottom_up(subnet_id)
+    }
+
+    async fn current_epoch(&self) -> Result<ChainEpoch> {
+        Ok(self.epoch())
+    }
+
+    async fn has_voted(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let state = self.state();
+        Ok(state
+            .subnet_actor(subnet_id)?
+            .voting
+            .has_voted(epoch, validator))
+    }
+}
+
+#[async_trait]
+impl CheckpointQuery<NativeBottomUpCheckpoint> for SimChain {
+    async fn checkpoint_period(&self, subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        Ok(self.state().subnet_actor(subnet_id)?.bottom_up_period)
+    }
+
+    async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>> {
+        Ok(self.state().subnet_actor(subnet_id)?.validators.clone())
+    }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64> {
+        Ok(self.state().subnet_actor(subnet_id)?.configuration_number)
+    }
+}
+
+#[async_trait]
+impl BottomUpHandler for SimChain {
+    async fn checkpoint_template(&self, epoch: ChainEpoch) -> Result<NativeBottomUpCheckpoint> {
+        let template = self.state().checkpoint_template(epoch);
+        NativeBottomUpCheckpoint::try_from(template)
+    }
+
+    async fn populate_prev_hash(
+        &self,
+        template: &mut NativeBottomUpCheckpoint,
+        subnet: &SubnetID,
+        _previous_epoch: ChainEpoch,
+    ) -> Result<()> {
+        let prev = self.state().prev_checkpoint(subnet)?;
+        template.prev_check = prev.map(|cid| cid.to_bytes());
+        Ok(())
+    }
+
+    async fn populate_proof(&self, template: &mut NativeBottomUpCheckpoint) -> Result<()> {
+        let proof = create_proof(self, template.epoch).await?;
+        let bytes = cbor::serialize(&proof, "sim bottom up checkpoint proof")?.to_vec();
+        template.proof = Some(bytes);
+        Ok(())
+    }
+
+    async fn submit(
+        &self,
+        validator: &Address,
+        checkpoint: NativeBottomUpCheckpoint,
+    ) -> Result<String> {
+        let mut committed = BottomUpCheckpoint::try_from(&checkpoint)?;
+        if let Some(prev) = &checkpoint.prev_check {
+            committed.data.prev_check = TCid::from(Cid::try_from(prev.as_slice())?);
+        }
+        self.state().submit_bottom_up(validator, committed)
+    }
+}
+
+#[async_trait]
+impl SubmissionQuery for SimChain {
+    async fn wait_submission(&self, submission: &str) -> Result<ChainEpoch> {
+        self.state().submission_epoch(submission)
+    }
+}
+
+#[async_trait]
+impl VoteQuery<TopDownCheckpoint> for SimChain {
+    async fn last_executed_epoch(&self, _subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        Ok(self.last_executed_top_down())
+    }
+
+    async fn current_epoch(&self) -> Result<ChainEpoch> {
+        Ok(self.epoch())
+    }
+
+    async fn has_voted(
+        &self,
+        _subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let state = self.state();
+        Ok(state.gateway.top_down_voting.has_voted(epoch, validator))
+    }
+}
+
+#[async_trait]
+impl CheckpointQuery<TopDownCheckpoint> for SimChain {
+    async fn checkpoint_period(&self, _subnet_id: &SubnetID) -> Result<ChainEpoch> {
+        Ok(self.state().gateway.top_down_period)
+    }
+
+    async fn validators(&self, subnet_id: &SubnetID) -> Result<Vec<Address>> {
+        Ok(self.state().subnet_actor(subnet_id)?.validators.clone())
+    }
+
+    async fn validator_set_version(&self, subnet_id: &SubnetID) -> Result<u64> {
+        Ok(self.state().subnet_actor(subnet_id)?.configuration_number)
+    }
+}
+
+#[async_trait]
+impl TopDownHandler for SimChain {
+    async fn gateway_initialized(&self) -> Result<bool> {
+        Ok(self.state().gateway.initialized)
+    }
+
+    async fn applied_topdown_nonce(&self, _subnet_id: &SubnetID) -> Result<u64> {
+        Ok(self.applied_top_down_nonce())
+    }
+
+    async fn top_down_msgs(
+        &self,
+        subnet_id: &SubnetID,
+        nonce: u64,
+        epoch: ChainEpoch,
+    ) -> Result<Vec<CrossMsg>> {
+        Ok(self.state().top_down_msgs(subnet_id, nonce, epoch))
+    }
+
+    async fn submit(&self, validator: &Address, checkpoint: TopDownCheckpoint) -> Result<String> {
+        self.state().submit_top_down(validator, checkpoint)
+    }
+}
+
+#[async_trait]
+impl PostboxHandler for SimChain {
+    async fn child_subnets(&self) -> Result<Vec<SubnetID>> {
+        Ok(self.state().subnet_actors.keys().cloned().collect())
+    }
+
+    async fn last_committed_epoch(&self, child: &SubnetID) -> Result<ChainEpoch> {
+        self.last_executed_bottom_up(child)
+    }
+
+    async fn committed_checkpoints(
+        &self,
+        child: &SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        SimChain::committed_checkpoints(self, child, from_epoch, to_epoch)
+    }
+
+    async fn propagate(
+        &self,
+        _from: &Address,
+        postbox_msg_cid: Cid,
+        _max_fee: TokenAmount,
+    ) -> Result<String> {
+        self.state().propagate(&postbox_msg_cid)
+    }
+}
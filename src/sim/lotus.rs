@@ -0,0 +1,283 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The lotus api of the simulated chain. Only the chain and ipc methods are simulated, the
+//! mpool, wallet and generic state methods return an error.
+
+use crate::lotus::message::chain::{ChainHeadResponse, GetTipSetByHeightResponse};
+use crate::lotus::message::ipc::{
+    IPCReadGatewayStateResponse, IPCReadSubnetActorStateResponse, Validator, ValidatorSet, Voting,
+};
+use crate::lotus::message::mpool::{MpoolPushMessage, MpoolPushMessageResponseInner};
+use crate::lotus::message::state::{ReadStateResponse, StateWaitMsgResponse};
+use crate::lotus::message::wallet::{WalletKeyType, WalletListResponse};
+use crate::lotus::message::CIDMap;
+use crate::lotus::{LotusClient, NetworkVersion};
+use crate::manager::SubnetInfo;
+use crate::sim::chain::SimSubnetActor;
+use crate::sim::SimChain;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cid::Cid;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, Status, TopDownCheckpoint};
+use ipc_sdk::subnet_id::SubnetID;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+#[async_trait]
+impl LotusClient for SimChain {
+    async fn mpool_push_message(
+        &self,
+        _msg: MpoolPushMessage,
+    ) -> Result<MpoolPushMessageResponseInner> {
+        Err(not_simulated("MpoolPushMessage"))
+    }
+
+    async fn mpool_push(&self, _msg: MpoolPushMessage) -> Result<Cid> {
+        Err(not_simulated("MpoolPush"))
+    }
+
+    async fn state_wait_msg(&self, cid: Cid) -> Result<StateWaitMsgResponse> {
+        let state = self.state();
+        let height = state.submission_epoch(&cid.to_string())?;
+        let response = json!({
+            "Message": {"/": cid.to_string()},
+            "Receipt": {"ExitCode": 0, "Return": null, "GasUsed": 0},
+            "TipSet": [{"/": state.tip_set(height).to_string()}],
+            "Height": height,
+        });
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn state_network_name(&self) -> Result<String> {
+        Ok(self.subnet_id().to_string())
+    }
+
+    async fn state_network_version(&self, _tip_sets: Vec<Cid>) -> Result<NetworkVersion> {
+        Err(not_simulated("StateNetworkVersion"))
+    }
+
+    async fn state_actor_code_cids(
+        &self,
+        _network_version: NetworkVersion,
+    ) -> Result<HashMap<String, Cid>> {
+        Err(not_simulated("StateActorCodeCIDs"))
+    }
+
+    async fn wallet_default(&self) -> Result<Address> {
+        Err(not_simulated("WalletDefaultAddress"))
+    }
+
+    async fn wallet_list(&self) -> Result<WalletListResponse> {
+        Err(not_simulated("WalletList"))
+    }
+
+    async fn wallet_new(&self, _key_type: WalletKeyType) -> Result<String> {
+        Err(not_simulated("WalletNew"))
+    }
+
+    async fn wallet_balance(&self, _address: &Address) -> Result<TokenAmount> {
+        Err(not_simulated("WalletBalance"))
+    }
+
+    async fn read_state<State: DeserializeOwned + Debug>(
+        &self,
+        _address: Address,
+        _tipset: Cid,
+    ) -> Result<ReadStateResponse<State>> {
+        Err(not_simulated("StateReadState"))
+    }
+
+    async fn chain_head(&self) -> Result<ChainHeadResponse> {
+        let state = self.state();
+        Ok(ChainHeadResponse {
+            cids: vec![CIDMap::from(state.tip_set(state.epoch))],
+            blocks: vec![],
+            height: state.epoch as u64,
+        })
+    }
+
+    async fn current_epoch(&self) -> Result<ChainEpoch> {
+        Ok(self.epoch())
+    }
+
+    async fn get_tipset_by_height(
+        &self,
+        epoch: ChainEpoch,
+        _tip_set: Cid,
+    ) -> Result<GetTipSetByHeightResponse> {
+        let state = self.state();
+        if epoch > state.epoch {
+            return Err(anyhow!("epoch {epoch:} is ahead of the chain head"));
+        }
+        let response = json!({
+            "Cids": [{"/": state.tip_set(epoch).to_string()}],
+            "Blocks": [{"ParentStateRoot": {"/": state.state_root(epoch).to_string()}}],
+        });
+        Ok(serde_json::from_value(response)?)
+    }
+
+    async fn ipc_submit_top_down_checkpoint(
+        &self,
+        _gateway_addr: Address,
+        validator: &Address,
+        checkpoint: TopDownCheckpoint,
+    ) -> Result<Cid> {
+        let submission = self.state().submit_top_down(validator, checkpoint)?;
+        Ok(Cid::try_from(submission.as_str())?)
+    }
+
+    async fn ipc_get_prev_checkpoint_for_child(
+        &self,
+        _gateway_addr: &Address,
+        child_subnet_id: &SubnetID,
+    ) -> Result<Option<CIDMap>> {
+        let prev = self.state().prev_checkpoint(child_subnet_id)?;
+        Ok(prev.map(CIDMap::from))
+    }
+
+    async fn ipc_get_checkpoint_template(
+        &self,
+        _gateway_addr: &Address,
+        epoch: ChainEpoch,
+    ) -> Result<BottomUpCheckpoint> {
+        Ok(self.state().checkpoint_template(epoch))
+    }
+
+    async fn ipc_get_checkpoint(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+    ) -> Result<BottomUpCheckpoint> {
+        self.state().checkpoint(subnet_id, epoch)
+    }
+
+    async fn ipc_read_gateway_state(
+        &self,
+        _gateway_addr: &Address,
+        _tip_set: Cid,
+    ) -> Result<IPCReadGatewayStateResponse> {
+        let state = self.state();
+        let gateway = &state.gateway;
+        Ok(IPCReadGatewayStateResponse {
+            bottom_up_check_period: gateway.bottom_up_period,
+            top_down_check_period: gateway.top_down_period,
+            applied_topdown_nonce: gateway.applied_top_down_nonce,
+            top_down_checkpoint_voting: Voting {
+                genesis_epoch: 0,
+                last_voting_executed: gateway.top_down_voting.last_executed_epoch,
+            },
+            initialized: gateway.initialized,
+        })
+    }
+
+    async fn ipc_read_subnet_actor_state(
+        &self,
+        subnet_id: &SubnetID,
+        _tip_set: Cid,
+    ) -> Result<IPCReadSubnetActorStateResponse> {
+        let state = self.state();
+        let actor = state.subnet_actor(subnet_id)?;
+        Ok(IPCReadSubnetActorStateResponse {
+            bottom_up_check_period: actor.bottom_up_period,
+            validator_set: validator_set(actor),
+            min_validators: 0,
+            bottom_up_checkpoint_voting: Voting {
+                genesis_epoch: 0,
+                last_voting_executed: actor.voting.last_executed_epoch,
+            },
+        })
+    }
+
+    async fn ipc_list_child_subnets(&self, _gateway_addr: Address) -> Result<Vec<SubnetInfo>> {
+        let state = self.state();
+        Ok(state.subnet_actors.keys().map(subnet_info).collect())
+    }
+
+    async fn ipc_validator_has_voted_bottomup(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let state = self.state();
+        Ok(state
+            .subnet_actor(subnet_id)?
+            .voting
+            .has_voted(epoch, validator))
+    }
+
+    async fn ipc_validator_has_voted_topdown(
+        &self,
+        _gateway_addr: &Address,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let state = self.state();
+        Ok(state.gateway.top_down_voting.has_voted(epoch, validator))
+    }
+
+    async fn ipc_get_topdown_msgs(
+        &self,
+        subnet_id: &SubnetID,
+        _gateway_addr: &Address,
+        _tip_set: Cid,
+        nonce: u64,
+    ) -> Result<Vec<CrossMsg>> {
+        let state = self.state();
+        Ok(state.top_down_msgs(subnet_id, nonce, state.epoch))
+    }
+
+    async fn ipc_get_genesis_epoch_for_subnet(
+        &self,
+        subnet_id: &SubnetID,
+        _gateway_addr: Address,
+    ) -> Result<ChainEpoch> {
+        self.state().subnet_actor(subnet_id)?;
+        Ok(0)
+    }
+
+    async fn ipc_list_checkpoints(
+        &self,
+        subnet_id: SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        self.committed_checkpoints(&subnet_id, from_epoch, to_epoch)
+    }
+}
+
+/// The validator set of the subnet actor as returned by the lotus api.
+pub(super) fn validator_set(actor: &SimSubnetActor) -> ValidatorSet {
+    let validators = actor
+        .validators
+        .iter()
+        .map(|v| Validator {
+            addr: v.to_string(),
+            net_addr: String::new(),
+            worker_addr: Some(v.to_string()),
+            weight: String::from("1"),
+        })
+        .collect();
+    ValidatorSet {
+        validators: Some(validators),
+        configuration_number: actor.configuration_number,
+    }
+}
+
+pub(super) fn subnet_info(id: &SubnetID) -> SubnetInfo {
+    SubnetInfo {
+        id: id.clone(),
+        stake: TokenAmount::default(),
+        circ_supply: TokenAmount::default(),
+        status: Status::Active,
+    }
+}
+
+pub(super) fn not_simulated(method: &str) -> anyhow::Error {
+    anyhow!("{method:} is not supported by the simulated chain")
+}
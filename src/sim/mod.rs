@@ -0,0 +1,16 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! An in-memory simulated chain to test the checkpoint managers deterministically.
+//!
+//! A [`SimChain`] implements the lotus, evm and checkpoint handler apis of a subnet on top of a
+//! fake chain whose epoch, validator sets and cross messages are driven by the test. Checkpoints
+//! are voted and executed once two thirds of the validators submitted them, as the actors do.
+
+pub use chain::{SimChain, DEFAULT_SIM_PERIOD};
+
+mod chain;
+mod eth;
+mod handler;
+mod lotus;
+#[cfg(test)]
+mod tests;
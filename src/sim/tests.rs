@@ -0,0 +1,584 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cid::Cid;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use ipc_gateway::checkpoint::ChildCheck;
+use ipc_gateway::{BottomUpCheckpoint, CrossMsg, StorableMsg};
+use ipc_sdk::address::IPCAddress;
+use ipc_sdk::subnet_id::SubnetID;
+use primitives::{EthAddress, TCid};
+use url::Url;
+
+use crate::checkpoint::{
+    audit_chain, audit_proofs, submit_till_current_epoch, BottomUpManager, CheckpointJournal,
+    CheckpointManager, CheckpointStatusRegistry, TopDownManager, ValidatorCache,
+    DEFAULT_VALIDATOR_CACHE_TTL, MAX_TOP_DOWN_MSGS,
+};
+use crate::config::subnet::{
+    CheckpointingConfig, FVMSubnet, RelayerConfig, SubnetConfig, DEFAULT_FEE_BUDGET_PERIOD_SECS,
+    DEFAULT_RELAYER_POLLING_INTERVAL_SECS,
+};
+use crate::config::Subnet;
+use crate::events::{EventBus, EventTopic};
+use crate::manager::evm::evm_hash_to_cid;
+use crate::manager::fevm::FevmSubnetManager;
+use crate::relayer::{postbox_msg_cid, PostboxHandler, Relayer, RelayerJournal};
+use crate::sim::{SimChain, DEFAULT_SIM_PERIOD};
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+fn subnet(id: SubnetID, accounts: Vec<Address>) -> Subnet {
+    Subnet {
+        network_name: id.to_string(),
+        id,
+        config: SubnetConfig::Fvm(FVMSubnet {
+            gateway_addr: Address::new_id(64),
+            jsonrpc_api_http: Url::parse("http://127.0.0.1:1234/rpc/v1").unwrap(),
+            auth_token: None,
+            accounts,
+        }),
+        checkpointing: None,
+        relayer: None,
+    }
+}
+
+fn cross_msg(subnet: &SubnetID) -> CrossMsg {
+    CrossMsg {
+        msg: StorableMsg {
+            from: IPCAddress::new(subnet, &Address::new_id(1000)).unwrap(),
+            to: IPCAddress::new(subnet, &Address::new_id(1001)).unwrap(),
+            method: 0,
+            params: RawBytes::default(),
+            value: TokenAmount::from_atto(1),
+            nonce: 0,
+        },
+        wrapped: false,
+    }
+}
+
+/// Runs a single round of checkpoint submissions of the manager.
+async fn submit(manager: &dyn CheckpointManager) -> Option<ChainEpoch> {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = CheckpointJournal::load(dir.path().join("journal.json")).unwrap();
+    let status = CheckpointStatusRegistry::new();
+    let mut validators = ValidatorCache::new(DEFAULT_VALIDATOR_CACHE_TTL);
+    submit_till_current_epoch(
+        manager,
+        &journal,
+        &status,
+        &EventBus::new(),
+        &mut validators,
+    )
+    .await
+    .unwrap()
+}
+
+#[test]
+fn test_checkpoint_executed_on_quorum() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = (100..103).map(Address::new_id).collect::<Vec<_>>();
+
+    let chain = SimChain::new(root);
+    chain.register_subnet(child.clone(), validators.clone());
+    let vote = |validator: &Address| {
+        chain
+            .state()
+            .submit_bottom_up(validator, BottomUpCheckpoint::new(child.clone(), 10))
+    };
+
+    vote(&validators[0]).unwrap();
+    assert_eq!(chain.last_executed_bottom_up(&child).unwrap(), 0);
+    assert!(vote(&validators[0]).is_err());
+    assert!(vote(&Address::new_id(200)).is_err());
+
+    vote(&validators[1]).unwrap();
+    assert_eq!(chain.last_executed_bottom_up(&child).unwrap(), 10);
+    assert_eq!(chain.committed_checkpoints(&child, 0, 10).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_bottom_up_manager() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.advance(5);
+    child_chain.push_bottom_up_msg(cross_msg(&root));
+    child_chain.advance(20);
+
+    let manager = BottomUpManager::new(
+        subnet(root, validators),
+        subnet(child.clone(), vec![]),
+        parent_chain.clone(),
+        child_chain.clone(),
+    )
+    .await
+    .unwrap();
+
+    // the checkpoint at epoch 30 is not due yet
+    assert_eq!(submit(&manager).await, Some(30));
+    assert_eq!(parent_chain.last_executed_bottom_up(&child).unwrap(), 20);
+
+    let committed = parent_chain.committed_checkpoints(&child, 0, 30).unwrap();
+    assert_eq!(committed.len(), 2);
+    assert_eq!(
+        committed[0]
+            .data
+            .cross_msgs
+            .cross_msgs
+            .as_ref()
+            .map(Vec::len),
+        Some(1)
+    );
+    assert!(audit_chain(&committed, DEFAULT_SIM_PERIOD).is_empty());
+    assert!(audit_proofs(&child_chain, &committed).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_bottom_up_manager_fevm_child() {
+    let eth_addr = |a: &str| Address::from(EthAddress::from_str(a).unwrap());
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(
+        123,
+        vec![eth_addr("0x1A79385eAd0e873FE0C441C034636D3Edf7014cC")],
+    );
+    let grandchild = SubnetID::new(
+        123,
+        vec![
+            eth_addr("0x1A79385eAd0e873FE0C441C034636D3Edf7014cC"),
+            eth_addr("0x6be1ccf648c74800380d0520d797a170c808b624"),
+        ],
+    );
+    let validators = vec![Address::new_id(100)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.advance(5);
+    // the evm gateway of the child keeps the keccak-256 hash of the checkpoint of its child
+    let check = TCid::from(evm_hash_to_cid(&[7; 32]).unwrap());
+    child_chain.push_child_check(ChildCheck {
+        source: grandchild.clone(),
+        checks: vec![check.clone()],
+    });
+    child_chain.advance(10);
+
+    let manager = BottomUpManager::new(
+        subnet(root, validators),
+        subnet(child.clone(), vec![]),
+        parent_chain.clone(),
+        FevmSubnetManager::new(child_chain.clone(), child_chain.clone()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(submit(&manager).await, Some(20));
+
+    let committed = parent_chain.committed_checkpoints(&child, 0, 10).unwrap();
+    assert_eq!(committed.len(), 1);
+    assert_eq!(
+        committed[0].data.children,
+        vec![ChildCheck {
+            source: grandchild,
+            checks: vec![check],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_top_down_manager() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.set_gateway_validators(validators.clone());
+
+    parent_chain.advance(3);
+    parent_chain.push_top_down_msg(&child, cross_msg(&child));
+    parent_chain.advance(10);
+    parent_chain.push_top_down_msg(&child, cross_msg(&child));
+    parent_chain.advance(12);
+
+    let manager = TopDownManager::new(
+        subnet(root, vec![]),
+        subnet(child, validators),
+        parent_chain.clone(),
+        child_chain.clone(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(submit(&manager).await, Some(30));
+    assert_eq!(child_chain.last_executed_top_down(), 20);
+    // each checkpoint applied the message sent in its period
+    assert_eq!(child_chain.applied_top_down_nonce(), 2);
+}
+
+#[tokio::test]
+async fn test_validator_set_change() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), vec![validators[0]]);
+    let child_chain = SimChain::new(child.clone());
+    child_chain.advance(15);
+
+    let manager = BottomUpManager::new(
+        subnet(root, validators.clone()),
+        subnet(child.clone(), vec![]),
+        parent_chain.clone(),
+        child_chain.clone(),
+    )
+    .await
+    .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let journal = CheckpointJournal::load(dir.path().join("journal.json")).unwrap();
+    let status = CheckpointStatusRegistry::new();
+    let mut cache = ValidatorCache::new(DEFAULT_VALIDATOR_CACHE_TTL);
+    let events = EventBus::new();
+    let mut receiver = events.subscribe();
+
+    submit_till_current_epoch(&manager, &journal, &status, &events, &mut cache)
+        .await
+        .unwrap();
+    assert_eq!(parent_chain.last_executed_bottom_up(&child).unwrap(), 10);
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(event.topic, EventTopic::CheckpointSubmitted);
+    assert_eq!(event.data["epoch"], 10);
+
+    // the new validator is picked up as soon as the version of the validator set changes
+    parent_chain
+        .set_validators(&child, validators.clone())
+        .unwrap();
+    child_chain.advance(10);
+    submit_till_current_epoch(&manager, &journal, &status, &events, &mut cache)
+        .await
+        .unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(event.topic, EventTopic::ValidatorSetChanged);
+    assert_eq!(event.subnet, Some(child.to_string()));
+    // with two validators the checkpoint is only executed once both of them voted
+    assert_eq!(parent_chain.last_executed_bottom_up(&child).unwrap(), 20);
+}
+
+#[tokio::test]
+async fn test_checkpointing_policy() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = (100..104).map(Address::new_id).collect::<Vec<_>>();
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.advance(25);
+
+    let mut child_subnet = subnet(child.clone(), vec![]);
+    child_subnet.checkpointing = Some(CheckpointingConfig {
+        accounts: Some(validators[..2].to_vec()),
+        submission_delay_epochs: 10,
+        ..Default::default()
+    });
+    let manager = BottomUpManager::new(
+        subnet(root, validators.clone()),
+        child_subnet,
+        parent_chain.clone(),
+        child_chain,
+    )
+    .await
+    .unwrap();
+
+    // the checkpoint at epoch 20 is not old enough yet
+    assert_eq!(submit(&manager).await, Some(20));
+
+    // only the voting accounts voted, which is not enough to execute the checkpoint
+    assert_eq!(parent_chain.last_executed_bottom_up(&child).unwrap(), 0);
+    let state = parent_chain.state();
+    let voting = &state.subnet_actor(&child).unwrap().voting;
+    assert!(voting.has_voted(10, &validators[0]));
+    assert!(voting.has_voted(10, &validators[1]));
+    assert!(!voting.has_voted(10, &validators[2]));
+    assert!(!voting.has_voted(20, &validators[0]));
+}
+
+#[tokio::test]
+async fn test_top_down_batches() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.set_gateway_validators(validators.clone());
+
+    parent_chain.advance(3);
+    for _ in 0..2 * MAX_TOP_DOWN_MSGS + 1 {
+        parent_chain.push_top_down_msg(&child, cross_msg(&child));
+    }
+    parent_chain.advance(32);
+
+    let manager = TopDownManager::new(
+        subnet(root, vec![]),
+        subnet(child, validators),
+        parent_chain,
+        child_chain.clone(),
+    )
+    .await
+    .unwrap();
+
+    // the burst of messages is spread over the checkpoints at epochs 10, 20 and 30
+    assert_eq!(submit(&manager).await, Some(40));
+    assert_eq!(child_chain.last_executed_top_down(), 30);
+    assert_eq!(
+        child_chain.applied_top_down_nonce(),
+        2 * MAX_TOP_DOWN_MSGS as u64 + 1
+    );
+}
+
+#[tokio::test]
+async fn test_top_down_batches_ahead_of_execution() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    let child_chain = SimChain::new(child.clone());
+    child_chain.set_gateway_validators(validators.clone());
+
+    parent_chain.advance(3);
+    for _ in 0..2 * MAX_TOP_DOWN_MSGS {
+        parent_chain.push_top_down_msg(&child, cross_msg(&child));
+    }
+    parent_chain.advance(12);
+
+    // each agent manages a single validator, so its checkpoints alone are never executed
+    let manager = |validator: Address| {
+        TopDownManager::new(
+            subnet(root.clone(), vec![]),
+            subnet(child.clone(), vec![validator]),
+            parent_chain.clone(),
+            child_chain.clone(),
+        )
+    };
+    let max = MAX_TOP_DOWN_MSGS as u64;
+
+    assert_eq!(
+        submit(&manager(validators[0]).await.unwrap()).await,
+        Some(20)
+    );
+    assert_eq!(
+        child_chain.pending_top_down_nonces(10),
+        Some((0..max).collect())
+    );
+
+    // after a restart of the agent, the checkpoint at epoch 20 still carries the messages after
+    // the ones of the checkpoint at epoch 10
+    parent_chain.advance(10);
+    assert_eq!(
+        submit(&manager(validators[0]).await.unwrap()).await,
+        Some(30)
+    );
+    assert_eq!(
+        child_chain.pending_top_down_nonces(20),
+        Some((max..2 * max).collect())
+    );
+
+    // the agent of the other validator votes the same checkpoints
+    assert_eq!(
+        submit(&manager(validators[1]).await.unwrap()).await,
+        Some(30)
+    );
+    assert_eq!(child_chain.last_executed_top_down(), 20);
+    assert_eq!(child_chain.applied_top_down_nonce(), 2 * max);
+}
+
+#[tokio::test]
+async fn test_relayer() {
+    let root = SubnetID::new_root(123);
+    let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+    let sibling = SubnetID::new(123, vec![Address::new_id(1002)]);
+    let validators = vec![Address::new_id(100), Address::new_id(101)];
+    let owner = Address::new_id(1000);
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(child.clone(), validators.clone());
+    parent_chain.register_subnet(sibling.clone(), vec![]);
+    let child_chain = SimChain::new(child.clone());
+    child_chain.advance(5);
+
+    let msg = |from: &Address, to: &SubnetID, nonce: u64| {
+        let mut msg = cross_msg(&child);
+        msg.msg.from = IPCAddress::new(&child, from).unwrap();
+        msg.msg.to = IPCAddress::new(to, &Address::new_id(1001)).unwrap();
+        msg.msg.nonce = nonce;
+        msg
+    };
+    child_chain.push_bottom_up_msg(msg(&owner, &sibling, 0));
+    child_chain.push_bottom_up_msg(msg(&owner, &sibling, 1));
+    child_chain.push_bottom_up_msg(msg(&Address::new_id(2000), &sibling, 2));
+    // only the messages sent below the parent are stored in its postbox
+    child_chain.push_bottom_up_msg(msg(&owner, &root, 3));
+    child_chain.advance(20);
+
+    let manager = BottomUpManager::new(
+        subnet(root.clone(), validators),
+        subnet(child, vec![]),
+        parent_chain.clone(),
+        child_chain,
+    )
+    .await
+    .unwrap();
+    submit(&manager).await;
+    assert_eq!(parent_chain.postbox_len(), 3);
+
+    let dir = tempfile::tempdir().unwrap();
+    let journal = Arc::new(RelayerJournal::load(dir.path().join("relayer.json")).unwrap());
+    let relayer_subnet = |fee_budget: u64| {
+        let mut subnet = subnet(root.clone(), vec![owner]);
+        subnet.relayer = Some(RelayerConfig {
+            accounts: None,
+            fee_budget: TokenAmount::from_atto(fee_budget),
+            max_fee_per_msg: TokenAmount::from_atto(5),
+            budget_period_secs: DEFAULT_FEE_BUDGET_PERIOD_SECS,
+            polling_interval_secs: DEFAULT_RELAYER_POLLING_INTERVAL_SECS,
+        });
+        subnet
+    };
+
+    // the budget only covers the fee of a single message
+    let mut relayer =
+        Relayer::new(relayer_subnet(5), parent_chain.clone(), journal.clone()).unwrap();
+    assert_eq!(relayer.relay_pending().await.unwrap(), 1);
+    assert_eq!(relayer.relay_pending().await.unwrap(), 0);
+    assert_eq!(parent_chain.postbox_len(), 2);
+
+    // the message already relayed is not propagated again
+    let mut relayer =
+        Relayer::new(relayer_subnet(10), parent_chain.clone(), journal.clone()).unwrap();
+    assert_eq!(relayer.relay_pending().await.unwrap(), 1);
+    assert_eq!(relayer.relay_pending().await.unwrap(), 0);
+    assert_eq!(parent_chain.postbox_len(), 1);
+    assert_eq!(
+        journal.spent_since(&root, DEFAULT_FEE_BUDGET_PERIOD_SECS),
+        TokenAmount::from_atto(10)
+    );
+}
+
+/// A postbox handler failing the propagations of the messages in `failing`.
+struct FailingPostbox {
+    chain: SimChain,
+    failing: Arc<Mutex<HashSet<Cid>>>,
+}
+
+#[async_trait]
+impl PostboxHandler for FailingPostbox {
+    async fn child_subnets(&self) -> Result<Vec<SubnetID>> {
+        PostboxHandler::child_subnets(&self.chain).await
+    }
+
+    async fn last_committed_epoch(&self, child: &SubnetID) -> Result<ChainEpoch> {
+        PostboxHandler::last_committed_epoch(&self.chain, child).await
+    }
+
+    async fn committed_checkpoints(
+        &self,
+        child: &SubnetID,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Vec<BottomUpCheckpoint>> {
+        PostboxHandler::committed_checkpoints(&self.chain, child, from_epoch, to_epoch).await
+    }
+
+    async fn propagate(
+        &self,
+        from: &Address,
+        postbox_msg_cid: Cid,
+        max_fee: TokenAmount,
+    ) -> Result<String> {
+        if self.failing.lock().unwrap().contains(&postbox_msg_cid) {
+            return Err(anyhow!("propagation of {postbox_msg_cid:} failed"));
+        }
+        PostboxHandler::propagate(&self.chain, from, postbox_msg_cid, max_fee).await
+    }
+}
+
+#[tokio::test]
+async fn test_relayer_failed_propagation() {
+    let root = SubnetID::new_root(123);
+    let children = [
+        SubnetID::new(123, vec![Address::new_id(1001)]),
+        SubnetID::new(123, vec![Address::new_id(1002)]),
+    ];
+    let sibling = SubnetID::new(123, vec![Address::new_id(1003)]);
+    let validators = vec![Address::new_id(100)];
+    let owner = Address::new_id(1000);
+
+    let parent_chain = SimChain::new(root.clone());
+    parent_chain.register_subnet(sibling.clone(), vec![]);
+    let mut cids = vec![];
+    for child in children.iter() {
+        parent_chain.register_subnet(child.clone(), validators.clone());
+        let child_chain = SimChain::new(child.clone());
+        child_chain.advance(5);
+        let mut msg = cross_msg(child);
+        msg.msg.from = IPCAddress::new(child, &owner).unwrap();
+        msg.msg.to = IPCAddress::new(&sibling, &Address::new_id(1001)).unwrap();
+        cids.push(postbox_msg_cid(&msg).unwrap());
+        child_chain.push_bottom_up_msg(msg);
+        child_chain.advance(20);
+
+        let manager = BottomUpManager::new(
+            subnet(root.clone(), validators.clone()),
+            subnet(child.clone(), vec![]),
+            parent_chain.clone(),
+            child_chain,
+        )
+        .await
+        .unwrap();
+        submit(&manager).await;
+    }
+    assert_eq!(parent_chain.postbox_len(), 2);
+
+    let dir = tempfile::tempdir().unwrap();
+    let journal = Arc::new(RelayerJournal::load(dir.path().join("relayer.json")).unwrap());
+    let mut relayer_subnet = subnet(root.clone(), vec![owner]);
+    relayer_subnet.relayer = Some(RelayerConfig {
+        accounts: None,
+        fee_budget: TokenAmount::from_atto(100),
+        max_fee_per_msg: TokenAmount::from_atto(5),
+        budget_period_secs: DEFAULT_FEE_BUDGET_PERIOD_SECS,
+        polling_interval_secs: DEFAULT_RELAYER_POLLING_INTERVAL_SECS,
+    });
+    let failing = Arc::new(Mutex::new(HashSet::from([cids[0]])));
+    let handler = FailingPostbox {
+        chain: parent_chain.clone(),
+        failing: failing.clone(),
+    };
+    let mut relayer = Relayer::new(relayer_subnet, handler, journal).unwrap();
+
+    // the failed propagation in one child does not stop the relaying of the other one
+    assert_eq!(relayer.relay_pending().await.unwrap(), 1);
+    assert_eq!(parent_chain.postbox_len(), 1);
+
+    // the checkpoint of the failed propagation is scanned again in the next run
+    failing.lock().unwrap().clear();
+    assert_eq!(relayer.relay_pending().await.unwrap(), 1);
+    assert_eq!(parent_chain.postbox_len(), 0);
+}
@@ -0,0 +1,330 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Persistent record of the transactions submitted asynchronously through the json rpc api.
+//!
+//! An asynchronous call returns as soon as its first message or transaction is submitted, with the
+//! id of its record. The operation keeps on running in the agent, which records the messages
+//! submitted on its behalf, including the replacements of the stuck ones, and the outcome once it
+//! completes. The records survive a restart of the agent so that they can still be queried.
+
+use crate::config::ReloadableConfig;
+use anyhow::{anyhow, Result};
+use fvm_shared::address::Address;
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The name of the transactions file in the agent repo directory.
+pub const TRANSACTIONS_FILE_NAME: &str = "transactions.json";
+/// The number of records kept, the oldest ones are removed first.
+pub const MAX_TRACKED_TRANSACTIONS: usize = 1000;
+
+/// Receives the message cids or transaction hashes as they are submitted by a subnet manager.
+pub type SubmissionSink = UnboundedSender<String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// The operation is running, its last submission is waiting for inclusion.
+    Pending,
+    /// The operation completed, its result is recorded.
+    Completed,
+    /// The operation failed, its error is recorded.
+    Failed,
+    /// The agent stopped while the operation was running. Its submissions may still have been
+    /// executed and have to be checked on chain.
+    Interrupted,
+}
+
+impl Display for TransactionStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Pending => "pending",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Interrupted => "interrupted",
+        };
+        write!(f, "{status:}")
+    }
+}
+
+impl FromStr for TransactionStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "interrupted" => Ok(Self::Interrupted),
+            _ => Err(anyhow!("unknown transaction status: {s:}")),
+        }
+    }
+}
+
+/// The record of an operation submitted asynchronously.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TrackedTransaction {
+    /// The tracking id of the operation.
+    pub id: u64,
+    /// The json rpc method of the operation.
+    pub method: String,
+    /// The subnet the messages are submitted to.
+    pub subnet: String,
+    pub from: String,
+    pub status: TransactionStatus,
+    /// The message cids or transaction hashes submitted, in order. A transaction replacing a stuck
+    /// one is appended after it.
+    pub submissions: Vec<String>,
+    /// The response the method returns once the operation completed.
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Unix timestamp in seconds of the call.
+    pub created_at: u64,
+    /// Unix timestamp in seconds of the last update.
+    pub updated_at: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct TrackerContent {
+    next_id: u64,
+    transactions: Vec<TrackedTransaction>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    next_id: u64,
+    transactions: BTreeMap<u64, TrackedTransaction>,
+}
+
+/// The on-disk record of the transactions. Like the journals, every update is persisted by
+/// replacing the whole file.
+pub struct TransactionTracker {
+    path: PathBuf,
+    state: Mutex<TrackerState>,
+}
+
+impl TransactionTracker {
+    /// Loads the records stored in `path`, or starts with none if the file does not exist. The
+    /// operations still pending were interrupted by the restart of the agent.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let content = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("cannot parse transactions file {path:?}: {e:}"))?
+        } else {
+            TrackerContent::default()
+        };
+
+        let state = TrackerState {
+            next_id: content.next_id,
+            transactions: content
+                .transactions
+                .into_iter()
+                .map(|mut t| {
+                    if t.status == TransactionStatus::Pending {
+                        t.status = TransactionStatus::Interrupted;
+                    }
+                    (t.id, t)
+                })
+                .collect(),
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Loads the records from the repo directory of the agent config.
+    pub fn from_config(config: &ReloadableConfig) -> Result<Self> {
+        let repo = config
+            .get_config_repo()
+            .ok_or_else(|| anyhow!("No repo found in config to store the transactions"))?;
+        Self::load(Path::new(&repo).join(TRANSACTIONS_FILE_NAME))
+    }
+
+    /// Records a new operation sending messages from `from` to `subnet` and returns its id.
+    pub fn track(&self, method: &str, subnet: &SubnetID, from: &Address) -> Result<u64> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let now = now();
+        state.transactions.insert(
+            id,
+            TrackedTransaction {
+                id,
+                method: method.to_string(),
+                subnet: subnet.to_string(),
+                from: from.to_string(),
+                status: TransactionStatus::Pending,
+                submissions: vec![],
+                result: None,
+                error: None,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        while state.transactions.len() > MAX_TRACKED_TRANSACTIONS {
+            let oldest = *state.transactions.keys().next().unwrap();
+            state.transactions.remove(&oldest);
+        }
+
+        self.persist(&state)?;
+        Ok(id)
+    }
+
+    /// Records a message or transaction submitted by the operation.
+    pub fn add_submission(&self, id: u64, submission: String) -> Result<()> {
+        self.update(id, |t| t.submissions.push(submission))
+    }
+
+    /// Records the outcome of the operation.
+    pub fn finish(&self, id: u64, outcome: Result<serde_json::Value>) -> Result<()> {
+        self.update(id, |t| match outcome {
+            Ok(result) => {
+                t.status = TransactionStatus::Completed;
+                t.result = Some(result);
+            }
+            Err(e) => {
+                t.status = TransactionStatus::Failed;
+                t.error = Some(e.to_string());
+            }
+        })
+    }
+
+    pub fn get(&self, id: u64) -> Option<TrackedTransaction> {
+        let state = self.state.lock().unwrap();
+        state.transactions.get(&id).cloned()
+    }
+
+    /// The records matching the filters, the most recent first.
+    pub fn list(
+        &self,
+        subnet: Option<&SubnetID>,
+        status: Option<TransactionStatus>,
+        limit: usize,
+    ) -> Vec<TrackedTransaction> {
+        let subnet = subnet.map(|s| s.to_string());
+
+        let state = self.state.lock().unwrap();
+        state
+            .transactions
+            .values()
+            .rev()
+            .filter(|t| subnet.as_ref().map_or(true, |s| *s == t.subnet))
+            .filter(|t| status.map_or(true, |s| s == t.status))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn update<F: FnOnce(&mut TrackedTransaction)>(&self, id: u64, f: F) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let transaction = state
+            .transactions
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("transaction {id:} not tracked"))?;
+        f(transaction);
+        transaction.updated_at = now();
+        self.persist(&state)
+    }
+
+    fn persist(&self, state: &TrackerState) -> Result<()> {
+        let content = serde_json::to_string_pretty(&TrackerContent {
+            next_id: state.next_id,
+            transactions: state.transactions.values().cloned().collect(),
+        })?;
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracker::{TransactionStatus, TransactionTracker, TRANSACTIONS_FILE_NAME};
+    use anyhow::anyhow;
+    use fvm_shared::address::Address;
+    use ipc_sdk::subnet_id::SubnetID;
+
+    #[test]
+    fn test_tracker_persists_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(TRANSACTIONS_FILE_NAME);
+        let subnet = SubnetID::new_root(123);
+        let child = SubnetID::new(123, vec![Address::new_id(1001)]);
+        let from = Address::new_id(100);
+
+        let tracker = TransactionTracker::load(&path).unwrap();
+        let fund = tracker.track("ipc_fund", &subnet, &from).unwrap();
+        let release = tracker.track("ipc_release", &child, &from).unwrap();
+        let join = tracker.track("ipc_joinSubnet", &subnet, &from).unwrap();
+        assert_ne!(fund, release);
+
+        tracker.add_submission(fund, String::from("cid")).unwrap();
+        tracker.finish(fund, Ok(serde_json::json!(10))).unwrap();
+        tracker
+            .add_submission(release, String::from("hash"))
+            .unwrap();
+        tracker
+            .add_submission(release, String::from("replacement"))
+            .unwrap();
+        tracker
+            .finish(join, Err(anyhow!("not enough funds")))
+            .unwrap();
+        assert!(tracker.add_submission(100, String::from("cid")).is_err());
+
+        let tracker = TransactionTracker::load(&path).unwrap();
+        let t = tracker.get(fund).unwrap();
+        assert_eq!(t.status, TransactionStatus::Completed);
+        assert_eq!(t.submissions, vec![String::from("cid")]);
+        assert_eq!(t.result, Some(serde_json::json!(10)));
+
+        // the agent restarted while the release was waiting for its replacement
+        let t = tracker.get(release).unwrap();
+        assert_eq!(t.status, TransactionStatus::Interrupted);
+        assert_eq!(t.submissions.len(), 2);
+
+        let t = tracker.get(join).unwrap();
+        assert_eq!(t.status, TransactionStatus::Failed);
+        assert_eq!(t.error.as_deref(), Some("not enough funds"));
+
+        // the ids are not reused
+        let next = tracker.track("ipc_fund", &subnet, &from).unwrap();
+        assert!(next > join);
+
+        let listed = tracker.list(Some(&subnet), None, 10);
+        assert_eq!(
+            listed.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![next, join, fund]
+        );
+        let listed = tracker.list(None, Some(TransactionStatus::Failed), 10);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(tracker.list(None, None, 2).len(), 2);
+    }
+}